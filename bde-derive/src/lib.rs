@@ -0,0 +1,454 @@
+//! Derive macro for [`bde`](https://docs.rs/bde) emitting struct fields in canonical key order.
+//!
+//! `#[derive(BencodeSerialize)]` generates a `serde::Serialize` impl like `#[derive(Serialize)]` does,
+//! except that fields are serialized sorted by the raw bytes of their keys, as bencode requires.
+//! Serializers then don't have to sort them at runtime, so `bde::UnsortedSerializer` produces canonical output with no buffering.
+//!
+//! Field keys honor the `rename`, `rename_all`, `skip`, `skip_serializing`, `skip_serializing_if` and `flatten` serde attributes,
+//! so the same struct can still derive `Deserialize`:
+//!
+//! ```
+//! use bde_derive::BencodeSerialize;
+//! use serde_derive::Deserialize;
+//!
+//! #[derive(BencodeSerialize, Deserialize)]
+//! #[serde(rename_all = "kebab-case")]
+//! struct Info {
+//!     piece_length: u64,
+//!     #[serde(skip_serializing_if = "Option::is_none")]
+//!     private: Option<u8>,
+//!     #[serde(rename = "name", alias = "title")]
+//!     file_name: String,
+//! }
+//!
+//! let info = Info {
+//!     piece_length: 262144,
+//!     private: None,
+//!     file_name: "spam.iso".to_owned(),
+//! };
+//!
+//! // Sorted without any help from the serializer
+//! assert_eq!(
+//!     b"d4:name8:spam.iso12:piece-lengthi262144ee",
+//!     &bde::to_bytes_unsorted(&info).unwrap()[..]
+//! );
+//! ```
+//!
+//! Attributes changing how a field is serialized, like `with` or `serialize_with`, aren't supported and fail to compile,
+//! attributes only used by `Deserialize`, like `alias` above, are ignored.
+//!
+//! Flattened fields must serialize as dictionaries, they are converted into a `bde::Value` at runtime and merged with the other fields,
+//! so using `flatten` requires a dependency on `bde`. A flattened `Option` contributes no entries when it is `None`,
+//! as long as its type is spelled `Option<T>`.
+
+use proc_macro::TokenStream;
+use proc_macro2::{Span, TokenStream as TokenStream2};
+use quote::quote;
+use syn::{
+    parse_macro_input, parse_quote, Data, DeriveInput, Error, Expr, ExprPath, Fields, Ident,
+    LitStr, Result, Token, Type,
+};
+
+/// Derives `serde::Serialize` writing fields sorted by key, see the [crate] documentation.
+#[proc_macro_derive(BencodeSerialize, attributes(serde))]
+pub fn derive_bencode_serialize(input: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(input as DeriveInput);
+
+    expand(input)
+        .unwrap_or_else(Error::into_compile_error)
+        .into()
+}
+
+/// A field to serialize, in the order of the generated code
+struct Field {
+    member: Ident,
+    /// Key of the entry, `None` for flattened fields
+    key: Option<String>,
+    skip_serializing_if: Option<ExprPath>,
+    /// Whether the field is an `Option`, which contributes no entries when flattened and `None`
+    optional: bool,
+}
+
+/// How field names are turned into keys
+#[derive(Clone, Copy)]
+enum RenameRule {
+    None,
+    Lower,
+    Upper,
+    Pascal,
+    Camel,
+    Snake,
+    ScreamingSnake,
+    Kebab,
+    ScreamingKebab,
+}
+
+impl RenameRule {
+    fn parse(rule: &LitStr) -> Result<Self> {
+        Ok(match rule.value().as_str() {
+            "lowercase" => Self::Lower,
+            "UPPERCASE" => Self::Upper,
+            "PascalCase" => Self::Pascal,
+            "camelCase" => Self::Camel,
+            "snake_case" => Self::Snake,
+            "SCREAMING_SNAKE_CASE" => Self::ScreamingSnake,
+            "kebab-case" => Self::Kebab,
+            "SCREAMING-KEBAB-CASE" => Self::ScreamingKebab,
+            _ => return Err(Error::new(rule.span(), "unknown rename rule")),
+        })
+    }
+
+    /// Renames a snake_case field name, exactly like serde does
+    fn apply(self, field: &str) -> String {
+        match self {
+            Self::None | Self::Lower | Self::Snake => field.to_owned(),
+            Self::Upper | Self::ScreamingSnake => field.to_ascii_uppercase(),
+            Self::Pascal => {
+                let mut pascal = String::new();
+                let mut capitalize = true;
+                for c in field.chars() {
+                    if c == '_' {
+                        capitalize = true;
+                    } else if capitalize {
+                        pascal.push(c.to_ascii_uppercase());
+                        capitalize = false;
+                    } else {
+                        pascal.push(c);
+                    }
+                }
+                pascal
+            }
+            Self::Camel => {
+                let pascal = Self::Pascal.apply(field);
+                let mut chars = pascal.chars();
+                chars.next().map_or_else(String::new, |first| {
+                    first.to_ascii_lowercase().to_string() + chars.as_str()
+                })
+            }
+            Self::Kebab => field.replace('_', "-"),
+            Self::ScreamingKebab => field.to_ascii_uppercase().replace('_', "-"),
+        }
+    }
+}
+
+fn expand(input: DeriveInput) -> Result<TokenStream2> {
+    let Data::Struct(data) = &input.data else {
+        return Err(Error::new(
+            Span::call_site(),
+            "BencodeSerialize only supports structs with named fields",
+        ));
+    };
+    let Fields::Named(named) = &data.fields else {
+        return Err(Error::new(
+            Span::call_site(),
+            "BencodeSerialize only supports structs with named fields",
+        ));
+    };
+
+    let rename_rule = container_rename_rule(&input)?;
+
+    let mut fields = Vec::new();
+    for field in &named.named {
+        let member = field.ident.clone().expect("named field");
+        let name = member.to_string();
+        let name = name.strip_prefix("r#").unwrap_or(&name);
+
+        let mut key = Some(rename_rule.apply(name));
+        let mut skip = false;
+        let mut flatten = false;
+        let mut skip_serializing_if = None;
+
+        for attr in field
+            .attrs
+            .iter()
+            .filter(|attr| attr.path().is_ident("serde"))
+        {
+            attr.parse_nested_meta(|meta| {
+                if meta.path.is_ident("rename") {
+                    if let Some(rename) = parse_serialize_name(&meta)? {
+                        key = Some(rename.value());
+                    }
+                } else if meta.path.is_ident("skip") || meta.path.is_ident("skip_serializing") {
+                    skip = true;
+                } else if meta.path.is_ident("skip_serializing_if") {
+                    let path: LitStr = meta.value()?.parse()?;
+                    skip_serializing_if = Some(path.parse()?);
+                } else if meta.path.is_ident("flatten") {
+                    flatten = true;
+                } else if ["with", "serialize_with", "getter"]
+                    .iter()
+                    .any(|ident| meta.path.is_ident(ident))
+                {
+                    return Err(meta.error("not supported by BencodeSerialize"));
+                } else {
+                    skip_meta(&meta)?;
+                }
+                Ok(())
+            })?;
+        }
+
+        if flatten {
+            key = None;
+        }
+
+        if !skip {
+            fields.push(Field {
+                member,
+                key,
+                skip_serializing_if,
+                optional: is_option(&field.ty),
+            });
+        }
+    }
+
+    // Canonical order compares the raw bytes of keys, which is how `str` orders too
+    fields.sort_by(|a, b| a.key.cmp(&b.key));
+    for pair in fields.windows(2) {
+        if let (Some(a), Some(b)) = (&pair[0].key, &pair[1].key) {
+            if a == b {
+                return Err(Error::new(
+                    pair[1].member.span(),
+                    format!("duplicate key \"{b}\""),
+                ));
+            }
+        }
+    }
+
+    let ident = &input.ident;
+    let mut generics = input.generics.clone();
+    for param in generics.type_params_mut() {
+        param.bounds.push(parse_quote!(::serde::Serialize));
+    }
+    let (impl_generics, ty_generics, where_clause) = generics.split_for_impl();
+
+    let body = if fields.iter().any(|field| field.key.is_none()) {
+        expand_flatten(&fields)
+    } else {
+        expand_struct(&ident.to_string(), &fields)
+    };
+
+    Ok(quote! {
+        #[automatically_derived]
+        impl #impl_generics ::serde::Serialize for #ident #ty_generics #where_clause {
+            fn serialize<__S>(&self, __serializer: __S) -> ::core::result::Result<__S::Ok, __S::Error>
+            where
+                __S: ::serde::Serializer,
+            {
+                #body
+            }
+        }
+    })
+}
+
+/// Serializes a struct without flattened fields, keys are already sorted
+fn expand_struct(name: &str, fields: &[Field]) -> TokenStream2 {
+    let len = fields.iter().map(|field| {
+        let member = &field.member;
+        match &field.skip_serializing_if {
+            Some(path) => quote!(if #path(&self.#member) { 0 } else { 1 }),
+            None => quote!(1),
+        }
+    });
+
+    let entries = fields.iter().map(|field| {
+        let member = &field.member;
+        let key = field.key.as_deref().unwrap_or_default();
+        let serialize = quote! {
+            ::serde::ser::SerializeStruct::serialize_field(&mut __state, #key, &self.#member)?;
+        };
+
+        match &field.skip_serializing_if {
+            Some(path) => quote! {
+                if #path(&self.#member) {
+                    ::serde::ser::SerializeStruct::skip_field(&mut __state, #key)?;
+                } else {
+                    #serialize
+                }
+            },
+            None => serialize,
+        }
+    });
+
+    quote! {
+        let mut __state = ::serde::Serializer::serialize_struct(
+            __serializer,
+            #name,
+            0 #(+ #len)*,
+        )?;
+        #(#entries)*
+        ::serde::ser::SerializeStruct::end(__state)
+    }
+}
+
+/// Serializes a struct with flattened fields as a map, merging their sorted entries with the other fields
+fn expand_flatten(fields: &[Field]) -> TokenStream2 {
+    let flattened = fields
+        .iter()
+        .filter(|field| field.key.is_none())
+        .map(|field| {
+            let member = &field.member;
+            let value = if field.optional {
+                quote!(__value)
+            } else {
+                quote!(&self.#member)
+            };
+            let merge = quote! {
+                // `Value` can't be moved out of, its dictionary is taken instead
+                match &mut ::bde::to_value(#value).map_err(::serde::ser::Error::custom)? {
+                    ::bde::Value::Dictionary(__dictionary) => {
+                        for (__key, __value) in ::core::mem::take(__dictionary) {
+                            if __flattened.contains_key(&__key) {
+                                return ::core::result::Result::Err(::serde::ser::Error::custom(
+                                    ::core::format_args!("duplicate key {:?}", __key),
+                                ));
+                            }
+                            __flattened.insert(__key, __value);
+                        }
+                    }
+                    _ => {
+                        return ::core::result::Result::Err(::serde::ser::Error::custom(
+                            "flattened fields must serialize as dictionaries",
+                        ))
+                    }
+                }
+            };
+
+            let merge = if field.optional {
+                quote! {
+                    if let ::core::option::Option::Some(__value) = &self.#member {
+                        #merge
+                    }
+                }
+            } else {
+                merge
+            };
+
+            match &field.skip_serializing_if {
+                Some(path) => quote!(if !#path(&self.#member) { #merge }),
+                None => merge,
+            }
+        });
+
+    let entries = fields.iter().filter_map(|field| {
+        let member = &field.member;
+        let key = field.key.as_deref()?;
+
+        // Flattened entries sorted before this key come first
+        let entry = quote! {
+            while let ::core::option::Option::Some((__key, _)) = __rest.peek() {
+                match ::core::borrow::Borrow::<[u8]>::borrow(__key).cmp(#key.as_bytes()) {
+                    ::core::cmp::Ordering::Less => {
+                        if let ::core::option::Option::Some((__key, __value)) = __rest.next() {
+                            ::serde::ser::SerializeMap::serialize_entry(&mut __map, &__key, &__value)?;
+                        }
+                    }
+                    ::core::cmp::Ordering::Equal => {
+                        return ::core::result::Result::Err(::serde::ser::Error::custom(
+                            ::core::concat!("duplicate key \"", #key, "\""),
+                        ));
+                    }
+                    ::core::cmp::Ordering::Greater => break,
+                }
+            }
+        };
+
+        let serialize = quote! {
+            ::serde::ser::SerializeMap::serialize_entry(&mut __map, #key, &self.#member)?;
+        };
+
+        Some(match &field.skip_serializing_if {
+            Some(path) => quote!(if !#path(&self.#member) { #entry #serialize }),
+            None => quote!(#entry #serialize),
+        })
+    });
+
+    quote! {
+        let mut __flattened = ::bde::Dictionary::new();
+        #(#flattened)*
+
+        let mut __map = ::serde::Serializer::serialize_map(__serializer, ::core::option::Option::None)?;
+        let mut __rest = __flattened.into_iter().peekable();
+        #(#entries)*
+        for (__key, __value) in __rest {
+            ::serde::ser::SerializeMap::serialize_entry(&mut __map, &__key, &__value)?;
+        }
+        ::serde::ser::SerializeMap::end(__map)
+    }
+}
+
+/// Reads `rename_all` from the attributes of the struct
+fn container_rename_rule(input: &DeriveInput) -> Result<RenameRule> {
+    let mut rule = RenameRule::None;
+
+    for attr in input
+        .attrs
+        .iter()
+        .filter(|attr| attr.path().is_ident("serde"))
+    {
+        attr.parse_nested_meta(|meta| {
+            if meta.path.is_ident("rename_all") {
+                if let Some(rename) = parse_serialize_name(&meta)? {
+                    rule = RenameRule::parse(&rename)?;
+                }
+            } else if [
+                "transparent",
+                "into",
+                "tag",
+                "content",
+                "untagged",
+                "remote",
+            ]
+            .iter()
+            .any(|ident| meta.path.is_ident(ident))
+            {
+                return Err(meta.error("not supported by BencodeSerialize"));
+            } else {
+                skip_meta(&meta)?;
+            }
+            Ok(())
+        })?;
+    }
+
+    Ok(rule)
+}
+
+/// Parses `rename = "..."` or the serialize half of `rename(serialize = "...", deserialize = "...")`
+fn parse_serialize_name(meta: &syn::meta::ParseNestedMeta) -> Result<Option<LitStr>> {
+    if meta.input.peek(Token![=]) {
+        return Ok(Some(meta.value()?.parse()?));
+    }
+
+    let mut name = None;
+    meta.parse_nested_meta(|meta| {
+        if meta.path.is_ident("serialize") {
+            name = Some(meta.value()?.parse()?);
+        } else {
+            skip_meta(&meta)?;
+        }
+        Ok(())
+    })?;
+    Ok(name)
+}
+
+/// Skips an attribute only used by `Deserialize`, whatever its arguments
+fn skip_meta(meta: &syn::meta::ParseNestedMeta) -> Result<()> {
+    if meta.input.peek(Token![=]) {
+        meta.value()?.parse::<Expr>()?;
+    } else if meta.input.peek(syn::token::Paren) {
+        meta.parse_nested_meta(|meta| skip_meta(&meta))?;
+    }
+    Ok(())
+}
+
+/// Whether a type is spelled as an `Option`, like `Option<T>` or `core::option::Option<T>`
+fn is_option(ty: &Type) -> bool {
+    match ty {
+        Type::Path(path) if path.qself.is_none() => path
+            .path
+            .segments
+            .last()
+            .is_some_and(|segment| segment.ident == "Option"),
+        Type::Group(group) => is_option(&group.elem),
+        _ => false,
+    }
+}
@@ -0,0 +1,297 @@
+use std::collections::HashMap;
+
+use bde::{ser::KeyOrder, SerializerOptions};
+use bde_derive::BencodeSerialize;
+use serde::ser::Serialize;
+use serde_derive::{Deserialize, Serialize};
+
+/// Serializes without sorting, failing if keys aren't already sorted
+fn to_bytes_verified<T: Serialize>(value: &T) -> bde::Result<Vec<u8>> {
+    SerializerOptions::new()
+        .key_order(KeyOrder::Verify)
+        .to_bytes(value)
+}
+
+#[derive(BencodeSerialize, Deserialize, Debug, PartialEq)]
+struct Peer {
+    port: u16,
+    #[serde(rename = "peer id")]
+    id: String,
+    ip: String,
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    r#type: Option<String>,
+    #[serde(skip)]
+    connected: bool,
+}
+
+#[test]
+fn fields_are_sorted() {
+    let peer = Peer {
+        port: 6881,
+        id: "-bde-".to_owned(),
+        ip: "10.0.0.1".to_owned(),
+        r#type: None,
+        connected: true,
+    };
+
+    let bytes = to_bytes_verified(&peer).unwrap();
+    assert_eq!(b"d2:ip8:10.0.0.17:peer id5:-bde-4:porti6881ee", &bytes[..]);
+    assert_eq!(bde::to_bytes(&peer).unwrap(), bytes);
+    assert_eq!(bde::to_bytes_unsorted(&peer).unwrap(), bytes);
+
+    let peer = Peer {
+        r#type: Some("seed".to_owned()),
+        connected: false,
+        ..peer
+    };
+    let bytes = to_bytes_verified(&peer).unwrap();
+    assert_eq!(
+        b"d2:ip8:10.0.0.17:peer id5:-bde-4:porti6881e4:type4:seede",
+        &bytes[..]
+    );
+    assert_eq!(peer, bde::from_bytes(&bytes).unwrap());
+}
+
+#[test]
+fn rename_all() {
+    #[derive(BencodeSerialize)]
+    #[serde(rename_all = "kebab-case", deny_unknown_fields)]
+    struct Info {
+        piece_length: u64,
+        name: &'static str,
+        #[serde(rename(serialize = "md5sum", deserialize = "md5"))]
+        md5_sum: &'static str,
+        file_size_in_bytes: u64,
+    }
+
+    let info = Info {
+        piece_length: 16,
+        name: "spam",
+        md5_sum: "eggs",
+        file_size_in_bytes: 4,
+    };
+
+    assert_eq!(
+        b"d18:file-size-in-bytesi4e6:md5sum4:eggs4:name4:spam12:piece-lengthi16ee",
+        &to_bytes_verified(&info).unwrap()[..]
+    );
+
+    #[derive(BencodeSerialize)]
+    #[serde(rename_all = "camelCase")]
+    struct Camel {
+        b_c: u8,
+        a_b_c: u8,
+        b: u8,
+    }
+
+    assert_eq!(
+        b"d3:aBCi2e1:bi3e2:bCi1ee",
+        &to_bytes_verified(&Camel {
+            b_c: 1,
+            a_b_c: 2,
+            b: 3
+        })
+        .unwrap()[..]
+    );
+}
+
+#[test]
+#[allow(non_snake_case)]
+fn rename_all_matches_serde() {
+    /// Serializes a struct deriving `BencodeSerialize` and its twin deriving serde's `Serialize`
+    fn assert_same<B: Serialize, S: Serialize>(bencode: &B, serde: &S) {
+        assert_eq!(
+            bde::to_bytes(serde).unwrap(),
+            to_bytes_verified(bencode).unwrap()
+        );
+    }
+
+    #[derive(BencodeSerialize)]
+    #[serde(rename_all = "lowercase")]
+    struct LowerBencode {
+        _foo: u8,
+        bar_baz: u8,
+        Qux: u8,
+    }
+
+    #[derive(Serialize)]
+    #[serde(rename_all = "lowercase")]
+    struct LowerSerde {
+        _foo: u8,
+        bar_baz: u8,
+        Qux: u8,
+    }
+
+    assert_same(
+        &LowerBencode {
+            _foo: 1,
+            bar_baz: 2,
+            Qux: 3,
+        },
+        &LowerSerde {
+            _foo: 1,
+            bar_baz: 2,
+            Qux: 3,
+        },
+    );
+
+    #[derive(BencodeSerialize)]
+    #[serde(rename_all = "camelCase")]
+    struct CamelBencode {
+        _foo: u8,
+        bar_baz: u8,
+        qux__quux_: u8,
+    }
+
+    #[derive(Serialize)]
+    #[serde(rename_all = "camelCase")]
+    struct CamelSerde {
+        _foo: u8,
+        bar_baz: u8,
+        qux__quux_: u8,
+    }
+
+    assert_same(
+        &CamelBencode {
+            _foo: 1,
+            bar_baz: 2,
+            qux__quux_: 3,
+        },
+        &CamelSerde {
+            _foo: 1,
+            bar_baz: 2,
+            qux__quux_: 3,
+        },
+    );
+    assert_eq!(
+        b"d6:barBazi2e3:fooi1e7:quxQuuxi3ee",
+        &bde::to_bytes(&CamelSerde {
+            _foo: 1,
+            bar_baz: 2,
+            qux__quux_: 3,
+        })
+        .unwrap()[..]
+    );
+
+    #[derive(BencodeSerialize)]
+    #[serde(rename_all = "PascalCase")]
+    struct PascalBencode {
+        _foo: u8,
+        bar_baz: u8,
+        qux__quux_: u8,
+    }
+
+    #[derive(Serialize)]
+    #[serde(rename_all = "PascalCase")]
+    struct PascalSerde {
+        _foo: u8,
+        bar_baz: u8,
+        qux__quux_: u8,
+    }
+
+    assert_same(
+        &PascalBencode {
+            _foo: 1,
+            bar_baz: 2,
+            qux__quux_: 3,
+        },
+        &PascalSerde {
+            _foo: 1,
+            bar_baz: 2,
+            qux__quux_: 3,
+        },
+    );
+}
+
+#[test]
+fn flatten() {
+    #[derive(Serialize)]
+    struct Extension {
+        m: u8,
+        v: &'static str,
+    }
+
+    #[derive(BencodeSerialize)]
+    struct Handshake {
+        p: u16,
+        #[serde(flatten)]
+        extension: Extension,
+        e: u8,
+        #[serde(flatten, skip_serializing_if = "HashMap::is_empty")]
+        extra: HashMap<&'static str, u64>,
+    }
+
+    let mut handshake = Handshake {
+        p: 6881,
+        extension: Extension { m: 1, v: "bde" },
+        e: 0,
+        extra: HashMap::from([("z", 1), ("a", 2), ("n", 3)]),
+    };
+
+    assert_eq!(
+        b"d1:ai2e1:ei0e1:mi1e1:ni3e1:pi6881e1:v3:bde1:zi1ee",
+        &to_bytes_verified(&handshake).unwrap()[..]
+    );
+
+    handshake.extra.clear();
+    assert_eq!(
+        b"d1:ei0e1:mi1e1:pi6881e1:v3:bdee",
+        &to_bytes_verified(&handshake).unwrap()[..]
+    );
+
+    handshake.extra.insert("p", 1);
+    assert!(to_bytes_verified(&handshake).is_err());
+
+    handshake.extra.clear();
+    handshake.extra.insert("m", 1);
+    assert!(to_bytes_verified(&handshake).is_err());
+}
+
+#[test]
+fn flatten_option() {
+    #[derive(BencodeSerialize)]
+    struct Handshake {
+        p: u16,
+        #[serde(flatten)]
+        extension: Option<HashMap<&'static str, u64>>,
+    }
+
+    let mut handshake = Handshake {
+        p: 6881,
+        extension: None,
+    };
+    assert_eq!(b"d1:pi6881ee", &to_bytes_verified(&handshake).unwrap()[..]);
+
+    handshake.extension = Some(HashMap::new());
+    assert_eq!(b"d1:pi6881ee", &to_bytes_verified(&handshake).unwrap()[..]);
+
+    handshake.extension = Some(HashMap::from([("m", 1), ("v", 2)]));
+    assert_eq!(
+        b"d1:mi1e1:pi6881e1:vi2ee",
+        &to_bytes_verified(&handshake).unwrap()[..]
+    );
+}
+
+#[test]
+fn generics() {
+    #[derive(BencodeSerialize)]
+    struct Response<'a, T> {
+        y: &'a str,
+        r: T,
+    }
+
+    #[derive(BencodeSerialize)]
+    struct Pong {
+        id: [u8; 2],
+    }
+
+    let response = Response {
+        y: "r",
+        r: Pong { id: [1, 2] },
+    };
+
+    assert_eq!(
+        b"d1:rd2:idli1ei2eee1:y1:re",
+        &to_bytes_verified(&response).unwrap()[..]
+    );
+}
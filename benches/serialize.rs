@@ -0,0 +1,99 @@
+use std::{fs, hint::black_box, io};
+
+use bde::{Serializer, UnsortedSerializer, Value};
+use criterion::{criterion_group, criterion_main, BenchmarkId, Criterion, Throughput};
+use serde::Serialize;
+
+fn torrents() -> Vec<(String, Value)> {
+    let mut torrents: Vec<_> = fs::read_dir("tests/torrents")
+        .unwrap()
+        .map(|entry| {
+            let path = entry.unwrap().path();
+            let name = path.file_stem().unwrap().to_string_lossy().into_owned();
+            let value = bde::from_bytes(&fs::read(&path).unwrap()).unwrap();
+            (name, value)
+        })
+        .collect();
+    torrents.sort_by(|a, b| a.0.cmp(&b.0));
+    torrents
+}
+
+/// A writer paying for every call, like a socket or an unbuffered file would
+struct PerCallWriter(Vec<u8>);
+
+impl io::Write for PerCallWriter {
+    #[inline(never)]
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.0.extend_from_slice(black_box(buf));
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+fn to_bytes(c: &mut Criterion) {
+    let mut group = c.benchmark_group("to_bytes");
+
+    for (name, value) in torrents() {
+        group.throughput(Throughput::Bytes(value.encoded_len() as u64));
+        group.bench_with_input(BenchmarkId::new("sorted", &name), &value, |b, value| {
+            b.iter(|| bde::to_bytes(value).unwrap())
+        });
+        group.bench_with_input(BenchmarkId::new("unsorted", &name), &value, |b, value| {
+            b.iter(|| bde::to_bytes_unsorted(value).unwrap())
+        });
+        group.bench_with_input(
+            BenchmarkId::new("encoded_len", &name),
+            &value,
+            |b, value| b.iter(|| bde::encoded_len(value).unwrap()),
+        );
+    }
+
+    group.finish();
+}
+
+fn to_writer(c: &mut Criterion) {
+    let mut group = c.benchmark_group("to_writer");
+
+    for (name, value) in torrents() {
+        group.throughput(Throughput::Bytes(value.encoded_len() as u64));
+
+        for (label, buffer_size) in [("buffered", 8 * 1024), ("unbuffered", 0)] {
+            group.bench_with_input(
+                BenchmarkId::new(format!("sorted/{label}"), &name),
+                &value,
+                |b, value| {
+                    let mut writer = PerCallWriter(Vec::new());
+                    b.iter(|| {
+                        writer.0.clear();
+                        value
+                            .serialize(&mut Serializer::new(&mut writer).buffer_size(buffer_size))
+                            .unwrap();
+                    })
+                },
+            );
+            group.bench_with_input(
+                BenchmarkId::new(format!("unsorted/{label}"), &name),
+                &value,
+                |b, value| {
+                    let mut writer = PerCallWriter(Vec::new());
+                    b.iter(|| {
+                        writer.0.clear();
+                        value
+                            .serialize(
+                                &mut UnsortedSerializer::new(&mut writer).buffer_size(buffer_size),
+                            )
+                            .unwrap();
+                    })
+                },
+            );
+        }
+    }
+
+    group.finish();
+}
+
+criterion_group!(benches, to_bytes, to_writer);
+criterion_main!(benches);
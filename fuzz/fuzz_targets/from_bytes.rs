@@ -1,7 +1,21 @@
 #![no_main]
-use bde::{from_bytes, Value};
+use bde::{from_bytes, BorrowedValue, Value};
 use libfuzzer_sys::fuzz_target;
+use serde::de::IgnoredAny;
 
 fuzz_target!(|data: &[u8]| {
-    let _ = from_bytes::<Value>(data);
+    let value = from_bytes::<Value>(data).map(|_| ());
+    let visited = from_bytes::<BorrowedValue>(data).map(|_| ());
+    let skipped = from_bytes::<IgnoredAny>(data).map(|_| ());
+
+    // Skipping a value must accept and reject exactly the same inputs as decoding it
+    let skipped = skipped.map_err(|error| error.to_string());
+    assert_eq!(value.map_err(|error| error.to_string()), skipped);
+    assert_eq!(visited.map_err(|error| error.to_string()), skipped);
+
+    // The SWAR fast path must parse numbers exactly like the scalar path, errors included
+    bde::de::assert_fast_numbers_match_scalar(data);
+
+    // The structural scanner must skip exactly the values the scalar path accepts
+    bde::de::assert_fast_skip_matches_scalar(data);
 });
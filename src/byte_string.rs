@@ -20,20 +20,18 @@ of the Software.
 */
 
 use core::{
+    borrow::Borrow,
     cmp::{self, Ordering},
+    convert::Infallible,
     fmt::{self, Debug},
     hash::{Hash, Hasher},
-    ops::{Deref, DerefMut},
-};
-use std::{
-    convert::Infallible,
-    ops::{Index, IndexMut},
+    ops::{Deref, DerefMut, Index, IndexMut},
     slice::SliceIndex,
     str::FromStr,
-    string::String,
-    vec::Vec,
 };
 
+use alloc::{string::String, vec::Vec};
+
 use serde::{
     de::{Deserialize, Deserializer, Error, SeqAccess, Visitor},
     ser::{Serialize, Serializer},
@@ -81,12 +79,25 @@ impl<T: Into<Vec<u8>>> From<T> for ByteString {
     }
 }
 
+#[cfg(feature = "bytes")]
+impl From<ByteString> for bytes::Bytes {
+    fn from(byte_string: ByteString) -> Self {
+        Self::from(byte_string.inner)
+    }
+}
+
 impl AsRef<[u8]> for ByteString {
     fn as_ref(&self) -> &[u8] {
         &self.inner
     }
 }
 
+impl Borrow<[u8]> for ByteString {
+    fn borrow(&self) -> &[u8] {
+        &self.inner
+    }
+}
+
 impl AsMut<[u8]> for ByteString {
     fn as_mut(&mut self) -> &mut [u8] {
         &mut self.inner
@@ -189,7 +200,7 @@ impl Serialize for ByteString {
     }
 }
 
-struct ByteStringVisitor;
+pub(crate) struct ByteStringVisitor;
 
 impl<'de> Visitor<'de> for ByteStringVisitor {
     type Value = ByteString;
@@ -0,0 +1,510 @@
+use alloc::{collections::btree_map::Entry, vec::Vec};
+
+use crate::{ser::Write, ByteString, Dictionary, Encoder, Error, Integer, Result, Value};
+
+/// What to do with a key appearing more than once in the same dictionary.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum DuplicateKeys {
+    /// Fails with [`Error::DuplicateKey`].
+    #[default]
+    Reject,
+    /// Keeps the value of the first occurrence.
+    KeepFirst,
+    /// Keeps the value of the last occurrence.
+    KeepLast,
+}
+
+/// Settings of [`canonicalize`] and [`canonical_eq`].
+///
+/// ```
+/// use bde::{canonical::DuplicateKeys, CanonicalizeOptions};
+///
+/// let options = CanonicalizeOptions::new().duplicate_keys(DuplicateKeys::KeepLast);
+///
+/// let canonical = options.canonicalize(b"d4:spami007e3:eggi-0e4:spami1ee").unwrap();
+/// assert_eq!(b"d3:eggi0e4:spami1ee", &canonical.bytes[..]);
+/// assert!(!canonical.was_canonical);
+/// ```
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct CanonicalizeOptions {
+    duplicate_keys: DuplicateKeys,
+}
+
+/// Output of [`CanonicalizeOptions::canonicalize`].
+#[derive(Debug)]
+pub struct Canonical {
+    /// The input in canonical form.
+    pub bytes: Vec<u8>,
+    /// Whether the input was already in canonical form, byte for byte.
+    pub was_canonical: bool,
+}
+
+impl CanonicalizeOptions {
+    pub const fn new() -> Self {
+        Self {
+            duplicate_keys: DuplicateKeys::Reject,
+        }
+    }
+
+    /// Sets what to do with duplicate keys, see [`DuplicateKeys`].
+    pub const fn duplicate_keys(mut self, duplicate_keys: DuplicateKeys) -> Self {
+        self.duplicate_keys = duplicate_keys;
+        self
+    }
+
+    /// Rewrites bencode in canonical form, see [`canonicalize`].
+    ///
+    /// # Errors
+    /// Returns an error if the input isn't bencode, even leniently, or has duplicate keys that are rejected.
+    pub fn canonicalize(&self, bytes: &[u8]) -> Result<Canonical> {
+        let mut output = Vec::with_capacity(bytes.len());
+        let was_canonical = self.canonicalize_to_writer(bytes, &mut output)?;
+
+        Ok(Canonical {
+            bytes: output,
+            was_canonical,
+        })
+    }
+
+    /// Writes bencode in canonical form into a writer, returning whether the input was already canonical.
+    ///
+    /// # Errors
+    /// Returns an error if the input isn't bencode, even leniently, has duplicate keys that are rejected,
+    /// or the writer fails.
+    pub fn canonicalize_to_writer<W>(&self, bytes: &[u8], writer: W) -> Result<bool>
+    where
+        W: Write,
+    {
+        let value = self.parse(bytes)?;
+
+        let mut encoder = Encoder::new(Comparing {
+            writer,
+            input: bytes,
+            equal: true,
+        })
+        .checked(false);
+        emit(&mut encoder, &value)?;
+
+        let writer = encoder.finish()?;
+        Ok(writer.equal && writer.input.is_empty())
+    }
+
+    /// Compares two documents once in canonical form, see [`canonical_eq`].
+    ///
+    /// # Errors
+    /// Returns an error if either input can't be canonicalized.
+    pub fn canonical_eq(&self, a: &[u8], b: &[u8]) -> Result<bool> {
+        Ok(self.canonicalize(a)?.bytes == self.canonicalize(b)?.bytes)
+    }
+
+    /// Parses a value leniently, without recursion
+    fn parse(&self, bytes: &[u8]) -> Result<Value> {
+        let mut parser = Parser { bytes, index: 0 };
+        let mut stack: Vec<Frame> = Vec::new();
+
+        let value = loop {
+            // Inside a dictionary a key comes before every value
+            if let Some(Frame::Dictionary { pending_key, .. }) = stack.last_mut() {
+                if pending_key.is_none() {
+                    match parser.peek_byte()? {
+                        b'e' => {}
+                        b'0'..=b'9' => {
+                            *pending_key = Some(ByteString::from(parser.parse_byte_string()?));
+                            continue;
+                        }
+                        token => {
+                            return Err(Error::unexpected_token(
+                                "one of: 0-9, e",
+                                token,
+                                parser.index,
+                            ))
+                        }
+                    }
+                }
+            }
+
+            let value = match parser.peek_byte()? {
+                b'i' => Value::Integer(parser.parse_integer()?),
+                b'0'..=b'9' => Value::ByteString(ByteString::from(parser.parse_byte_string()?)),
+                b'l' => {
+                    parser.index += 1;
+                    stack.push(Frame::List(Vec::new()));
+                    continue;
+                }
+                b'd' => {
+                    parser.index += 1;
+                    stack.push(Frame::Dictionary {
+                        dictionary: Dictionary::new(),
+                        pending_key: None,
+                    });
+                    continue;
+                }
+                b'e' if stack.last().is_some_and(Frame::can_end) => {
+                    parser.index += 1;
+                    match stack.pop() {
+                        Some(Frame::List(list)) => Value::List(list),
+                        Some(Frame::Dictionary { dictionary, .. }) => Value::Dictionary(dictionary),
+                        None => unreachable!(),
+                    }
+                }
+                token => {
+                    return Err(Error::unexpected_token(
+                        "one of: i, 0-9, l, d",
+                        token,
+                        parser.index,
+                    ))
+                }
+            };
+
+            match stack.last_mut() {
+                None => break value,
+                Some(Frame::List(list)) => list.push(value),
+                Some(Frame::Dictionary {
+                    dictionary,
+                    pending_key,
+                }) => {
+                    let Some(key) = pending_key.take() else {
+                        unreachable!("keys are read before values")
+                    };
+
+                    match (dictionary.entry(key), self.duplicate_keys) {
+                        (Entry::Vacant(entry), _) => {
+                            entry.insert(value);
+                        }
+                        (Entry::Occupied(entry), DuplicateKeys::Reject) => {
+                            return Err(Error::DuplicateKey(entry.key().clone()))
+                        }
+                        (Entry::Occupied(_), DuplicateKeys::KeepFirst) => {}
+                        (Entry::Occupied(mut entry), DuplicateKeys::KeepLast) => {
+                            entry.insert(value);
+                        }
+                    }
+                }
+            }
+        };
+
+        if parser.index < bytes.len() {
+            return Err(Error::TrailingBytes);
+        }
+
+        Ok(value)
+    }
+}
+
+/// Rewrites bencode in canonical form.
+///
+/// The input is parsed leniently: dictionary keys may come in any order,
+/// and integers and byte string lengths may have leading zeros or be a negative zero.
+/// The output has keys sorted by their raw bytes and integers and lengths without leading zeros.
+/// Duplicate keys are rejected, use [`CanonicalizeOptions`] to keep one of their values instead
+/// or to find out whether the input was already canonical.
+///
+/// Integers must fit in an `i64` or `u64`.
+///
+/// ```
+/// assert_eq!(
+///     b"d3:agei42e4:name4:spame",
+///     &bde::canonicalize(b"d4:name04:spam3:agei0042ee").unwrap()[..]
+/// );
+/// ```
+///
+/// # Errors
+/// Returns an error if the input isn't bencode, even leniently, or has duplicate keys.
+pub fn canonicalize(bytes: &[u8]) -> Result<Vec<u8>> {
+    CanonicalizeOptions::new()
+        .canonicalize(bytes)
+        .map(|canonical| canonical.bytes)
+}
+
+/// Writes bencode in canonical form into a writer, returning whether the input was already canonical.
+///
+/// See [`canonicalize`].
+///
+/// # Errors
+/// Returns an error if the input isn't bencode, even leniently, has duplicate keys, or the writer fails.
+pub fn canonicalize_to_writer<W>(bytes: &[u8], writer: W) -> Result<bool>
+where
+    W: Write,
+{
+    CanonicalizeOptions::new().canonicalize_to_writer(bytes, writer)
+}
+
+/// Returns whether two documents are the same once in canonical form, see [`canonicalize`].
+///
+/// ```
+/// assert!(bde::canonical_eq(b"d1:bi1e1:ai01ee", b"d1:ai1e1:bi1ee").unwrap());
+/// assert!(!bde::canonical_eq(b"i1e", b"1:1").unwrap());
+/// ```
+///
+/// # Errors
+/// Returns an error if either input can't be canonicalized.
+pub fn canonical_eq(a: &[u8], b: &[u8]) -> Result<bool> {
+    CanonicalizeOptions::new().canonical_eq(a, b)
+}
+
+/// Writes a value without recursion, its dictionaries are already sorted
+fn emit<W>(encoder: &mut Encoder<W>, value: &Value) -> Result<()>
+where
+    W: Write,
+{
+    enum Pending<'a> {
+        Value(&'a Value),
+        Key(&'a [u8]),
+        End,
+    }
+
+    let mut stack = alloc::vec![Pending::Value(value)];
+
+    while let Some(pending) = stack.pop() {
+        match pending {
+            Pending::Value(Value::ByteString(byte_string)) => encoder.bytes(byte_string)?,
+            Pending::Value(Value::Integer(integer)) => match integer.as_u64() {
+                Some(n) => encoder.int(n)?,
+                None => encoder.int(integer.as_i64().unwrap_or_default())?,
+            },
+            Pending::Value(Value::List(list)) => {
+                encoder.begin_list()?;
+                stack.push(Pending::End);
+                stack.extend(list.iter().rev().map(Pending::Value));
+            }
+            Pending::Value(Value::Dictionary(dictionary)) => {
+                encoder.begin_dict()?;
+                stack.push(Pending::End);
+                for (key, value) in dictionary.iter().rev() {
+                    stack.push(Pending::Value(value));
+                    stack.push(Pending::Key(key));
+                }
+            }
+            Pending::Key(key) => encoder.key(key)?,
+            Pending::End => encoder.end()?,
+        }
+    }
+
+    Ok(())
+}
+
+/// A list or dictionary being parsed
+enum Frame {
+    List(Vec<Value>),
+    Dictionary {
+        dictionary: Dictionary,
+        /// Key read but still waiting for its value
+        pending_key: Option<ByteString>,
+    },
+}
+
+impl Frame {
+    fn can_end(&self) -> bool {
+        match self {
+            Self::List(_) => true,
+            Self::Dictionary { pending_key, .. } => pending_key.is_none(),
+        }
+    }
+}
+
+struct Parser<'a> {
+    bytes: &'a [u8],
+    index: usize,
+}
+
+impl<'a> Parser<'a> {
+    fn peek_byte(&self) -> Result<u8> {
+        self.bytes.get(self.index).copied().ok_or(Error::Eof)
+    }
+
+    /// Reads digits up to `end`, ignoring leading zeros
+    fn parse_digits(&mut self, end: u8) -> Result<&'a [u8]> {
+        let start = self.index;
+
+        loop {
+            match self.peek_byte()? {
+                b'0'..=b'9' => self.index += 1,
+                byte if byte == end && self.index > start => break,
+                token => {
+                    return Err(Error::unexpected_token(
+                        "number between 0-9",
+                        token,
+                        self.index,
+                    ))
+                }
+            }
+        }
+
+        let digits = &self.bytes[start..self.index];
+        self.index += 1;
+
+        let zeros = digits.iter().take_while(|&&digit| digit == b'0').count();
+        Ok(&digits[zeros.min(digits.len() - 1)..])
+    }
+
+    fn parse_integer(&mut self) -> Result<Integer> {
+        self.index += 1;
+
+        let negative = self.peek_byte()? == b'-';
+        if negative {
+            self.index += 1;
+        }
+
+        // Digits are ascii at this point
+        let digits = core::str::from_utf8(self.parse_digits(b'e')?).map_err(Error::from)?;
+
+        // Prefer i64 like the deserializer does, positive numbers that don't fit are still valid as u64
+        if negative {
+            digits
+                .parse::<u64>()
+                .ok()
+                .and_then(|n| 0i64.checked_sub_unsigned(n))
+                .map(Integer::from)
+                .ok_or(Error::OutOfBound)
+        } else {
+            let n = digits.parse::<u64>().map_err(|_| Error::OutOfBound)?;
+            Ok(i64::try_from(n).map_or(Integer::from(n), Integer::from))
+        }
+    }
+
+    fn parse_byte_string(&mut self) -> Result<&'a [u8]> {
+        let digits = core::str::from_utf8(self.parse_digits(b':')?).map_err(Error::from)?;
+        let len = digits.parse::<usize>().map_err(|_| Error::OutOfBound)?;
+
+        let end = self
+            .index
+            .checked_add(len)
+            .filter(|&end| end <= self.bytes.len())
+            .ok_or(Error::EofWhileParsingByteString)?;
+
+        let bytes = &self.bytes[self.index..end];
+        self.index = end;
+        Ok(bytes)
+    }
+}
+
+/// Writer comparing everything written to the input being canonicalized
+struct Comparing<'a, W> {
+    writer: W,
+    /// What is left of the input
+    input: &'a [u8],
+    equal: bool,
+}
+
+impl<W> Write for Comparing<'_, W>
+where
+    W: Write,
+{
+    fn write_all(&mut self, buf: &[u8]) -> Result<()> {
+        if self.equal {
+            match self.input.strip_prefix(buf) {
+                Some(rest) => self.input = rest,
+                None => self.equal = false,
+            }
+        }
+
+        self.writer.write_all(buf)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{canonical_eq, canonicalize, CanonicalizeOptions, DuplicateKeys};
+    use crate::Error;
+
+    #[test]
+    fn canonical_input_is_unchanged() {
+        for torrent in std::fs::read_dir("tests/torrents").unwrap() {
+            let bytes = std::fs::read(torrent.unwrap().path()).unwrap();
+            let canonical = CanonicalizeOptions::new().canonicalize(&bytes).unwrap();
+
+            assert!(canonical.was_canonical);
+            assert_eq!(bytes, canonical.bytes);
+        }
+
+        let canonical = CanonicalizeOptions::new().canonicalize(b"i0e").unwrap();
+        assert!(canonical.was_canonical);
+    }
+
+    #[test]
+    fn normalizes() {
+        let cases: [(&[u8], &[u8]); 8] = [
+            (b"i-0e", b"i0e"),
+            (b"i000e", b"i0e"),
+            (b"i-007e", b"i-7e"),
+            (b"i18446744073709551615e", b"i18446744073709551615e"),
+            (b"i-9223372036854775808e", b"i-9223372036854775808e"),
+            (b"004:spam", b"4:spam"),
+            (b"d1:bi1e1:ald1:d0:1:c0:eee", b"d1:ald1:c0:1:d0:ee1:bi1ee"),
+            (b"l0:lee", b"l0:lee"),
+        ];
+
+        for (input, expected) in cases {
+            let canonical = CanonicalizeOptions::new().canonicalize(input).unwrap();
+            assert_eq!(expected, &canonical.bytes[..]);
+            assert_eq!(input == expected, canonical.was_canonical);
+        }
+
+        let mut output = Vec::new();
+        assert!(!super::canonicalize_to_writer(b"d1:bi1e1:ai2ee", &mut output).unwrap());
+        assert_eq!(b"d1:ai2e1:bi1ee", &output[..]);
+    }
+
+    #[test]
+    fn duplicate_keys() {
+        let input = b"d1:ai1e1:bi2e1:ai3ee";
+
+        assert!(matches!(canonicalize(input), Err(Error::DuplicateKey(key)) if key == "a"));
+
+        let keep_first = CanonicalizeOptions::new().duplicate_keys(DuplicateKeys::KeepFirst);
+        assert_eq!(
+            b"d1:ai1e1:bi2ee",
+            &keep_first.canonicalize(input).unwrap().bytes[..]
+        );
+
+        let keep_last = CanonicalizeOptions::new().duplicate_keys(DuplicateKeys::KeepLast);
+        assert_eq!(
+            b"d1:ai3e1:bi2ee",
+            &keep_last.canonicalize(input).unwrap().bytes[..]
+        );
+    }
+
+    #[test]
+    fn invalid_input() {
+        let cases: [&[u8]; 12] = [
+            b"",
+            b"i1",
+            b"ie",
+            b"i-e",
+            b"i1-e",
+            b"i18446744073709551616e",
+            b"i-9223372036854775809e",
+            b"5:spam",
+            b"l",
+            b"di1ei2ee",
+            b"d1:ae",
+            b"i1ei2e",
+        ];
+
+        for input in cases {
+            assert!(canonicalize(input).is_err(), "{input:?}");
+        }
+
+        assert!(matches!(
+            canonicalize(b"e"),
+            Err(Error::UnexpectedToken { .. })
+        ));
+        assert!(matches!(canonicalize(b"i1ee"), Err(Error::TrailingBytes)));
+    }
+
+    #[test]
+    fn semantic_equality() {
+        assert!(canonical_eq(b"d1:bi1e1:ai01ee", b"d1:ai1e1:bi1ee").unwrap());
+        assert!(canonical_eq(b"i-0e", b"i0e").unwrap());
+        assert!(!canonical_eq(b"d1:ai1ee", b"d1:ai2ee").unwrap());
+        assert!(!canonical_eq(b"le", b"de").unwrap());
+        assert!(canonical_eq(b"i1e", b"i1").is_err());
+    }
+
+    #[test]
+    fn deep_nesting() {
+        let depth = 100_000;
+        let input = [vec![b'l'; depth], vec![b'e'; depth]].concat();
+        assert_eq!(input, canonicalize(&input).unwrap());
+    }
+}
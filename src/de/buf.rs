@@ -0,0 +1,74 @@
+use bytes::Buf;
+use serde::de::DeserializeOwned;
+
+use super::{from_bytes, io_read::IoRead, Deserializer};
+use crate::Result;
+
+/// Deserializes a single value from the front of a [`Buf`], leaving anything after it in the buffer.
+///
+/// Contiguous buffers such as [`BytesMut`](bytes::BytesMut) are deserialized in place and only advanced on success,
+/// so an incomplete message can be retried once more data arrives.
+/// Non-contiguous buffers such as chains have the value copied out of them first,
+/// in that case the buffer might be partially consumed on error.
+///
+/// # Errors
+/// See [`from_bytes`].
+pub fn from_buf<B, T>(buf: &mut B) -> Result<T>
+where
+    B: Buf,
+    T: DeserializeOwned,
+{
+    if buf.chunk().len() == buf.remaining() {
+        let mut deserializer = Deserializer::from_bytes(buf.chunk());
+        let value = T::deserialize(&mut deserializer)?;
+        let consumed = deserializer.index;
+        buf.advance(consumed);
+        Ok(value)
+    } else {
+        let mut buffer = Vec::new();
+        IoRead::new(buf.reader()).read_value_into(&mut buffer)?;
+        from_bytes(&buffer)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use bytes::{Buf, Bytes, BytesMut};
+
+    use super::from_buf;
+    use crate::{to_buf, ByteString, Error};
+
+    #[test]
+    fn contiguous() {
+        let mut buf = BytesMut::from(&b"li1ei2eei3e4:sp"[..]);
+
+        assert_eq!(vec![1, 2], from_buf::<_, Vec<u8>>(&mut buf).unwrap());
+        assert_eq!(3, from_buf::<_, u8>(&mut buf).unwrap());
+
+        // Incomplete values are left in the buffer
+        assert!(matches!(
+            from_buf::<_, String>(&mut buf),
+            Err(Error::EofWhileParsingByteString)
+        ));
+        buf.extend_from_slice(b"am");
+        assert_eq!("spam", from_buf::<_, String>(&mut buf).unwrap());
+        assert!(buf.is_empty());
+
+        to_buf(&mut buf, &ByteString::from("egg")).unwrap();
+        assert_eq!(b"3:egg", &buf[..]);
+        let byte_string: ByteString = from_buf(&mut buf).unwrap();
+        assert_eq!(Bytes::from_static(b"egg"), Bytes::from(byte_string.clone()));
+        assert_eq!(byte_string, ByteString::from(Bytes::from_static(b"egg")));
+    }
+
+    #[test]
+    fn chain() {
+        let mut buf = Bytes::from_static(b"d1:ali1ei").chain(Bytes::from_static(b"2eee1:b"));
+
+        assert_eq!(
+            std::collections::BTreeMap::from([("a".to_string(), vec![1u8, 2])]),
+            from_buf(&mut buf).unwrap()
+        );
+        assert_eq!(b"1:b", &buf.copy_to_bytes(buf.remaining())[..]);
+    }
+}
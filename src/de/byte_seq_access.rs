@@ -0,0 +1,75 @@
+use core::slice;
+use serde::de::{self, DeserializeSeed, SeqAccess, Visitor};
+
+use crate::{Error, Result};
+
+/// Visits the bytes of a byte string as a sequence of `u8`.
+///
+/// Elements can only be deserialized as `u8`, so a byte string isn't mistaken for a sequence of anything else.
+pub struct ByteSeqAccess<'a> {
+    bytes: slice::Iter<'a, u8>,
+}
+
+impl<'a> ByteSeqAccess<'a> {
+    pub fn new(bytes: &'a [u8]) -> Self {
+        Self {
+            bytes: bytes.iter(),
+        }
+    }
+
+    /// Number of bytes that weren't visited
+    pub fn remaining(&self) -> usize {
+        self.bytes.len()
+    }
+}
+
+impl<'de> SeqAccess<'de> for ByteSeqAccess<'_> {
+    type Error = Error;
+
+    fn next_element_seed<T>(&mut self, seed: T) -> Result<Option<T::Value>>
+    where
+        T: DeserializeSeed<'de>,
+    {
+        match self.bytes.next() {
+            Some(&byte) => seed.deserialize(ByteDeserializer(byte)).map(Some),
+            None => Ok(None),
+        }
+    }
+
+    fn size_hint(&self) -> Option<usize> {
+        Some(self.bytes.len())
+    }
+}
+
+/// Deserializes a single byte of a [`ByteSeqAccess`], failing with [`Error::InvalidType`] unless it's read as `u8`
+struct ByteDeserializer(u8);
+
+impl<'de> de::Deserializer<'de> for ByteDeserializer {
+    type Error = Error;
+
+    fn deserialize_any<V>(self, _visitor: V) -> Result<V::Value>
+    where
+        V: Visitor<'de>,
+    {
+        Err(Error::InvalidType)
+    }
+
+    fn deserialize_u8<V>(self, visitor: V) -> Result<V::Value>
+    where
+        V: Visitor<'de>,
+    {
+        visitor.visit_u8(self.0)
+    }
+
+    fn deserialize_ignored_any<V>(self, visitor: V) -> Result<V::Value>
+    where
+        V: Visitor<'de>,
+    {
+        visitor.visit_unit()
+    }
+
+    serde::forward_to_deserialize_any! {
+        bool i8 i16 i32 i64 i128 u16 u32 u64 u128 f32 f64 char str string bytes byte_buf option
+        unit unit_struct newtype_struct seq tuple tuple_struct map struct enum identifier
+    }
+}
@@ -0,0 +1,77 @@
+use std::io::{self, Read};
+
+use super::io_read::IoRead;
+use crate::Error;
+
+/// Reads the content of a byte string straight from the underlying reader.
+///
+/// Reading stops after exactly the declared length of the byte string.
+/// Dropping it early is fine, the unread bytes are skipped before decoding continues.
+///
+/// Only byte strings that are direct elements of a [`StreamList`](super::StreamList) or values of a
+/// [`StreamDictionary`](super::StreamDictionary) can be read this way. Byte strings nested deeper,
+/// like the `pieces` of a torrent's `info` dictionary, are part of an element that is buffered whole.
+pub struct ByteStringReader<'a, R> {
+    reader: &'a mut IoRead<R>,
+    remaining: u64,
+}
+
+impl<'a, R> ByteStringReader<'a, R>
+where
+    R: Read,
+{
+    pub(crate) fn new(reader: &'a mut IoRead<R>, len: usize) -> Self {
+        Self {
+            reader,
+            remaining: len as u64,
+        }
+    }
+
+    /// Returns the number of bytes that are left to read.
+    pub const fn remaining(&self) -> u64 {
+        self.remaining
+    }
+}
+
+impl<'a, R> Read for ByteStringReader<'a, R>
+where
+    R: Read,
+{
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        if self.remaining == 0 || buf.is_empty() {
+            return Ok(0);
+        }
+
+        let max = buf
+            .len()
+            .min(usize::try_from(self.remaining).unwrap_or(usize::MAX));
+        let read = self.reader.reader.read(&mut buf[..max])?;
+
+        if read == 0 {
+            return Err(io::Error::new(
+                io::ErrorKind::UnexpectedEof,
+                Error::EofWhileParsingByteString,
+            ));
+        }
+
+        self.remaining -= read as u64;
+        self.reader.index += read;
+
+        Ok(read)
+    }
+}
+
+impl<'a, R> Drop for ByteStringReader<'a, R> {
+    fn drop(&mut self) {
+        self.reader.skip = self.remaining;
+    }
+}
+
+/// Turns the errors raised by [`ByteStringReader`] back into an [`Error`]
+pub(crate) fn io_error_into_error(error: io::Error) -> Error {
+    if error.get_ref().is_some_and(|inner| inner.is::<Error>()) {
+        Error::EofWhileParsingByteString
+    } else {
+        Error::Io(error)
+    }
+}
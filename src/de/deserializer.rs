@@ -1,16 +1,14 @@
+use alloc::vec::Vec;
 use num_traits::{cast::AsPrimitive, NumCast, PrimInt, WrappingNeg};
-use serde::{
-    de::{self, DeserializeSeed, SeqAccess, Visitor},
-    serde_if_integer128,
-};
+use serde::de::{self, DeserializeSeed, SeqAccess, Visitor};
 
-use super::map_deserializer::MapDeserializer;
-use crate::{Error, Result};
+use super::{map_deserializer::MapDeserializer, scan, visit_byte_seq, Event};
+use crate::{value, ByteString, Dictionary, Error, Result, Value};
 
 #[derive(Clone)]
 pub struct Deserializer<'de> {
     pub(super) bytes: &'de [u8],
-    pub(super) index: usize,
+    pub(crate) index: usize,
 }
 
 impl<'de> Deserializer<'de> {
@@ -21,7 +19,7 @@ impl<'de> Deserializer<'de> {
 
     /// Returns the next byte and advances the internal buffer by one.
     /// Returns None if empty.
-    pub(super) fn next_byte(&mut self) -> Result<u8> {
+    pub(crate) fn next_byte(&mut self) -> Result<u8> {
         if let Some(byte) = self.bytes.get(self.index) {
             self.advance();
             Ok(*byte)
@@ -32,7 +30,7 @@ impl<'de> Deserializer<'de> {
 
     /// Look at the next byte without advancing the buffer.
     /// Returns None if empty.
-    pub(super) fn peek_byte(&mut self) -> Result<u8> {
+    pub(crate) fn peek_byte(&mut self) -> Result<u8> {
         if let Some(byte) = self.bytes.get(self.index) {
             Ok(*byte)
         } else {
@@ -65,6 +63,26 @@ impl<'de> Deserializer<'de> {
     {
         let mut significand = N::zero();
 
+        // Fast path: parse up to 8 digits at once, as long as they fit in N.
+        // Values that don't fit are left to the scalar loop so that it reports the error.
+        if let Some(chunk) = self.bytes.get(self.index..self.index + 8) {
+            let (value, count) = scan::parse_digits(chunk.try_into().unwrap_or_default());
+            if let Some(value) = N::from(value) {
+                significand = value;
+                self.index += count;
+            }
+        }
+
+        self.accumulate_digits(significand, negative, until)
+    }
+
+    /// Parses the remaining ascii numbers one byte at a time, starting from an already parsed significand
+    fn accumulate_digits<N>(&mut self, mut significand: N, negative: bool, until: u8) -> Result<N>
+    where
+        N: Copy + PrimInt + NumCast + WrappingNeg + 'static,
+        u8: AsPrimitive<N>,
+        i8: AsPrimitive<N>,
+    {
         loop {
             match self.next_byte()? {
                 integer @ b'0'..=b'9' => {
@@ -125,8 +143,18 @@ impl<'de> Deserializer<'de> {
         }
     }
 
+    /// Parses an integer that may be negative ignoring the leading "i" bytes
+    pub(super) fn parse_signed_integer(&mut self) -> Result<i64> {
+        if let b'-' = self.peek_byte()? {
+            self.advance();
+            self.parse_integer(true)
+        } else {
+            self.parse_integer(false)
+        }
+    }
+
     /// Parses a byte string
-    pub(super) fn parse_byte_string(&mut self) -> Result<&'de [u8]> {
+    pub(crate) fn parse_byte_string(&mut self) -> Result<&'de [u8]> {
         let len = self.next_ascii_number_until::<usize>(false, b':')?;
 
         if len == 0 {
@@ -145,9 +173,167 @@ impl<'de> Deserializer<'de> {
             Err(Error::OutOfBound)
         }
     }
+
+    /// Parses the next piece of a value, keeping track of the enclosing lists and dictionaries in `stack`.
+    ///
+    /// A value is complete once `stack` is empty again. Every piece is validated exactly like
+    /// [`deserialize_any`](de::Deserializer::deserialize_any) would, returning the same errors,
+    /// but nesting doesn't use the call stack.
+    pub(crate) fn next_event(&mut self, stack: &mut Vec<Frame<'de>>) -> Result<Event<'de>> {
+        match stack.last_mut() {
+            Some(Frame::List) => {
+                if let b'e' = self.peek_byte()? {
+                    self.advance();
+                    stack.pop();
+                    return Ok(Event::End);
+                }
+            }
+            // Inside a dictionary a key comes before every value
+            Some(Frame::Dictionary {
+                last_key,
+                value_next,
+            }) => {
+                if *value_next {
+                    *value_next = false;
+                } else {
+                    return match self.peek_byte()? {
+                        b'e' => {
+                            self.advance();
+                            stack.pop();
+                            Ok(Event::End)
+                        }
+                        b'0'..=b'9' => {
+                            let key = self.parse_byte_string()?;
+                            if matches!(last_key, Some(last_key) if *last_key > key) {
+                                return Err(Error::UnsortedKeys);
+                            }
+                            *last_key = Some(key);
+                            *value_next = true;
+                            Ok(Event::Key(key))
+                        }
+                        token => Err(Error::unexpected_token(
+                            "number between 0-9",
+                            token,
+                            self.index,
+                        )),
+                    };
+                }
+            }
+            None => {}
+        }
+
+        match self.peek_byte()? {
+            b'i' => {
+                self.advance();
+                self.parse_signed_integer().map(Event::Integer)
+            }
+            b'0'..=b'9' => self.parse_byte_string().map(Event::Bytes),
+            b'l' => {
+                self.advance();
+                stack.push(Frame::List);
+                Ok(Event::ListStart)
+            }
+            b'd' => {
+                self.advance();
+                stack.push(Frame::Dictionary {
+                    last_key: None,
+                    value_next: false,
+                });
+                Ok(Event::DictStart)
+            }
+            token => Err(Error::unexpected_token(
+                "one of: i, 0-9, l, d",
+                token,
+                self.index,
+            )),
+        }
+    }
+
+    /// Skips the next value without visiting it.
+    ///
+    /// Well formed values are stepped over by the structural scanner [`scan::skip`].
+    /// Anything it doesn't accept is walked again by the scalar path, which reports the error.
+    pub(crate) fn skip_value(&mut self) -> Result<()> {
+        if let Some(end) = scan::skip(self.bytes, self.index) {
+            self.index = end;
+            return Ok(());
+        }
+
+        self.skip_value_scalar()
+    }
+
+    /// Skips the next value one token at a time, without recursion
+    fn skip_value_scalar(&mut self) -> Result<()> {
+        let mut stack = Vec::new();
+
+        loop {
+            self.next_event(&mut stack)?;
+
+            if stack.is_empty() {
+                return Ok(());
+            }
+        }
+    }
+
+    /// Parses the next value into a [`Value`] without recursion.
+    pub(crate) fn parse_value(&mut self) -> Result<Value> {
+        enum Partial {
+            List(Vec<Value>),
+            Dictionary(Dictionary, Option<ByteString>),
+        }
+
+        let mut stack = Vec::new();
+        let mut partials = Vec::new();
+
+        loop {
+            let value = match self.next_event(&mut stack)? {
+                Event::Integer(integer) => Value::Integer(integer.into()),
+                Event::Bytes(bytes) => Value::ByteString(bytes.into()),
+                Event::ListStart => {
+                    partials.push(Partial::List(Vec::new()));
+                    continue;
+                }
+                Event::DictStart => {
+                    partials.push(Partial::Dictionary(Dictionary::new(), None));
+                    continue;
+                }
+                Event::Key(key) => {
+                    if let Some(Partial::Dictionary(_, pending_key)) = partials.last_mut() {
+                        *pending_key = Some(key.into());
+                    }
+                    continue;
+                }
+                Event::End => match partials.pop() {
+                    Some(Partial::List(list)) => Value::List(list),
+                    Some(Partial::Dictionary(dictionary, _)) => Value::Dictionary(dictionary),
+                    None => return Err(Error::Syntax),
+                },
+            };
+
+            match partials.last_mut() {
+                None => return Ok(value),
+                Some(Partial::List(list)) => list.push(value),
+                Some(Partial::Dictionary(dictionary, pending_key)) => {
+                    if let Some(key) = pending_key.take() {
+                        dictionary.insert(key, value);
+                    }
+                }
+            }
+        }
+    }
 }
 
-impl<'de, 'a> de::Deserializer<'de> for &'a mut Deserializer<'de> {
+/// A list or dictionary [`Deserializer::next_event`] is inside of
+pub(crate) enum Frame<'de> {
+    List,
+    Dictionary {
+        last_key: Option<&'de [u8]>,
+        /// Whether a key was just parsed
+        value_next: bool,
+    },
+}
+
+impl<'de> de::Deserializer<'de> for &mut Deserializer<'de> {
     type Error = Error;
 
     fn deserialize_any<V>(self, visitor: V) -> Result<V::Value>
@@ -230,13 +416,7 @@ impl<'de, 'a> de::Deserializer<'de> for &'a mut Deserializer<'de> {
         V: de::Visitor<'de>,
     {
         if let b'i' = self.next_byte()? {
-            match self.peek_byte()? {
-                b'-' => {
-                    self.advance();
-                    visitor.visit_i64(self.parse_integer(true)?)
-                }
-                _ => visitor.visit_i64(self.parse_integer(false)?),
-            }
+            visitor.visit_i64(self.parse_signed_integer()?)
         } else {
             Err(Error::InvalidType)
         }
@@ -286,34 +466,31 @@ impl<'de, 'a> de::Deserializer<'de> for &'a mut Deserializer<'de> {
         }
     }
 
-    serde_if_integer128! {
-
-        fn deserialize_u128<V>(self, visitor: V) -> Result<V::Value>
-        where
-            V: Visitor<'de>,
-        {
-            if let b'i' = self.next_byte()? {
-                visitor.visit_u128(self.parse_integer(false)?)
-            } else {
-                Err(Error::InvalidType)
-            }
+    fn deserialize_u128<V>(self, visitor: V) -> Result<V::Value>
+    where
+        V: Visitor<'de>,
+    {
+        if let b'i' = self.next_byte()? {
+            visitor.visit_u128(self.parse_integer(false)?)
+        } else {
+            Err(Error::InvalidType)
         }
+    }
 
-        fn deserialize_i128<V>(self, visitor: V) -> Result<V::Value>
-        where
-            V: de::Visitor<'de>,
-        {
-            if let b'i' = self.next_byte()? {
-                match self.peek_byte()? {
-                    b'-' => {
-                        self.advance();
-                        visitor.visit_i128(self.parse_integer(true)?)
-                    }
-                    _ => visitor.visit_i128(self.parse_integer(false)?),
+    fn deserialize_i128<V>(self, visitor: V) -> Result<V::Value>
+    where
+        V: de::Visitor<'de>,
+    {
+        if let b'i' = self.next_byte()? {
+            match self.peek_byte()? {
+                b'-' => {
+                    self.advance();
+                    visitor.visit_i128(self.parse_integer(true)?)
                 }
-            } else {
-                Err(Error::InvalidType)
+                _ => visitor.visit_i128(self.parse_integer(false)?),
             }
+        } else {
+            Err(Error::InvalidType)
         }
     }
 
@@ -356,7 +533,7 @@ impl<'de, 'a> de::Deserializer<'de> for &'a mut Deserializer<'de> {
     where
         V: Visitor<'de>,
     {
-        visitor.visit_bytes(self.parse_byte_string()?)
+        visitor.visit_borrowed_bytes(self.parse_byte_string()?)
     }
 
     fn deserialize_byte_buf<V>(self, visitor: V) -> Result<V::Value>
@@ -370,7 +547,8 @@ impl<'de, 'a> de::Deserializer<'de> for &'a mut Deserializer<'de> {
     where
         V: Visitor<'de>,
     {
-        visitor.visit_some(self) // TODO: Bencode doesn't really have a concept of missing value.
+        // Bencode has no null value. A missing struct field is turned into None by serde, so anything present is Some
+        visitor.visit_some(self)
     }
 
     fn deserialize_unit<V>(self, _visitor: V) -> Result<V::Value>
@@ -387,10 +565,17 @@ impl<'de, 'a> de::Deserializer<'de> for &'a mut Deserializer<'de> {
         Err(Error::Unsupported("()")) // TODO: Use the name to provide better errors
     }
 
-    fn deserialize_newtype_struct<V>(self, _name: &'static str, visitor: V) -> Result<V::Value>
+    fn deserialize_newtype_struct<V>(self, name: &'static str, visitor: V) -> Result<V::Value>
     where
         V: Visitor<'de>,
     {
+        if name == value::NAME {
+            // Nested values are checked and decoded without recursion
+            let start = self.index;
+            self.skip_value()?;
+            return visitor.visit_map(value::RawValue::new(&self.bytes[start..self.index]));
+        }
+
         visitor.visit_newtype_struct(self)
     }
 
@@ -398,17 +583,38 @@ impl<'de, 'a> de::Deserializer<'de> for &'a mut Deserializer<'de> {
     where
         V: Visitor<'de>,
     {
-        match self.next_byte()? {
-            b'l' => visitor.visit_seq(self),
-            token => Err(Error::unexpected_token("l", token, self.index)),
+        match self.peek_byte()? {
+            b'l' => {
+                self.advance();
+                visitor.visit_seq(self)
+            }
+            // Byte strings are valid sources for sequences of u8
+            b'0'..=b'9' => visit_byte_seq(self.parse_byte_string()?, visitor),
+            token => {
+                self.advance();
+                Err(Error::unexpected_token("l", token, self.index))
+            }
         }
     }
 
-    fn deserialize_tuple<V>(self, _len: usize, visitor: V) -> Result<V::Value>
+    fn deserialize_tuple<V>(self, len: usize, visitor: V) -> Result<V::Value>
     where
         V: Visitor<'de>,
     {
-        self.deserialize_seq(visitor)
+        if let b'0'..=b'9' = self.peek_byte()? {
+            let bytes = self.parse_byte_string()?;
+
+            if bytes.len() != len {
+                return Err(Error::ByteStringLengthMismatch {
+                    expected: len,
+                    found: bytes.len(),
+                });
+            }
+
+            visit_byte_seq(bytes, visitor)
+        } else {
+            self.deserialize_seq(visitor)
+        }
     }
 
     fn deserialize_tuple_struct<V>(
@@ -472,7 +678,8 @@ impl<'de, 'a> de::Deserializer<'de> for &'a mut Deserializer<'de> {
     where
         V: Visitor<'de>,
     {
-        self.deserialize_any(visitor)
+        self.skip_value()?;
+        visitor.visit_unit()
     }
 }
 
@@ -492,13 +699,259 @@ impl<'de> SeqAccess<'de> for Deserializer<'de> {
     }
 }
 
+/// Parses the number at the start of `bytes` as every integer type, with the SWAR fast path and with the scalar path alone,
+/// and panics if they don't return the same result or stop at the same offset.
+#[cfg(any(test, fuzzing))]
+#[doc(hidden)]
+pub fn assert_fast_numbers_match_scalar(bytes: &[u8]) {
+    fn assert_matches_scalar<N>(bytes: &[u8])
+    where
+        N: Copy + PrimInt + NumCast + WrappingNeg + core::fmt::Debug + 'static,
+        u8: AsPrimitive<N>,
+        i8: AsPrimitive<N>,
+    {
+        for (negative, until) in [(false, b'e'), (true, b'e'), (false, b':')] {
+            let mut fast = Deserializer::from_bytes(bytes);
+            let mut scalar = Deserializer::from_bytes(bytes);
+
+            let fast_result = fast.next_ascii_number_until::<N>(negative, until);
+            let scalar_result = scalar.accumulate_digits::<N>(N::zero(), negative, until);
+
+            assert_eq!(
+                alloc::format!("{scalar_result:?}"),
+                alloc::format!("{fast_result:?}"),
+                "{:?}",
+                alloc::string::String::from_utf8_lossy(bytes)
+            );
+            assert_eq!(scalar.index, fast.index);
+        }
+    }
+
+    assert_matches_scalar::<u8>(bytes);
+    assert_matches_scalar::<i8>(bytes);
+    assert_matches_scalar::<u16>(bytes);
+    assert_matches_scalar::<i32>(bytes);
+    assert_matches_scalar::<i64>(bytes);
+    assert_matches_scalar::<u64>(bytes);
+    assert_matches_scalar::<usize>(bytes);
+    assert_matches_scalar::<i128>(bytes);
+}
+
+/// Skips the value at the start of `bytes` with the structural scanner and with the scalar path alone,
+/// and panics if they don't return the same result or stop at the same offset,
+/// or if the scanner gives up on a value the scalar path accepts.
+#[cfg(any(test, fuzzing))]
+#[doc(hidden)]
+pub fn assert_fast_skip_matches_scalar(bytes: &[u8]) {
+    let mut fast = Deserializer::from_bytes(bytes);
+    let mut scalar = Deserializer::from_bytes(bytes);
+
+    let fast_result = fast.skip_value();
+    let scalar_result = scalar.skip_value_scalar();
+
+    assert_eq!(
+        alloc::format!("{scalar_result:?}"),
+        alloc::format!("{fast_result:?}"),
+        "{:?}",
+        alloc::string::String::from_utf8_lossy(bytes)
+    );
+    assert_eq!(scalar.index, fast.index);
+
+    // The scanner must not leave valid values to the scalar path either
+    assert_eq!(
+        scalar_result.ok().map(|()| scalar.index),
+        scan::skip(bytes, 0)
+    );
+}
+
 #[cfg(test)]
 mod tests {
 
-    use crate::from_bytes;
+    use serde::de::IgnoredAny;
+
+    use super::{assert_fast_numbers_match_scalar, assert_fast_skip_matches_scalar};
+    use crate::de::test_input::{corrupted, Xorshift};
+    use crate::{from_bytes, BorrowedValue, Error};
+
+    /// Every byte string made of digits followed by a terminator, plus some random noise
+    fn numbers() -> Vec<Vec<u8>> {
+        let mut numbers = Vec::new();
+        let mut rng = Xorshift::new(0x9E37_79B9_7F4A_7C15);
+
+        for len in 0..=24 {
+            for terminator in [&b"e"[..], b":", b"x", b""] {
+                for _ in 0..64 {
+                    let state = rng.next_u64();
+
+                    let mut number: Vec<u8> = (0..len)
+                        .map(|i| b'0' + (state.rotate_left(i * 5) % 10) as u8)
+                        .collect();
+                    number.extend_from_slice(terminator);
+                    // Extra bytes so the fast path kicks in even for short numbers
+                    number.extend_from_slice(&state.to_le_bytes()[..(state % 9) as usize]);
+                    numbers.push(number);
+                }
+            }
+        }
+
+        for number in [
+            "18446744073709551615e",
+            "18446744073709551616e",
+            "9223372036854775807e",
+            "9223372036854775808e",
+            "255e",
+            "256e",
+            "127e",
+            "128e",
+        ] {
+            numbers.push(number.as_bytes().to_vec());
+        }
+
+        numbers
+    }
+
+    #[test]
+    fn fast_numbers_match_scalar() {
+        for number in numbers() {
+            assert_fast_numbers_match_scalar(&number);
+        }
+    }
+
+    #[test]
+    fn skipping_matches_deserialize_any() {
+        let torrent = include_bytes!("../../tests/torrents/debian.torrent");
+
+        let mut inputs: Vec<Vec<u8>> = vec![
+            torrent.to_vec(),
+            b"d1:bi1e1:ai2ee".to_vec(),
+            b"d1:ai1ei2ei3ee".to_vec(),
+            b"li-0ee".to_vec(),
+            b"li01ee".to_vec(),
+            b"lx".to_vec(),
+            b"ll".to_vec(),
+        ];
+
+        // Corrupt and truncate the start of the torrent, where the structure is
+        inputs.extend(corrupted(&torrent[..400], 2000));
+
+        for input in inputs {
+            let skipped = from_bytes::<IgnoredAny>(&input)
+                .map(|_| ())
+                .map_err(|error| error.to_string());
+            // `Value` is decoded by skipping first, `BorrowedValue` is still visited
+            let visited = from_bytes::<BorrowedValue>(&input)
+                .map(|_| ())
+                .map_err(|error| error.to_string());
+            assert_eq!(visited, skipped, "{:?}", String::from_utf8_lossy(&input));
+        }
+    }
+
+    #[test]
+    fn fast_skip_matches_scalar() {
+        let torrent = include_bytes!("../../tests/torrents/debian.torrent");
+
+        let mut inputs: Vec<Vec<u8>> = vec![
+            torrent.to_vec(),
+            b"d1:ai1e1:ai2ee".to_vec(),
+            b"d1:bi1e1:ai2ee".to_vec(),
+            b"d1:ai1ei2ei3ee".to_vec(),
+            b"d1:ae".to_vec(),
+            b"d01:a0:e".to_vec(),
+            b"i0e".to_vec(),
+            b"i-0e".to_vec(),
+            b"i01e".to_vec(),
+            b"i-e".to_vec(),
+            b"ie".to_vec(),
+            b"i9223372036854775807e".to_vec(),
+            b"i-9223372036854775807e".to_vec(),
+            b"i9223372036854775808e".to_vec(),
+            b"i-9223372036854775808e".to_vec(),
+            b"i00000000000000000000001e".to_vec(),
+            b"00000000000000000004:spam".to_vec(),
+            b"18446744073709551616:".to_vec(),
+            b"5:spam".to_vec(),
+            b"l4:spami12345678ee".to_vec(),
+            b"lx".to_vec(),
+            b"le".to_vec(),
+            b"e".to_vec(),
+            b"".to_vec(),
+        ];
+        inputs.extend(corrupted(&torrent[..400], 2000));
+        inputs.extend(
+            numbers()
+                .into_iter()
+                .map(|number| [&b"i"[..], &number].concat()),
+        );
+        inputs.extend(numbers());
+
+        for input in inputs {
+            assert_fast_skip_matches_scalar(&input);
+        }
+    }
+
+    #[test]
+    fn skipping_deep_nesting() {
+        let depth = 100_000;
+        let mut bytes = vec![b'l'; depth];
+        bytes.resize(2 * depth, b'e');
+
+        assert!(from_bytes::<IgnoredAny>(&bytes).is_ok());
+    }
 
     #[test]
     fn zero_lenght_byte_string() {
         assert_eq!("", from_bytes::<&'static str>(b"0:").unwrap())
     }
+
+    #[test]
+    fn borrowed_byte_string() {
+        let bytes = b"4:spam";
+        let borrowed = from_bytes::<&[u8]>(bytes).unwrap();
+        assert_eq!(b"spam", borrowed);
+        assert!(bytes.as_ptr_range().contains(&borrowed.as_ptr()));
+    }
+
+    #[test]
+    fn byte_string_as_u8_sequence() {
+        assert_eq!(b"spam".to_vec(), from_bytes::<Vec<u8>>(b"4:spam").unwrap());
+        assert_eq!(vec![1u8, 2], from_bytes::<Vec<u8>>(b"li1ei2ee").unwrap());
+        assert_eq!(*b"spam", from_bytes::<[u8; 4]>(b"4:spam").unwrap());
+    }
+
+    #[test]
+    fn byte_string_is_only_a_u8_sequence() {
+        assert!(matches!(
+            from_bytes::<Vec<u32>>(b"4:spam"),
+            Err(Error::InvalidType)
+        ));
+        assert!(matches!(
+            from_bytes::<Vec<i8>>(b"4:spam"),
+            Err(Error::InvalidType)
+        ));
+        assert!(matches!(
+            from_bytes::<Vec<String>>(b"4:spam"),
+            Err(Error::InvalidType)
+        ));
+        assert!(matches!(
+            from_bytes::<(String, String)>(b"2:ab"),
+            Err(Error::InvalidType)
+        ));
+        assert!(matches!(
+            from_bytes::<(u8, u16)>(b"2:ab"),
+            Err(Error::InvalidType)
+        ));
+        assert_eq!((b'a', b'b'), from_bytes::<(u8, u8)>(b"2:ab").unwrap());
+    }
+
+    #[test]
+    fn byte_string_array_length_mismatch() {
+        assert!(matches!(
+            from_bytes::<[u8; 20]>(b"4:spam"),
+            Err(Error::ByteStringLengthMismatch {
+                expected: 20,
+                found: 4
+            })
+        ));
+        assert!(from_bytes::<[u8; 2]>(b"4:spam").is_err());
+    }
 }
@@ -0,0 +1,171 @@
+use std::io::{self, Read};
+
+use crate::{Error, Result};
+
+/// Reads bencode tokens from an [`io::Read`](std::io::Read) one byte at a time.
+///
+/// The reader should be buffered, for example with a [`BufReader`](std::io::BufReader).
+pub(crate) struct IoRead<R> {
+    pub(crate) reader: R,
+    peeked: Option<u8>,
+    /// Number of bytes consumed so far, used for error offsets
+    pub(crate) index: usize,
+    /// Bytes left unread by a [`ByteStringReader`](super::ByteStringReader), skipped before reading anything else
+    pub(crate) skip: u64,
+}
+
+impl<R> IoRead<R>
+where
+    R: Read,
+{
+    pub(crate) const fn new(reader: R) -> Self {
+        Self {
+            reader,
+            peeked: None,
+            index: 0,
+            skip: 0,
+        }
+    }
+
+    /// Discards the bytes left unread by a [`ByteStringReader`](super::ByteStringReader)
+    fn skip_pending(&mut self) -> Result<()> {
+        if self.skip > 0 {
+            let skip = core::mem::take(&mut self.skip);
+            let skipped = io::copy(&mut (&mut self.reader).take(skip), &mut io::sink())?;
+            self.index += skipped as usize;
+
+            if skipped != skip {
+                return Err(Error::EofWhileParsingByteString);
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Returns the next byte or None if the reader is over
+    fn read_byte(&mut self) -> Result<Option<u8>> {
+        self.skip_pending()?;
+
+        let mut byte = [0];
+
+        loop {
+            match self.reader.read(&mut byte) {
+                Ok(0) => return Ok(None),
+                Ok(_) => return Ok(Some(byte[0])),
+                Err(error) if error.kind() == io::ErrorKind::Interrupted => {}
+                Err(error) => return Err(error.into()),
+            }
+        }
+    }
+
+    /// Look at the next byte without consuming it.
+    pub(crate) fn peek_byte(&mut self) -> Result<u8> {
+        if self.peeked.is_none() {
+            self.peeked = self.read_byte()?;
+        }
+
+        self.peeked.ok_or(Error::Eof)
+    }
+
+    /// Returns the next byte and consumes it.
+    pub(crate) fn next_byte(&mut self) -> Result<u8> {
+        let byte = match self.peeked.take() {
+            Some(byte) => byte,
+            None => self.read_byte()?.ok_or(Error::Eof)?,
+        };
+
+        self.index += 1;
+        Ok(byte)
+    }
+
+    /// Ensures the reader is over
+    pub(crate) fn check_trailing_bytes(&mut self) -> Result<()> {
+        match self.peek_byte() {
+            Ok(_) => Err(Error::TrailingBytes),
+            Err(Error::Eof) => Ok(()),
+            Err(error) => Err(error),
+        }
+    }
+
+    /// Parses the length of a byte string and discards the ":" after it, the first digit was already consumed
+    pub(crate) fn parse_length(&mut self, first: u8) -> Result<usize> {
+        let mut len = usize::from(first - b'0');
+
+        loop {
+            match self.next_byte()? {
+                digit @ b'0'..=b'9' => {
+                    len = len
+                        .checked_mul(10)
+                        .and_then(|len| len.checked_add(usize::from(digit - b'0')))
+                        .ok_or(Error::OutOfBound)?;
+                }
+                b':' => break Ok(len),
+                token => break Err(Error::unexpected_token(":", token, self.index)),
+            }
+        }
+    }
+
+    /// Appends the content of a byte string of the given length to the buffer
+    pub(crate) fn read_byte_string_into(&mut self, len: usize, buffer: &mut Vec<u8>) -> Result<()> {
+        debug_assert!(self.peeked.is_none());
+
+        // Never trust the length to preallocate, the reader might end much sooner
+        let read = (&mut self.reader).take(len as u64).read_to_end(buffer)?;
+        self.index += read;
+
+        if read == len {
+            Ok(())
+        } else {
+            Err(Error::EofWhileParsingByteString)
+        }
+    }
+
+    /// Copies the raw bytes of the next complete value into the buffer.
+    ///
+    /// Only the structure is checked here, the content is validated when the copy is deserialized.
+    pub(crate) fn read_value_into(&mut self, buffer: &mut Vec<u8>) -> Result<()> {
+        let mut depth = 0usize;
+
+        loop {
+            match self.next_byte()? {
+                b'i' => {
+                    buffer.push(b'i');
+
+                    loop {
+                        match self.next_byte()? {
+                            byte @ (b'0'..=b'9' | b'-') => buffer.push(byte),
+                            b'e' => break buffer.push(b'e'),
+                            token => return Err(Error::unexpected_token("e", token, self.index)),
+                        }
+                    }
+                }
+                digit @ b'0'..=b'9' => {
+                    let len = self.parse_length(digit)?;
+                    buffer.extend_from_slice(itoa::Buffer::new().format(len).as_bytes());
+                    buffer.push(b':');
+                    self.read_byte_string_into(len, buffer)?;
+                }
+                token @ (b'l' | b'd') => {
+                    buffer.push(token);
+                    depth += 1;
+                    continue;
+                }
+                b'e' if depth > 0 => {
+                    buffer.push(b'e');
+                    depth -= 1;
+                }
+                token => {
+                    return Err(Error::unexpected_token(
+                        "one of: i, 0-9, l, d",
+                        token,
+                        self.index,
+                    ))
+                }
+            }
+
+            if depth == 0 {
+                break Ok(());
+            }
+        }
+    }
+}
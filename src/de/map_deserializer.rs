@@ -1,11 +1,11 @@
-use crate::{ByteString, Error, Result};
-use serde::de::{Deserialize, DeserializeSeed, MapAccess};
+use crate::{Error, Result};
+use serde::de::{DeserializeSeed, MapAccess};
 
-use super::Deserializer;
+use super::{map_key_deserializer::MapKeyDeserializer, Deserializer};
 
 pub struct MapDeserializer<'a, 'de: 'a> {
     deserializer: &'a mut Deserializer<'de>,
-    last_key: Option<ByteString>,
+    last_key: Option<&'de [u8]>,
 }
 
 impl<'a, 'de> MapDeserializer<'a, 'de> {
@@ -30,24 +30,17 @@ impl<'a, 'de> MapAccess<'de> for MapDeserializer<'a, 'de> {
                 Ok(None)
             }
             b'0'..=b'9' => {
-                // seed.deserialize(&mut *self).map(Some)
-                /*
-                HACK: We need to deserialize the key without actually advancing the deserializer buffer.
-                Cloning is a quick way to achive this but suboptimal to say the least.
-                */
-                let key = ByteString::deserialize(&mut (*self.deserializer).clone())?;
+                let key = self.deserializer.parse_byte_string()?;
 
-                if let Some(last_key) = &self.last_key {
-                    if last_key > &key {
-                        Err(Error::UnsortedKeys)
-                    } else {
-                        seed.deserialize(&mut *self.deserializer).map(Some)
+                if let Some(last_key) = self.last_key {
+                    if last_key > key {
+                        return Err(Error::UnsortedKeys);
                     }
-                } else {
-                    self.last_key = Some(key);
-
-                    seed.deserialize(&mut *self.deserializer).map(Some)
                 }
+
+                self.last_key = Some(key);
+
+                seed.deserialize(MapKeyDeserializer::new(key)).map(Some)
             }
             token => Err(Error::unexpected_token(
                 "number between 0-9",
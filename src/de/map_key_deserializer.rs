@@ -0,0 +1,238 @@
+use core::str::FromStr;
+use serde::de::{self, value::BorrowedStrDeserializer, Visitor};
+
+use crate::{Error, Result};
+
+/// Deserializes a dictionary key.
+///
+/// Keys are always byte strings, but integers, unit enum variants and newtypes can be parsed back from them.
+pub struct MapKeyDeserializer<'de> {
+    key: &'de [u8],
+}
+
+impl<'de> MapKeyDeserializer<'de> {
+    pub const fn new(key: &'de [u8]) -> Self {
+        Self { key }
+    }
+
+    /// Parses the key as a decimal integer following the same rules as bencode integers
+    fn parse_integer<N>(&self) -> Result<N>
+    where
+        N: FromStr,
+    {
+        let digits = match self.key {
+            [b'-', b'0', ..] => return Err(Error::NegativeZero),
+            [b'-', digits @ ..] => digits,
+            digits => digits,
+        };
+
+        match digits {
+            [] => return Err(Error::InvalidType),
+            [b'0', _, ..] => return Err(Error::LeadingZero),
+            digits if !digits.iter().all(u8::is_ascii_digit) => return Err(Error::InvalidType),
+            _ => {}
+        }
+
+        // The key only contains ascii at this point
+        core::str::from_utf8(self.key)?
+            .parse()
+            .map_err(|_| Error::OutOfBound)
+    }
+}
+
+impl<'de> de::Deserializer<'de> for MapKeyDeserializer<'de> {
+    type Error = Error;
+
+    fn deserialize_any<V>(self, visitor: V) -> Result<V::Value>
+    where
+        V: Visitor<'de>,
+    {
+        visitor.visit_borrowed_bytes(self.key)
+    }
+
+    fn deserialize_i8<V>(self, visitor: V) -> Result<V::Value>
+    where
+        V: Visitor<'de>,
+    {
+        visitor.visit_i8(self.parse_integer()?)
+    }
+
+    fn deserialize_i16<V>(self, visitor: V) -> Result<V::Value>
+    where
+        V: Visitor<'de>,
+    {
+        visitor.visit_i16(self.parse_integer()?)
+    }
+
+    fn deserialize_i32<V>(self, visitor: V) -> Result<V::Value>
+    where
+        V: Visitor<'de>,
+    {
+        visitor.visit_i32(self.parse_integer()?)
+    }
+
+    fn deserialize_i64<V>(self, visitor: V) -> Result<V::Value>
+    where
+        V: Visitor<'de>,
+    {
+        visitor.visit_i64(self.parse_integer()?)
+    }
+
+    fn deserialize_i128<V>(self, visitor: V) -> Result<V::Value>
+    where
+        V: Visitor<'de>,
+    {
+        visitor.visit_i128(self.parse_integer()?)
+    }
+
+    fn deserialize_u8<V>(self, visitor: V) -> Result<V::Value>
+    where
+        V: Visitor<'de>,
+    {
+        visitor.visit_u8(self.parse_integer()?)
+    }
+
+    fn deserialize_u16<V>(self, visitor: V) -> Result<V::Value>
+    where
+        V: Visitor<'de>,
+    {
+        visitor.visit_u16(self.parse_integer()?)
+    }
+
+    fn deserialize_u32<V>(self, visitor: V) -> Result<V::Value>
+    where
+        V: Visitor<'de>,
+    {
+        visitor.visit_u32(self.parse_integer()?)
+    }
+
+    fn deserialize_u64<V>(self, visitor: V) -> Result<V::Value>
+    where
+        V: Visitor<'de>,
+    {
+        visitor.visit_u64(self.parse_integer()?)
+    }
+
+    fn deserialize_u128<V>(self, visitor: V) -> Result<V::Value>
+    where
+        V: Visitor<'de>,
+    {
+        visitor.visit_u128(self.parse_integer()?)
+    }
+
+    fn deserialize_str<V>(self, visitor: V) -> Result<V::Value>
+    where
+        V: Visitor<'de>,
+    {
+        visitor.visit_borrowed_str(core::str::from_utf8(self.key)?)
+    }
+
+    fn deserialize_string<V>(self, visitor: V) -> Result<V::Value>
+    where
+        V: Visitor<'de>,
+    {
+        self.deserialize_str(visitor)
+    }
+
+    fn deserialize_option<V>(self, visitor: V) -> Result<V::Value>
+    where
+        V: Visitor<'de>,
+    {
+        visitor.visit_some(self)
+    }
+
+    fn deserialize_newtype_struct<V>(self, _name: &'static str, visitor: V) -> Result<V::Value>
+    where
+        V: Visitor<'de>,
+    {
+        visitor.visit_newtype_struct(self)
+    }
+
+    fn deserialize_enum<V>(
+        self,
+        _name: &'static str,
+        _variants: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value>
+    where
+        V: Visitor<'de>,
+    {
+        // Only unit variants can be used as keys, they are stored by name
+        visitor.visit_enum(BorrowedStrDeserializer::new(core::str::from_utf8(
+            self.key,
+        )?))
+    }
+
+    serde::forward_to_deserialize_any! {
+        bool f32 f64 char bytes byte_buf unit unit_struct seq tuple
+        tuple_struct map struct identifier ignored_any
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use alloc::collections::BTreeMap;
+    use serde_derive::{Deserialize, Serialize};
+
+    use crate::{from_bytes, to_bytes, Error};
+
+    #[derive(Debug, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+    enum Priority {
+        Low,
+        High,
+    }
+
+    #[derive(Debug, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+    struct PieceIndex(u32);
+
+    #[test]
+    fn integer_keys() {
+        let map = BTreeMap::from([(2u32, "b"), (10, "a")]);
+
+        // Keys are sorted on their encoded bytes, "10" comes before "2"
+        let bytes = to_bytes(&map).unwrap();
+        assert_eq!(b"d2:101:a1:21:be".to_vec(), bytes);
+        assert_eq!(map, from_bytes::<BTreeMap<u32, &str>>(&bytes).unwrap());
+
+        let negative = BTreeMap::from([(-1i64, 1u8)]);
+        assert_eq!(negative, from_bytes(&to_bytes(&negative).unwrap()).unwrap());
+    }
+
+    #[test]
+    fn enum_and_newtype_keys() {
+        let priorities = BTreeMap::from([(Priority::Low, 1u8), (Priority::High, 2)]);
+        let bytes = to_bytes(&priorities).unwrap();
+        assert_eq!(b"d4:Highi2e3:Lowi1ee".to_vec(), bytes);
+        assert_eq!(priorities, from_bytes(&bytes).unwrap());
+
+        let pieces = BTreeMap::from([(PieceIndex(7), 1u8)]);
+        let bytes = to_bytes(&pieces).unwrap();
+        assert_eq!(b"d1:7i1ee".to_vec(), bytes);
+        assert_eq!(pieces, from_bytes(&bytes).unwrap());
+    }
+
+    #[test]
+    fn invalid_integer_keys() {
+        assert!(matches!(
+            from_bytes::<BTreeMap<u32, u8>>(b"d2:07i1ee"),
+            Err(Error::LeadingZero)
+        ));
+        assert!(matches!(
+            from_bytes::<BTreeMap<i32, u8>>(b"d2:-0i1ee"),
+            Err(Error::NegativeZero)
+        ));
+        assert!(matches!(
+            from_bytes::<BTreeMap<u8, u8>>(b"d3:256i1ee"),
+            Err(Error::OutOfBound)
+        ));
+        assert!(from_bytes::<BTreeMap<u8, u8>>(b"d1:ai1ee").is_err());
+    }
+
+    #[test]
+    fn unsorted_keys_after_the_first() {
+        assert!(matches!(
+            from_bytes::<BTreeMap<&str, u8>>(b"d1:ai1e1:ci1e1:bi1ee"),
+            Err(Error::UnsortedKeys)
+        ));
+    }
+}
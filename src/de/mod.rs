@@ -1,11 +1,55 @@
-use serde::Deserialize;
+use serde::de::{Deserialize, Visitor};
 
-use crate::Result;
+use crate::{Error, Result};
 
+#[cfg(feature = "bytes")]
+mod buf;
+mod byte_seq_access;
+#[cfg(feature = "std")]
+mod byte_string_reader;
 mod deserializer;
+#[cfg(feature = "std")]
+mod io_read;
 mod map_deserializer;
+mod map_key_deserializer;
+mod reader;
+mod recover;
+mod scan;
+#[cfg(feature = "std")]
+mod stream;
+#[cfg(test)]
+mod test_input;
 
+#[cfg(feature = "bytes")]
+pub use buf::from_buf;
+#[cfg(feature = "std")]
+pub use byte_string_reader::ByteStringReader;
 pub use deserializer::Deserializer;
+#[cfg(fuzzing)]
+pub use deserializer::{assert_fast_numbers_match_scalar, assert_fast_skip_matches_scalar};
+#[cfg(feature = "std")]
+pub use reader::IoReader;
+pub use reader::{Event, Reader};
+pub use recover::{from_bytes_recovering, Diagnostic, Recovered};
+#[cfg(feature = "std")]
+pub use stream::{stream_dictionary, stream_list, StreamDictionary, StreamList};
+
+/// Visits a byte string as a sequence of `u8`, failing with [`Error::InvalidType`] on any other element type
+pub(crate) fn visit_byte_seq<'de, V>(bytes: &[u8], visitor: V) -> Result<V::Value>
+where
+    V: Visitor<'de>,
+{
+    let mut seq = byte_seq_access::ByteSeqAccess::new(bytes);
+    let value = visitor.visit_seq(&mut seq)?;
+
+    match seq.remaining() {
+        0 => Ok(value),
+        remaining => Err(Error::ByteStringLengthMismatch {
+            expected: bytes.len() - remaining,
+            found: bytes.len(),
+        }),
+    }
+}
 
 /// # Errors
 /// TODO
@@ -0,0 +1,450 @@
+use alloc::vec::Vec;
+#[cfg(feature = "std")]
+use std::io::Read;
+
+use super::deserializer::Frame;
+#[cfg(feature = "std")]
+use super::io_read::IoRead;
+#[cfg(feature = "std")]
+use crate::Error;
+use crate::{Deserializer, Result};
+
+/// A piece of a bencode value produced by a [`Reader`] or an [`IoReader`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Event<'a> {
+    /// Start of a list, its elements follow until the matching [`Event::End`].
+    ListStart,
+    /// Start of a dictionary, its keys and values follow until the matching [`Event::End`].
+    DictStart,
+    /// Key of a dictionary entry, its value comes next.
+    Key(&'a [u8]),
+    /// A byte string.
+    Bytes(&'a [u8]),
+    /// An integer.
+    Integer(i64),
+    /// End of the innermost list or dictionary.
+    End,
+}
+
+/// Pull parser yielding the [`Event`]s of a bencode value in a slice together with their offset.
+///
+/// Events are checked exactly like [`from_bytes`](crate::from_bytes) does,
+/// so integers and keys must be canonical and the slice must end right after the value.
+/// The first error is yielded and then the iterator stops.
+///
+/// ```
+/// use bde::de::{Event, Reader};
+///
+/// let events: Vec<_> = Reader::new(b"d3:fooli42eee").collect::<Result<_, _>>().unwrap();
+///
+/// assert_eq!(
+///     vec![
+///         (0, Event::DictStart),
+///         (1, Event::Key(b"foo")),
+///         (6, Event::ListStart),
+///         (7, Event::Integer(42)),
+///         (11, Event::End),
+///         (12, Event::End),
+///     ],
+///     events
+/// );
+/// ```
+pub struct Reader<'de> {
+    deserializer: Deserializer<'de>,
+    stack: Vec<Frame<'de>>,
+    started: bool,
+    done: bool,
+}
+
+impl<'de> Reader<'de> {
+    /// Creates a reader over a single bencode value.
+    pub const fn new(bytes: &'de [u8]) -> Self {
+        Self {
+            deserializer: Deserializer::from_bytes(bytes),
+            stack: Vec::new(),
+            started: false,
+            done: false,
+        }
+    }
+
+    /// Offset of the next byte to be read.
+    pub const fn offset(&self) -> usize {
+        self.deserializer.index
+    }
+
+    /// Number of lists and dictionaries the reader is currently inside of.
+    pub fn depth(&self) -> usize {
+        self.stack.len()
+    }
+
+    fn read_event(&mut self) -> Result<Option<(usize, Event<'de>)>> {
+        if self.started && self.stack.is_empty() {
+            self.done = true;
+            return self.deserializer.check_trailing_bytes().map(|()| None);
+        }
+
+        self.started = true;
+
+        let offset = self.deserializer.index;
+        let event = self.deserializer.next_event(&mut self.stack)?;
+        Ok(Some((offset, event)))
+    }
+}
+
+impl<'de> Iterator for Reader<'de> {
+    type Item = Result<(usize, Event<'de>)>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.done {
+            return None;
+        }
+
+        let event = self.read_event();
+        if event.is_err() {
+            self.done = true;
+        }
+
+        event.transpose()
+    }
+}
+
+/// Pull parser yielding the [`Event`]s of a bencode value read from an [`io::Read`](std::io::Read).
+///
+/// Behaves exactly like [`Reader`], returning the same events, offsets and errors,
+/// but byte strings and keys borrow from an internal buffer so events are returned one at a time by [`IoReader::next_event`].
+/// The reader should be buffered, for example with a [`BufReader`](std::io::BufReader).
+#[cfg(feature = "std")]
+pub struct IoReader<R> {
+    reader: IoRead<R>,
+    stack: Vec<IoFrame>,
+    buffer: Vec<u8>,
+    started: bool,
+    done: bool,
+}
+
+/// Same as [`Frame`] with owned keys
+#[cfg(feature = "std")]
+enum IoFrame {
+    List,
+    Dictionary {
+        last_key: Option<Vec<u8>>,
+        value_next: bool,
+    },
+}
+
+/// Kind of the event read into the buffer of an [`IoReader`]
+#[cfg(feature = "std")]
+enum Buffered {
+    ListStart,
+    DictStart,
+    Key,
+    Bytes,
+    Integer(i64),
+    End,
+}
+
+#[cfg(feature = "std")]
+impl<R> IoReader<R>
+where
+    R: Read,
+{
+    /// Creates a reader over a single bencode value.
+    pub const fn new(reader: R) -> Self {
+        Self {
+            reader: IoRead::new(reader),
+            stack: Vec::new(),
+            buffer: Vec::new(),
+            started: false,
+            done: false,
+        }
+    }
+
+    /// Offset of the next byte to be read.
+    pub const fn offset(&self) -> usize {
+        self.reader.index
+    }
+
+    /// Number of lists and dictionaries the reader is currently inside of.
+    pub fn depth(&self) -> usize {
+        self.stack.len()
+    }
+
+    /// Reads the next event and its offset, `None` once the value is over.
+    ///
+    /// # Errors
+    /// Returns the same errors as [`Reader`] and io errors, after an error only `None` is returned.
+    pub fn next_event(&mut self) -> Result<Option<(usize, Event<'_>)>> {
+        if self.done {
+            return Ok(None);
+        }
+
+        let event = match self.read_event() {
+            Ok(event) => event,
+            Err(error) => {
+                self.done = true;
+                return Err(error);
+            }
+        };
+
+        Ok(event.map(|(offset, event)| {
+            let event = match event {
+                Buffered::ListStart => Event::ListStart,
+                Buffered::DictStart => Event::DictStart,
+                Buffered::Key => Event::Key(&self.buffer),
+                Buffered::Bytes => Event::Bytes(&self.buffer),
+                Buffered::Integer(integer) => Event::Integer(integer),
+                Buffered::End => Event::End,
+            };
+            (offset, event)
+        }))
+    }
+
+    /// Mirrors [`Deserializer::next_event`] on top of an [`IoRead`]
+    fn read_event(&mut self) -> Result<Option<(usize, Buffered)>> {
+        if self.started && self.stack.is_empty() {
+            self.done = true;
+            return self.reader.check_trailing_bytes().map(|()| None);
+        }
+
+        self.started = true;
+
+        let offset = self.reader.index;
+
+        match self.stack.last_mut() {
+            Some(IoFrame::List) => {
+                if let b'e' = self.reader.peek_byte()? {
+                    self.reader.next_byte()?;
+                    self.stack.pop();
+                    return Ok(Some((offset, Buffered::End)));
+                }
+            }
+            Some(IoFrame::Dictionary { value_next, .. }) if *value_next => *value_next = false,
+            Some(IoFrame::Dictionary { .. }) => {
+                return match self.reader.peek_byte()? {
+                    b'e' => {
+                        self.reader.next_byte()?;
+                        self.stack.pop();
+                        Ok(Some((offset, Buffered::End)))
+                    }
+                    b'0'..=b'9' => {
+                        self.read_byte_string()?;
+
+                        if let Some(IoFrame::Dictionary {
+                            last_key,
+                            value_next,
+                        }) = self.stack.last_mut()
+                        {
+                            if matches!(last_key, Some(last_key) if *last_key > self.buffer) {
+                                return Err(Error::UnsortedKeys);
+                            }
+                            let last_key = last_key.get_or_insert_with(Vec::new);
+                            last_key.clear();
+                            last_key.extend_from_slice(&self.buffer);
+                            *value_next = true;
+                        }
+
+                        Ok(Some((offset, Buffered::Key)))
+                    }
+                    token => Err(Error::unexpected_token(
+                        "number between 0-9",
+                        token,
+                        self.reader.index,
+                    )),
+                };
+            }
+            None => {}
+        }
+
+        let event = match self.reader.peek_byte()? {
+            b'i' => {
+                self.reader.next_byte()?;
+                Buffered::Integer(self.read_integer()?)
+            }
+            b'0'..=b'9' => {
+                self.read_byte_string()?;
+                Buffered::Bytes
+            }
+            b'l' => {
+                self.reader.next_byte()?;
+                self.stack.push(IoFrame::List);
+                Buffered::ListStart
+            }
+            b'd' => {
+                self.reader.next_byte()?;
+                self.stack.push(IoFrame::Dictionary {
+                    last_key: None,
+                    value_next: false,
+                });
+                Buffered::DictStart
+            }
+            token => {
+                return Err(Error::unexpected_token(
+                    "one of: i, 0-9, l, d",
+                    token,
+                    self.reader.index,
+                ))
+            }
+        };
+
+        Ok(Some((offset, event)))
+    }
+
+    /// Buffers bytes while they match, plus the first one that doesn't, stopping early at the end of the reader
+    fn buffer_token(&mut self, matches: impl Fn(u8) -> bool) -> Result<()> {
+        self.buffer.clear();
+
+        loop {
+            match self.reader.peek_byte() {
+                Ok(byte) => {
+                    self.reader.next_byte()?;
+                    self.buffer.push(byte);
+                    if !matches(byte) {
+                        return Ok(());
+                    }
+                }
+                Err(Error::Eof) => return Ok(()),
+                Err(error) => return Err(error),
+            }
+        }
+    }
+
+    /// Reads an integer after the leading "i", parsing it with a [`Deserializer`]
+    fn read_integer(&mut self) -> Result<i64> {
+        let start = self.reader.index;
+        self.buffer_token(|byte| byte.is_ascii_digit() || byte == b'-')?;
+
+        Deserializer::from_bytes(&self.buffer)
+            .parse_signed_integer()
+            .map_err(|error| offset_error(error, start))
+    }
+
+    /// Reads a byte string into the buffer, parsing its length with a [`Deserializer`]
+    fn read_byte_string(&mut self) -> Result<()> {
+        let start = self.reader.index;
+        self.buffer_token(|byte| byte.is_ascii_digit())?;
+
+        let len = Deserializer::from_bytes(&self.buffer)
+            .next_ascii_number_until::<usize>(false, b':')
+            .map_err(|error| offset_error(error, start))?;
+
+        self.buffer.clear();
+        if len == 0 {
+            return Ok(());
+        }
+        if self.reader.index.checked_add(len).is_none() {
+            return Err(Error::OutOfBound);
+        }
+
+        self.reader.read_byte_string_into(len, &mut self.buffer)
+    }
+}
+
+/// Moves the offset of an error found in a buffered token to where the token is in the input
+#[cfg(feature = "std")]
+fn offset_error(error: Error, start: usize) -> Error {
+    match error {
+        Error::UnexpectedToken {
+            expected,
+            found,
+            index,
+        } => Error::unexpected_token(expected, found, start + index),
+        error => error,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use alloc::{string::ToString, vec, vec::Vec};
+
+    use super::{Event, Reader};
+    use crate::{de::test_input::corrupted, from_bytes, Value};
+
+    fn inputs() -> Vec<Vec<u8>> {
+        let torrent = include_bytes!("../../tests/torrents/endeavour.torrent");
+        let mut inputs = vec![
+            torrent.to_vec(),
+            b"d1:bi1e1:ai2ee".to_vec(),
+            b"d1:ai1e1:ai2ee".to_vec(),
+            b"d1:ai1ei2ei3ee".to_vec(),
+            b"li-0ee".to_vec(),
+            b"li01ee".to_vec(),
+            b"li-12e0:5:abcdee".to_vec(),
+            b"i99999999999999999999e".to_vec(),
+            b"lx".to_vec(),
+            b"li1e".to_vec(),
+            b"i1ei2e".to_vec(),
+            b"".to_vec(),
+        ];
+
+        inputs.extend(corrupted(&torrent[..300], 1000));
+
+        inputs
+    }
+
+    #[test]
+    fn events() {
+        let events: Vec<_> = Reader::new(b"ld1:ai-3e1:b0:e4:spame")
+            .map(|event| event.unwrap().1)
+            .collect();
+
+        assert_eq!(
+            vec![
+                Event::ListStart,
+                Event::DictStart,
+                Event::Key(b"a"),
+                Event::Integer(-3),
+                Event::Key(b"b"),
+                Event::Bytes(b""),
+                Event::End,
+                Event::Bytes(b"spam"),
+                Event::End,
+            ],
+            events
+        );
+    }
+
+    #[test]
+    fn errors_match_from_bytes() {
+        for input in inputs() {
+            let read = Reader::new(&input)
+                .collect::<Result<Vec<_>, _>>()
+                .map(|_| ())
+                .map_err(|error| error.to_string());
+            let deserialized = from_bytes::<Value>(&input)
+                .map(|_| ())
+                .map_err(|error| error.to_string());
+
+            assert_eq!(deserialized, read, "{:?}", String::from_utf8_lossy(&input));
+        }
+    }
+
+    #[cfg(feature = "std")]
+    #[test]
+    fn io_reader_matches_reader() {
+        use super::IoReader;
+
+        for input in inputs() {
+            let mut expected = Reader::new(&input);
+            let mut reader = IoReader::new(input.as_slice());
+
+            loop {
+                let event = reader.next_event().map_err(|error| error.to_string());
+                let expected_event = expected
+                    .next()
+                    .transpose()
+                    .map_err(|error| error.to_string());
+
+                assert_eq!(
+                    expected_event,
+                    event,
+                    "{:?}",
+                    String::from_utf8_lossy(&input)
+                );
+                if !matches!(event, Ok(Some(_))) {
+                    break;
+                }
+                assert_eq!(expected.depth(), reader.depth());
+            }
+        }
+    }
+}
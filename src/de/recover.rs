@@ -0,0 +1,495 @@
+use alloc::vec::Vec;
+use core::ops::Range;
+
+use crate::{ByteString, Dictionary, Error, Integer, Value};
+
+/// A problem found while decoding in recovery mode.
+#[derive(Debug)]
+pub struct Diagnostic {
+    /// Offset in the input where the problem starts.
+    pub offset: usize,
+    /// What went wrong.
+    pub error: Error,
+}
+
+/// Best-effort result of [`from_bytes_recovering`].
+#[derive(Debug)]
+pub struct Recovered {
+    /// Everything that could be decoded, `None` if not even the outermost value could be read.
+    pub value: Option<Value>,
+    /// Every problem found, in the order they were found.
+    ///
+    /// This is the order of the input, except that an unterminated list or dictionary
+    /// is reported at its start after the problems found inside it.
+    pub diagnostics: Vec<Diagnostic>,
+}
+
+impl Recovered {
+    /// Returns true if the input was valid bencode.
+    pub fn is_valid(&self) -> bool {
+        self.diagnostics.is_empty()
+    }
+}
+
+/// Decodes a [`Value`] reporting every problem found instead of stopping at the first.
+///
+/// Where possible the decoder resynchronizes and keeps going:
+/// - unsorted keys are kept, duplicate keys keep the first value
+/// - integers with leading zeros or negative zero are still read
+/// - integers out of bound and values with unexpected tokens are dropped
+/// - truncated byte strings keep the bytes that are present
+/// - truncated lists and dictionaries keep the elements that are present
+///
+/// Every one of these is reported as a [`Diagnostic`] using the same [`Error`] variants as [`from_bytes`](crate::from_bytes).
+pub fn from_bytes_recovering(bytes: &[u8]) -> Recovered {
+    let mut recovery = Recovery {
+        bytes,
+        index: 0,
+        diagnostics: Vec::new(),
+    };
+
+    let value = recovery.parse_value();
+
+    if recovery.index < bytes.len() {
+        recovery.report(recovery.index, Error::TrailingBytes);
+    }
+
+    Recovered {
+        value,
+        diagnostics: recovery.diagnostics,
+    }
+}
+
+/// A list or dictionary being decoded
+enum Frame<'a> {
+    List {
+        start: usize,
+        list: Vec<Value>,
+    },
+    Dictionary {
+        start: usize,
+        dictionary: Dictionary,
+        last_key: Option<&'a [u8]>,
+        /// What to do with the value being parsed
+        pending: Option<Pending<'a>>,
+    },
+}
+
+/// Destination of a dictionary value being parsed
+enum Pending<'a> {
+    Value(&'a [u8]),
+    /// The value of a duplicate or invalid key
+    Discard,
+}
+
+/// Progress made by starting or resuming a value
+enum Step {
+    /// A value was read, or dropped after being reported
+    Value(Option<Value>),
+    /// A list or dictionary was opened
+    Open,
+}
+
+struct Recovery<'a> {
+    bytes: &'a [u8],
+    index: usize,
+    diagnostics: Vec<Diagnostic>,
+}
+
+impl<'a> Recovery<'a> {
+    fn report(&mut self, offset: usize, error: Error) {
+        self.diagnostics.push(Diagnostic { offset, error });
+    }
+
+    fn peek_byte(&self) -> Option<u8> {
+        self.bytes.get(self.index).copied()
+    }
+
+    /// Parses any value without recursion, always consuming at least one byte unless the input is over
+    fn parse_value(&mut self) -> Option<Value> {
+        let mut stack = Vec::new();
+        let mut step = self.start_value(&mut stack);
+
+        loop {
+            let value = match step {
+                Step::Value(value) => value,
+                Step::Open => {
+                    step = self.resume(&mut stack);
+                    continue;
+                }
+            };
+
+            match stack.last_mut() {
+                None => return value,
+                Some(Frame::List { list, .. }) => list.extend(value),
+                Some(Frame::Dictionary {
+                    dictionary,
+                    pending,
+                    ..
+                }) => {
+                    if let (Some(Pending::Value(key)), Some(value)) = (pending.take(), value) {
+                        dictionary.insert(ByteString::from(key), value);
+                    }
+                }
+            }
+
+            step = self.resume(&mut stack);
+        }
+    }
+
+    /// Parses a scalar or opens a list or dictionary
+    fn start_value(&mut self, stack: &mut Vec<Frame<'a>>) -> Step {
+        match self.peek_byte() {
+            Some(b'i') => Step::Value(self.parse_integer().map(Value::Integer)),
+            Some(b'0'..=b'9') => Step::Value(
+                self.parse_byte_string()
+                    .map(|bytes| Value::ByteString(ByteString::from(bytes))),
+            ),
+            Some(b'l') => {
+                stack.push(Frame::List {
+                    start: self.index,
+                    list: Vec::new(),
+                });
+                self.index += 1;
+                Step::Open
+            }
+            Some(b'd') => {
+                stack.push(Frame::Dictionary {
+                    start: self.index,
+                    dictionary: Dictionary::new(),
+                    last_key: None,
+                    pending: None,
+                });
+                self.index += 1;
+                Step::Open
+            }
+            Some(token) => {
+                self.report(
+                    self.index,
+                    Error::unexpected_token("one of: i, 0-9, l, d", token, self.index),
+                );
+                self.index += 1;
+                Step::Value(None)
+            }
+            None => {
+                self.report(self.index, Error::Eof);
+                Step::Value(None)
+            }
+        }
+    }
+
+    /// Continues the innermost list or dictionary until it starts an element or ends
+    fn resume(&mut self, stack: &mut Vec<Frame<'a>>) -> Step {
+        loop {
+            match stack.last_mut() {
+                // Only called with an open list or dictionary
+                None => return Step::Value(None),
+                Some(Frame::List { start, .. }) => {
+                    let start = *start;
+
+                    return match self.peek_byte() {
+                        Some(b'e') => {
+                            self.index += 1;
+                            Self::close(stack)
+                        }
+                        Some(_) => self.start_value(stack),
+                        None => {
+                            self.report(start, Error::Eof);
+                            Self::close(stack)
+                        }
+                    };
+                }
+                Some(Frame::Dictionary {
+                    start,
+                    dictionary,
+                    last_key,
+                    pending,
+                }) => {
+                    let start = *start;
+                    let key_offset = self.index;
+
+                    let key = match self.peek_byte() {
+                        Some(b'e') => {
+                            self.index += 1;
+                            return Self::close(stack);
+                        }
+                        Some(b'0'..=b'9') => self.parse_byte_string(),
+                        Some(token) => {
+                            // Skip the whole value used as a key
+                            self.report(
+                                key_offset,
+                                Error::unexpected_token("number between 0-9", token, key_offset),
+                            );
+                            if let b'i' | b'l' | b'd' = token {
+                                *pending = Some(Pending::Discard);
+                                return self.start_value(stack);
+                            }
+                            self.index += 1;
+                            continue;
+                        }
+                        None => {
+                            self.report(start, Error::Eof);
+                            return Self::close(stack);
+                        }
+                    };
+
+                    let Some(key) = key else {
+                        continue;
+                    };
+
+                    if let Some(last_key) = *last_key {
+                        if last_key > key {
+                            self.report(key_offset, Error::UnsortedKeys);
+                        }
+                    }
+                    *last_key = Some(key);
+
+                    let duplicate = dictionary.contains_key(key);
+                    if duplicate {
+                        self.report(key_offset, Error::DuplicateKey(ByteString::from(key)));
+                    }
+
+                    // A missing value is reported, the end of the dictionary is handled by the next iteration
+                    match self.peek_byte() {
+                        Some(b'e') => {
+                            self.report(
+                                self.index,
+                                Error::unexpected_token("one of: i, 0-9, l, d", b'e', self.index),
+                            );
+                        }
+                        Some(_) => {
+                            *pending = Some(if duplicate {
+                                Pending::Discard
+                            } else {
+                                Pending::Value(key)
+                            });
+                            return self.start_value(stack);
+                        }
+                        None => {}
+                    }
+                }
+            }
+        }
+    }
+
+    /// Ends the innermost list or dictionary, keeping the elements read so far
+    fn close(stack: &mut Vec<Frame<'a>>) -> Step {
+        Step::Value(stack.pop().map(|frame| match frame {
+            Frame::List { list, .. } => Value::List(list),
+            Frame::Dictionary { dictionary, .. } => Value::Dictionary(dictionary),
+        }))
+    }
+
+    fn parse_integer(&mut self) -> Option<Integer> {
+        let start = self.index;
+
+        // Resynchronize on the closing "e", the whole integer is dropped if it is malformed
+        let Some(end) = self.bytes[start..].iter().position(|&byte| byte == b'e') else {
+            self.report(start, Error::Eof);
+            self.index = self.bytes.len();
+            return None;
+        };
+        let end = start + end;
+        self.index = end + 1;
+
+        let body = start + 1..end;
+        let (negative, digits) = match self.bytes.get(body.start) {
+            Some(b'-') => (true, body.start + 1..body.end),
+            _ => (false, body.clone()),
+        };
+
+        if let Some(offset) = self.find_non_digit(digits.clone()) {
+            let found = self.bytes[offset];
+            self.report(
+                offset,
+                Error::unexpected_token("number between 0-9", found, offset),
+            );
+            return None;
+        }
+
+        match &self.bytes[digits] {
+            [] => {
+                self.report(
+                    end,
+                    Error::unexpected_token("number between 0-9", b'e', end),
+                );
+                return None;
+            }
+            [b'0'] if negative => self.report(start, Error::NegativeZero),
+            [b'0', _, ..] => self.report(start, Error::LeadingZero),
+            _ => {}
+        }
+
+        // Digits are ascii at this point
+        let text = core::str::from_utf8(&self.bytes[body]).ok()?;
+
+        // Prefer i64 like the deserializer does, positive numbers that don't fit are still valid as u64
+        let integer = match text.parse::<i64>() {
+            Ok(integer) => Some(Integer::from(integer)),
+            Err(_) if !negative => text.parse::<u64>().ok().map(Integer::from),
+            Err(_) => None,
+        };
+
+        if integer.is_none() {
+            self.report(start, Error::OutOfBound);
+        }
+
+        integer
+    }
+
+    fn parse_byte_string(&mut self) -> Option<&'a [u8]> {
+        let start = self.index;
+
+        let Some(colon) = self.bytes[start..]
+            .iter()
+            .position(|byte| !byte.is_ascii_digit())
+        else {
+            self.report(start, Error::Eof);
+            self.index = self.bytes.len();
+            return None;
+        };
+        let colon = start + colon;
+
+        if self.bytes[colon] != b':' {
+            let found = self.bytes[colon];
+            self.report(colon, Error::unexpected_token(":", found, colon));
+            self.index = colon;
+            return None;
+        }
+
+        self.index = colon + 1;
+
+        let Ok(len) = core::str::from_utf8(&self.bytes[start..colon])
+            .unwrap_or_default()
+            .parse::<usize>()
+        else {
+            self.report(start, Error::OutOfBound);
+            return None;
+        };
+
+        match self.index.checked_add(len) {
+            Some(end) if end <= self.bytes.len() => {
+                let bytes = &self.bytes[self.index..end];
+                self.index = end;
+                Some(bytes)
+            }
+            _ => {
+                // Keep whatever is left
+                self.report(start, Error::EofWhileParsingByteString);
+                let bytes = &self.bytes[self.index..];
+                self.index = self.bytes.len();
+                Some(bytes)
+            }
+        }
+    }
+
+    fn find_non_digit(&self, range: Range<usize>) -> Option<usize> {
+        let start = range.start;
+        self.bytes[range]
+            .iter()
+            .position(|byte| !byte.is_ascii_digit())
+            .map(|offset| start + offset)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::from_bytes_recovering;
+    use crate::{from_bytes, Error, Value};
+
+    fn errors(bytes: &[u8]) -> Vec<(usize, String)> {
+        from_bytes_recovering(bytes)
+            .diagnostics
+            .into_iter()
+            .map(|diagnostic| (diagnostic.offset, diagnostic.error.to_string()))
+            .collect()
+    }
+
+    #[test]
+    fn valid_input_matches_from_bytes() {
+        let bytes = include_bytes!("../../tests/torrents/archlinux.torrent");
+        let recovered = from_bytes_recovering(bytes);
+
+        assert!(recovered.is_valid());
+        assert_eq!(
+            from_bytes::<Value>(bytes).unwrap(),
+            recovered.value.unwrap()
+        );
+    }
+
+    #[test]
+    fn collects_every_problem() {
+        let recovered = from_bytes_recovering(b"d1:bi01e1:ai-0e1:ai2e1:ci99999999999999999999ee");
+
+        let kinds: Vec<_> = recovered
+            .diagnostics
+            .iter()
+            .map(|diagnostic| (diagnostic.offset, &diagnostic.error))
+            .collect();
+
+        assert!(matches!(
+            kinds.as_slice(),
+            [
+                (4, Error::LeadingZero),
+                (8, Error::UnsortedKeys),
+                (11, Error::NegativeZero),
+                (15, Error::DuplicateKey(key)),
+                (24, Error::OutOfBound),
+            ] if key == "a"
+        ));
+
+        assert_eq!(
+            from_bytes::<Value>(b"d1:ai0e1:bi1ee").unwrap(),
+            recovered.value.unwrap()
+        );
+    }
+
+    #[test]
+    fn truncated_input() {
+        let recovered = from_bytes_recovering(b"li1e5:spa");
+        assert_eq!(
+            vec![
+                (4, Error::EofWhileParsingByteString.to_string()),
+                (0, Error::Eof.to_string())
+            ],
+            errors(b"li1e5:spa")
+        );
+        assert_eq!(
+            from_bytes::<Value>(b"li1e3:spae").unwrap(),
+            recovered.value.unwrap()
+        );
+    }
+
+    #[test]
+    fn deeply_nested_input() {
+        const DEPTH: usize = 1_000_000;
+
+        let recovered = from_bytes_recovering(&vec![b'l'; DEPTH]);
+        assert_eq!(DEPTH, recovered.diagnostics.len());
+
+        let mut value = recovered.value.as_ref();
+        let mut depth = 0;
+        while let Some(Value::List(list)) = value {
+            value = list.first();
+            depth += 1;
+        }
+        assert_eq!(DEPTH, depth);
+    }
+
+    #[test]
+    fn resynchronizes_after_bad_tokens() {
+        let recovered = from_bytes_recovering(b"lxi1ei1x2ei3eee");
+        assert_eq!(
+            from_bytes::<Value>(b"li1ei3ee").unwrap(),
+            recovered.value.unwrap()
+        );
+        assert_eq!(
+            vec![1, 7, 14],
+            recovered
+                .diagnostics
+                .iter()
+                .map(|diagnostic| diagnostic.offset)
+                .collect::<Vec<_>>()
+        );
+    }
+}
@@ -0,0 +1,246 @@
+//! SWAR (SIMD within a register) helpers used to speed up parsing.
+//!
+//! Everything here only relies on plain `u64` arithmetic, so it works the same on every target.
+//!
+//! Digits are scanned 8 at a time, and [`skip`] uses them to step over whole values without
+//! going through [`Deserializer::next_event`](super::Deserializer::next_event).
+
+use alloc::vec::Vec;
+
+const ASCII_ZEROS: u64 = u64::from_ne_bytes([b'0'; 8]);
+const HIGH_NIBBLES: u64 = u64::from_ne_bytes([0xF0; 8]);
+const SIXES: u64 = u64::from_ne_bytes([0x06; 8]);
+
+/// Returns how many bytes at the start of the chunk are ascii digits.
+///
+/// The chunk must be loaded in little endian order.
+pub(crate) const fn digit_count(chunk: u64) -> usize {
+    // A byte is a digit if its high nibble is 3 both before and after adding 6.
+    // Adding 6 can carry into the next byte, but only from a byte that isn't a digit,
+    // and nothing after the first non digit matters.
+    let high = (chunk & HIGH_NIBBLES) ^ ASCII_ZEROS;
+    let shifted = (chunk.wrapping_add(SIXES) & HIGH_NIBBLES) ^ ASCII_ZEROS;
+    let non_digits = high | shifted;
+
+    (non_digits.trailing_zeros() / 8) as usize
+}
+
+/// Parses the digits at the start of a chunk of 8 bytes.
+///
+/// Returns the parsed value and the number of digits, which is between 0 and 8.
+pub(crate) fn parse_digits(bytes: [u8; 8]) -> (u64, usize) {
+    let chunk = u64::from_le_bytes(bytes);
+    let count = digit_count(chunk);
+
+    if count == 0 {
+        return (0, 0);
+    }
+
+    // Digits don't borrow when subtracting, so anything that goes wrong only affects the bytes after them.
+    // Shifting them to the top turns the missing digits into leading zeros.
+    let digits = chunk.wrapping_sub(ASCII_ZEROS) << (8 * (8 - count));
+
+    // Combine pairs of digits, then pairs of pairs and so on
+    let digits = (digits.wrapping_mul(10) + (digits >> 8)) & 0x00FF_00FF_00FF_00FF;
+    let digits = (digits.wrapping_mul(100) + (digits >> 16)) & 0x0000_FFFF_0000_FFFF;
+    let digits = (digits.wrapping_mul(10000) + (digits >> 32)) & 0x0000_0000_FFFF_FFFF;
+
+    (digits, count)
+}
+
+/// Powers of ten for every possible number of digits in a chunk
+const POWERS_OF_TEN: [u64; 9] = [
+    1,
+    10,
+    100,
+    1_000,
+    10_000,
+    100_000,
+    1_000_000,
+    10_000_000,
+    100_000_000,
+];
+
+/// Largest integer [`Deserializer`](super::Deserializer) decodes as an `i64`, as ascii digits
+const MAX_INTEGER: &[u8] = b"9223372036854775807";
+
+/// Loads the 8 bytes starting at `index`, padding the end of the input with bytes that aren't digits
+fn load(bytes: &[u8], index: usize) -> [u8; 8] {
+    let mut chunk = [0; 8];
+    if let Some(rest) = bytes.get(index..) {
+        let len = rest.len().min(8);
+        chunk[..len].copy_from_slice(&rest[..len]);
+    }
+    chunk
+}
+
+/// Returns the index of the first byte at or after `index` that isn't a digit
+fn skip_digits(bytes: &[u8], mut index: usize) -> usize {
+    loop {
+        let count = digit_count(u64::from_le_bytes(load(bytes, index)));
+        index += count;
+        if count < 8 {
+            return index;
+        }
+    }
+}
+
+/// Parses the length of a byte string, returning it with the index of the byte after the digits
+fn parse_length(bytes: &[u8], mut index: usize) -> Option<(usize, usize)> {
+    let start = index;
+    let mut length = 0u64;
+
+    loop {
+        let (value, count) = parse_digits(load(bytes, index));
+        length = length
+            .checked_mul(POWERS_OF_TEN[count])?
+            .checked_add(value)?;
+        index += count;
+        if count < 8 {
+            break;
+        }
+    }
+
+    if index == start {
+        return None;
+    }
+    Some((usize::try_from(length).ok()?, index))
+}
+
+/// Steps over the byte string at `index`, returning it with the index after it
+fn skip_byte_string(bytes: &[u8], index: usize) -> Option<(&[u8], usize)> {
+    let (length, index) = parse_length(bytes, index)?;
+    if *bytes.get(index)? != b':' {
+        return None;
+    }
+
+    let start = index + 1;
+    let end = start.checked_add(length)?;
+    Some((bytes.get(start..end)?, end))
+}
+
+/// Steps over the digits and the closing `e` of the integer after the `i` at `index`
+fn skip_integer(bytes: &[u8], index: usize) -> Option<usize> {
+    let negative = *bytes.get(index)? == b'-';
+    let start = index + usize::from(negative);
+    let end = skip_digits(bytes, start);
+
+    match &bytes[start..end] {
+        [] => return None,
+        // Zero can't be negative or have more digits after it
+        [b'0'] if !negative => {}
+        [b'0', ..] => return None,
+        // Equal lengths of digits compare like the numbers they spell
+        digits
+            if digits.len() > MAX_INTEGER.len()
+                || (digits.len() == MAX_INTEGER.len() && digits > MAX_INTEGER) =>
+        {
+            return None
+        }
+        _ => {}
+    }
+
+    (*bytes.get(end)? == b'e').then_some(end + 1)
+}
+
+/// A list or dictionary [`skip`] is inside of
+enum Open<'a> {
+    List,
+    /// The last key of a dictionary, if any
+    Dictionary(Option<&'a [u8]>),
+}
+
+/// Finds the end of the value starting at `index` without visiting it.
+///
+/// Lengths and integers are stepped over 8 digits at a time and byte strings are jumped over.
+/// Only well formed values are accepted: when this returns the end of a value,
+/// [`Deserializer::next_event`](super::Deserializer::next_event) would have stopped at the same index.
+/// Anything else returns `None`, leaving it to the scalar path to find and report the error.
+pub(crate) fn skip(bytes: &[u8], mut index: usize) -> Option<usize> {
+    let mut stack = Vec::new();
+
+    loop {
+        match stack.last_mut() {
+            Some(_) if bytes.get(index) == Some(&b'e') => {
+                index += 1;
+                stack.pop();
+            }
+            Some(Open::Dictionary(last_key)) => {
+                // `skip_byte_string` also fails unless the key starts with a digit
+                let (key, end) = skip_byte_string(bytes, index)?;
+                if matches!(last_key, Some(last_key) if *last_key > key) {
+                    return None;
+                }
+                *last_key = Some(key);
+                index = skip_token(bytes, end, &mut stack)?;
+            }
+            _ => index = skip_token(bytes, index, &mut stack)?,
+        }
+
+        if stack.is_empty() {
+            return Some(index);
+        }
+    }
+}
+
+/// Steps over the integer or byte string at `index`, or opens the list or dictionary there
+fn skip_token<'a>(bytes: &'a [u8], index: usize, stack: &mut Vec<Open<'a>>) -> Option<usize> {
+    match *bytes.get(index)? {
+        b'i' => skip_integer(bytes, index + 1),
+        b'0'..=b'9' => skip_byte_string(bytes, index).map(|(_, end)| end),
+        b'l' => {
+            stack.push(Open::List);
+            Some(index + 1)
+        }
+        b'd' => {
+            stack.push(Open::Dictionary(None));
+            Some(index + 1)
+        }
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::parse_digits;
+    use crate::de::test_input::Xorshift;
+
+    /// Byte at a time reference implementation
+    fn parse_digits_scalar(bytes: [u8; 8]) -> (u64, usize) {
+        let count = bytes
+            .iter()
+            .take_while(|byte| byte.is_ascii_digit())
+            .count();
+        let value = bytes[..count]
+            .iter()
+            .fold(0, |value, digit| value * 10 + u64::from(digit - b'0'));
+        (value, count)
+    }
+
+    #[test]
+    fn matches_scalar() {
+        let mut rng = Xorshift::new(0x2545_F491_4F6C_DD1D);
+
+        for _ in 0..200_000 {
+            // Biased towards digits so runs of every length show up
+            let mut bytes = rng.next_u64().to_le_bytes();
+            for byte in &mut bytes {
+                if *byte < 200 {
+                    *byte = b'0' + *byte % 10;
+                }
+            }
+
+            assert_eq!(parse_digits_scalar(bytes), parse_digits(bytes), "{bytes:?}");
+        }
+
+        for bytes in [
+            *b"00000000",
+            *b"99999999",
+            *b"1234567e",
+            *b"e1234567",
+            *b"9:\xff\xff\xff\xff\xff\xff",
+        ] {
+            assert_eq!(parse_digits_scalar(bytes), parse_digits(bytes), "{bytes:?}");
+        }
+    }
+}
@@ -0,0 +1,463 @@
+use core::marker::PhantomData;
+use serde::de::DeserializeOwned;
+use std::io::{self, Read, Write};
+
+use super::{
+    byte_string_reader::{io_error_into_error, ByteStringReader},
+    from_bytes,
+    io_read::IoRead,
+};
+use crate::{ByteString, Error, Result};
+
+/// Iterator over the elements of a bencode list read from an [`io::Read`](std::io::Read).
+///
+/// Created by [`stream_list`].
+pub struct StreamList<R, T> {
+    reader: IoRead<R>,
+    buffer: Vec<u8>,
+    done: bool,
+    marker: PhantomData<fn() -> T>,
+}
+
+/// Iterator over the entries of a bencode dictionary read from an [`io::Read`](std::io::Read).
+///
+/// Created by [`stream_dictionary`].
+pub struct StreamDictionary<R, T> {
+    reader: IoRead<R>,
+    buffer: Vec<u8>,
+    last_key: Option<ByteString>,
+    /// Key read by [`next_byte_string`](Self::next_byte_string) whose value wasn't a byte string
+    pending_key: Option<ByteString>,
+    done: bool,
+    marker: PhantomData<fn() -> T>,
+}
+
+/// Opens a list and deserializes its elements one at a time.
+///
+/// Only one element is held in memory at any time, making this suitable for lists that are too big to be loaded at once.
+/// Elements are validated exactly like [`from_bytes`] does and the reader must end right after the list.
+/// The reader should be buffered, for example with a [`BufReader`](std::io::BufReader).
+///
+/// # Errors
+/// Fails if the reader doesn't start with a list.
+/// Errors found while iterating are yielded by the iterator, which then stops.
+pub fn stream_list<R, T>(reader: R) -> Result<StreamList<R, T>>
+where
+    R: Read,
+    T: DeserializeOwned,
+{
+    let mut reader = IoRead::new(reader);
+
+    match reader.next_byte()? {
+        b'l' => Ok(StreamList {
+            reader,
+            buffer: Vec::new(),
+            done: false,
+            marker: PhantomData,
+        }),
+        token => Err(Error::unexpected_token("l", token, reader.index)),
+    }
+}
+
+/// Opens a dictionary and deserializes its entries one at a time.
+///
+/// Keys must be sorted like [`from_bytes`] requires.
+/// See [`stream_list`] for details.
+///
+/// # Errors
+/// Fails if the reader doesn't start with a dictionary.
+/// Errors found while iterating are yielded by the iterator, which then stops.
+pub fn stream_dictionary<R, T>(reader: R) -> Result<StreamDictionary<R, T>>
+where
+    R: Read,
+    T: DeserializeOwned,
+{
+    let mut reader = IoRead::new(reader);
+
+    match reader.next_byte()? {
+        b'd' => Ok(StreamDictionary {
+            reader,
+            buffer: Vec::new(),
+            last_key: None,
+            pending_key: None,
+            done: false,
+            marker: PhantomData,
+        }),
+        b'l' => Err(Error::ExpectedDictionaryFoundList),
+        b'i' => Err(Error::ExpectedDictionaryFoundInteger),
+        b'0'..=b'9' => Err(Error::ExpectedDictionaryFoundByteString),
+        token => Err(Error::unexpected_token("d", token, reader.index)),
+    }
+}
+
+impl<R, T> StreamList<R, T>
+where
+    R: Read,
+    T: DeserializeOwned,
+{
+    fn next_element(&mut self) -> Result<Option<T>> {
+        if let b'e' = self.reader.peek_byte()? {
+            self.reader.next_byte()?;
+            self.reader.check_trailing_bytes()?;
+            return Ok(None);
+        }
+
+        self.buffer.clear();
+        self.reader.read_value_into(&mut self.buffer)?;
+        from_bytes(&self.buffer).map(Some)
+    }
+
+    /// Returns a reader over the next element instead of deserializing it, the element must be a byte string.
+    ///
+    /// The content is never buffered, which makes this suitable for byte strings that don't fit in memory.
+    /// Iteration continues normally after it.
+    ///
+    /// # Errors
+    /// Fails with [`Error::InvalidType`] without consuming anything if the next element isn't a byte string.
+    /// Any other error stops the iterator.
+    pub fn next_byte_string(&mut self) -> Result<Option<ByteStringReader<'_, R>>> {
+        if self.done {
+            return Ok(None);
+        }
+
+        let len = match self.reader.peek_byte() {
+            Ok(b'0'..=b'9') => self
+                .reader
+                .next_byte()
+                .and_then(|digit| self.reader.parse_length(digit))
+                .map(Some),
+            Ok(b'e') => self.next_element().map(|_| None),
+            Ok(_) => return Err(Error::InvalidType),
+            Err(error) => Err(error),
+        };
+
+        match len {
+            Ok(Some(len)) => Ok(Some(ByteStringReader::new(&mut self.reader, len))),
+            Ok(None) => {
+                self.done = true;
+                Ok(None)
+            }
+            Err(error) => {
+                self.done = true;
+                Err(error)
+            }
+        }
+    }
+
+    /// Copies the next element straight into the writer, the element must be a byte string.
+    ///
+    /// Returns the number of bytes copied, or None if the list is over.
+    /// See [`next_byte_string`](Self::next_byte_string) for details.
+    ///
+    /// # Errors
+    /// See [`next_byte_string`](Self::next_byte_string).
+    pub fn copy_byte_string<W>(&mut self, writer: &mut W) -> Result<Option<u64>>
+    where
+        W: ?Sized + Write,
+    {
+        let copied = match self.next_byte_string()? {
+            Some(mut reader) => io::copy(&mut reader, writer),
+            None => return Ok(None),
+        };
+
+        copied.map(Some).map_err(|error| {
+            self.done = true;
+            io_error_into_error(error)
+        })
+    }
+}
+
+impl<R, T> Iterator for StreamList<R, T>
+where
+    R: Read,
+    T: DeserializeOwned,
+{
+    type Item = Result<T>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.done {
+            return None;
+        }
+
+        let element = self.next_element().transpose();
+        self.done = !matches!(element, Some(Ok(_)));
+        element
+    }
+}
+
+impl<R, T> StreamDictionary<R, T>
+where
+    R: Read,
+    T: DeserializeOwned,
+{
+    /// Reads the next key, checking it is sorted
+    fn next_key(&mut self) -> Result<Option<ByteString>> {
+        if let Some(key) = self.pending_key.take() {
+            return Ok(Some(key));
+        }
+
+        let key = match self.reader.next_byte()? {
+            b'e' => {
+                self.reader.check_trailing_bytes()?;
+                return Ok(None);
+            }
+            digit @ b'0'..=b'9' => {
+                let len = self.reader.parse_length(digit)?;
+                let mut key = Vec::new();
+                self.reader.read_byte_string_into(len, &mut key)?;
+                ByteString::from_vec(key)
+            }
+            token => {
+                return Err(Error::unexpected_token(
+                    "number between 0-9",
+                    token,
+                    self.reader.index,
+                ))
+            }
+        };
+
+        if let Some(last_key) = &self.last_key {
+            if last_key > &key {
+                return Err(Error::UnsortedKeys);
+            }
+        }
+
+        self.last_key = Some(key.clone());
+
+        Ok(Some(key))
+    }
+
+    fn next_entry(&mut self) -> Result<Option<(ByteString, T)>> {
+        let Some(key) = self.next_key()? else {
+            return Ok(None);
+        };
+
+        self.buffer.clear();
+        self.reader.read_value_into(&mut self.buffer)?;
+        let value = from_bytes(&self.buffer)?;
+
+        Ok(Some((key, value)))
+    }
+
+    /// Returns the next key and a reader over its value instead of deserializing it, the value must be a byte string.
+    ///
+    /// The content is never buffered, which makes this suitable for byte strings that don't fit in memory.
+    /// Iteration continues normally after it.
+    ///
+    /// # Errors
+    /// Fails with [`Error::InvalidType`] without consuming the value if it isn't a byte string,
+    /// the entry is then yielded by the next call to the iterator.
+    /// Any other error stops the iterator.
+    pub fn next_byte_string(&mut self) -> Result<Option<(ByteString, ByteStringReader<'_, R>)>> {
+        if self.done {
+            return Ok(None);
+        }
+
+        let key = match self.next_key() {
+            Ok(Some(key)) => key,
+            Ok(None) => {
+                self.done = true;
+                return Ok(None);
+            }
+            Err(error) => {
+                self.done = true;
+                return Err(error);
+            }
+        };
+
+        let len = match self.reader.peek_byte() {
+            Ok(b'0'..=b'9') => self
+                .reader
+                .next_byte()
+                .and_then(|digit| self.reader.parse_length(digit)),
+            Ok(_) => {
+                self.pending_key = Some(key);
+                return Err(Error::InvalidType);
+            }
+            Err(error) => Err(error),
+        };
+
+        match len {
+            Ok(len) => Ok(Some((key, ByteStringReader::new(&mut self.reader, len)))),
+            Err(error) => {
+                self.done = true;
+                Err(error)
+            }
+        }
+    }
+
+    /// Copies the value of the next entry straight into the writer, the value must be a byte string.
+    ///
+    /// Returns the key and the number of bytes copied, or None if the dictionary is over.
+    /// See [`next_byte_string`](Self::next_byte_string) for details.
+    ///
+    /// # Errors
+    /// See [`next_byte_string`](Self::next_byte_string).
+    pub fn copy_byte_string<W>(&mut self, writer: &mut W) -> Result<Option<(ByteString, u64)>>
+    where
+        W: ?Sized + Write,
+    {
+        let (key, copied) = match self.next_byte_string()? {
+            Some((key, mut reader)) => (key, io::copy(&mut reader, writer)),
+            None => return Ok(None),
+        };
+
+        match copied {
+            Ok(copied) => Ok(Some((key, copied))),
+            Err(error) => {
+                self.done = true;
+                Err(io_error_into_error(error))
+            }
+        }
+    }
+}
+
+impl<R, T> Iterator for StreamDictionary<R, T>
+where
+    R: Read,
+    T: DeserializeOwned,
+{
+    type Item = Result<(ByteString, T)>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.done {
+            return None;
+        }
+
+        let entry = self.next_entry().transpose();
+        self.done = !matches!(entry, Some(Ok(_)));
+        entry
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_derive::Deserialize;
+
+    use super::{stream_dictionary, stream_list};
+    use crate::{ByteString, Error, Result};
+
+    #[derive(Debug, PartialEq, Deserialize)]
+    struct Node {
+        id: String,
+        port: u16,
+    }
+
+    #[test]
+    fn list() {
+        let bytes: &[u8] = b"ld2:id1:a4:porti1eed2:id1:b4:porti2eee";
+
+        let nodes = stream_list::<_, Node>(bytes)
+            .unwrap()
+            .collect::<Result<Vec<_>>>()
+            .unwrap();
+
+        assert_eq!(
+            vec![
+                Node {
+                    id: "a".into(),
+                    port: 1
+                },
+                Node {
+                    id: "b".into(),
+                    port: 2
+                }
+            ],
+            nodes
+        );
+    }
+
+    #[test]
+    fn dictionary() {
+        let bytes: &[u8] = b"d1:ali1ei2ee1:bli3eee";
+
+        let entries = stream_dictionary::<_, Vec<u8>>(bytes)
+            .unwrap()
+            .collect::<Result<Vec<_>>>()
+            .unwrap();
+
+        assert_eq!(
+            vec![
+                (ByteString::from("a"), vec![1, 2]),
+                (ByteString::from("b"), vec![3])
+            ],
+            entries
+        );
+    }
+
+    #[test]
+    fn errors_stop_the_iterator() {
+        let mut iter = stream_list::<_, u8>(&b"li1ei01ei2ee"[..]).unwrap();
+        assert_eq!(1, iter.next().unwrap().unwrap());
+        assert!(matches!(iter.next(), Some(Err(Error::LeadingZero))));
+        assert!(iter.next().is_none());
+
+        let mut iter = stream_dictionary::<_, u8>(&b"d1:bi1e1:ai2ee"[..]).unwrap();
+        assert_eq!(1, iter.next().unwrap().unwrap().1);
+        assert!(matches!(iter.next(), Some(Err(Error::UnsortedKeys))));
+
+        let mut iter = stream_list::<_, u8>(&b"li1e"[..]).unwrap();
+        assert_eq!(1, iter.next().unwrap().unwrap());
+        assert!(matches!(iter.next(), Some(Err(Error::Eof))));
+
+        let mut iter = stream_list::<_, u8>(&b"lei1e"[..]).unwrap();
+        assert!(matches!(iter.next(), Some(Err(Error::TrailingBytes))));
+
+        let mut iter = stream_list::<_, String>(&b"l5:spam"[..]).unwrap();
+        assert!(matches!(
+            iter.next(),
+            Some(Err(Error::EofWhileParsingByteString))
+        ));
+
+        assert!(matches!(
+            stream_dictionary::<_, u8>(&b"li1ee"[..]),
+            Err(Error::ExpectedDictionaryFoundList)
+        ));
+    }
+
+    #[test]
+    fn byte_strings_are_not_buffered() {
+        use std::io::Read;
+
+        let bytes: &[u8] = b"l4:spami1e3:egg0:e";
+        let mut iter = stream_list::<_, u8>(bytes).unwrap();
+
+        let mut blob = iter.next_byte_string().unwrap().unwrap();
+        assert_eq!(4, blob.remaining());
+        let mut start = [0; 2];
+        blob.read_exact(&mut start).unwrap();
+        assert_eq!(b"sp", &start);
+        drop(blob);
+
+        // The rest of the byte string was skipped
+        assert!(matches!(iter.next_byte_string(), Err(Error::InvalidType)));
+        assert_eq!(1, iter.next().unwrap().unwrap());
+
+        let mut copy = Vec::new();
+        assert_eq!(Some(3), iter.copy_byte_string(&mut copy).unwrap());
+        assert_eq!(b"egg".to_vec(), copy);
+        assert_eq!(Some(0), iter.copy_byte_string(&mut copy).unwrap());
+        assert!(iter.copy_byte_string(&mut copy).unwrap().is_none());
+        assert!(iter.next().is_none());
+
+        let bytes: &[u8] = b"d3:agei2e4:blob4:spam4:sizei4ee";
+        let mut iter = stream_dictionary::<_, u8>(bytes).unwrap();
+        let mut copy = Vec::new();
+
+        // The key is kept for the iterator to yield the entry
+        assert!(matches!(iter.next_byte_string(), Err(Error::InvalidType)));
+        assert_eq!((ByteString::from("age"), 2), iter.next().unwrap().unwrap());
+        assert_eq!(
+            Some((ByteString::from("blob"), 4)),
+            iter.copy_byte_string(&mut copy).unwrap()
+        );
+        assert_eq!(b"spam".to_vec(), copy);
+        assert_eq!((ByteString::from("size"), 4), iter.next().unwrap().unwrap());
+
+        let mut iter = stream_list::<_, u8>(&b"l5:spam"[..]).unwrap();
+        assert!(matches!(
+            iter.copy_byte_string(&mut Vec::new()),
+            Err(Error::EofWhileParsingByteString)
+        ));
+    }
+}
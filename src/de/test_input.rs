@@ -0,0 +1,35 @@
+//! Pseudo-random inputs shared by the differential tests.
+
+/// Seed of [`corrupted`]
+const SEED: u64 = 0x2545_F491_4F6C_DD1D;
+
+/// xorshift64 generator, deterministic so that failures can be reproduced
+pub(crate) struct Xorshift(u64);
+
+impl Xorshift {
+    pub(crate) const fn new(seed: u64) -> Self {
+        Self(seed)
+    }
+
+    pub(crate) fn next_u64(&mut self) -> u64 {
+        self.0 ^= self.0 << 13;
+        self.0 ^= self.0 >> 7;
+        self.0 ^= self.0 << 17;
+        self.0
+    }
+}
+
+/// Copies of a valid input with one byte replaced by a token or a stray byte, truncated shortly after it.
+pub(crate) fn corrupted(valid: &[u8], count: usize) -> impl Iterator<Item = Vec<u8>> + '_ {
+    let mut rng = Xorshift::new(SEED);
+
+    (0..count).map(move |_| {
+        let state = rng.next_u64();
+
+        let mut input = valid.to_vec();
+        let position = (state % valid.len() as u64) as usize;
+        input[position] = b"ield0123456789:-x"[(state >> 32) as usize % 17];
+        input.truncate(position + (state >> 48) as usize % 64);
+        input
+    })
+}
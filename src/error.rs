@@ -1,6 +1,9 @@
+use alloc::string::{String, ToString};
 use core::{fmt::Display, str::Utf8Error};
 use serde::{de, ser};
 
+use crate::ByteString;
+
 pub type Result<T, E = Error> = core::result::Result<T, E>;
 
 #[derive(Debug, thiserror::Error)]
@@ -27,6 +30,7 @@ pub enum Error {
     },
     #[error("Error while parsing utf8 value")]
     Utf8(#[from] Utf8Error),
+    #[cfg(feature = "std")]
     #[error("Io Error")]
     Io(#[from] std::io::Error),
     #[error("Unsupported type \"{0}\"")]
@@ -47,11 +51,48 @@ pub enum Error {
     #[error("Unsorted keys")]
     UnsortedKeys,
 
+    /// The same key appears more than once in a dictionary.
+    #[error("Duplicate key \"{0:?}\"")]
+    DuplicateKey(ByteString),
+
+    /// A dictionary key was serialized after a greater one while verifying key order.
+    #[error(
+        "Key \"{key:?}\" was serialized after \"{previous:?}\", dictionary keys must be sorted"
+    )]
+    KeyOutOfOrder {
+        previous: ByteString,
+        key: ByteString,
+    },
+
+    /// The output would grow past the maximum size of the serializer options.
+    #[error("Output exceeds the maximum size of {0} bytes")]
+    MaxSizeExceeded(usize),
+
+    /// Lists and dictionaries are nested deeper than the maximum depth of the serializer options.
+    #[error("Nesting exceeds the maximum depth of {0}")]
+    MaxDepthExceeded(usize),
+
+    /// The reader of a [`ReadBytes`](crate::ReadBytes) ended before its announced length.
+    #[error("Expected {expected} bytes from the reader, found only {found}")]
+    ReaderTooShort { expected: u64, found: u64 },
+
+    /// The reader of a [`ReadBytes`](crate::ReadBytes) has more bytes than its announced length.
+    #[error("Expected {0} bytes from the reader, found more")]
+    ReaderTooLong(u64),
+
+    /// Tokens written with an [`Encoder`](crate::Encoder) don't form a valid value.
+    #[error("Invalid structure: {0}")]
+    InvalidStructure(&'static str),
+
     #[error("Integer out of bound")]
     OutOfBound,
 
     #[error("Map key must be a byte string")]
     MapKeyMustBeByteString,
+
+    /// A byte string was decoded into a fixed size array of a different length.
+    #[error("Expected a byte string of length {expected}, found one of length {found}")]
+    ByteStringLengthMismatch { expected: usize, found: usize },
 }
 
 impl Error {
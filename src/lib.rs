@@ -1,18 +1,51 @@
+#![cfg_attr(not(any(feature = "std", test)), no_std)]
+
+extern crate alloc;
+
 mod byte_string;
+pub mod canonical;
 pub mod de;
 pub mod error;
+#[cfg(feature = "mmap")]
+mod mmap;
 pub mod ser;
 pub mod value;
 
+#[cfg(feature = "derive")]
+#[doc(inline)]
+pub use bde_derive::BencodeSerialize;
 #[doc(inline)]
 pub use byte_string::ByteString;
 #[doc(inline)]
-pub use de::{from_bytes, Deserializer};
+pub use canonical::{canonical_eq, canonicalize, canonicalize_to_writer, CanonicalizeOptions};
+#[cfg(feature = "bytes")]
+#[doc(inline)]
+pub use de::from_buf;
+#[doc(inline)]
+pub use de::{from_bytes, from_bytes_recovering, Deserializer};
+#[cfg(feature = "std")]
+#[doc(inline)]
+pub use de::{stream_dictionary, stream_list};
 #[doc(inline)]
 pub use error::{Error, Result};
+#[cfg(feature = "mmap")]
+#[doc(inline)]
+pub use mmap::{from_file, Mmap};
+#[cfg(feature = "bytes")]
+#[doc(inline)]
+pub use ser::to_buf;
+#[cfg(feature = "std")]
+#[doc(inline)]
+pub use ser::ReadBytes;
 #[doc(inline)]
 pub use ser::{
-    to_bytes, to_bytes_unsorted, to_writer, to_writer_unsorted, Serializer, UnsortedSerializer,
+    encoded_len, to_bytes, to_bytes_unsorted, to_writer, to_writer_unsorted, Encoder, Serializer,
+    SerializerOptions, UnsortedSerializer,
 };
+#[cfg(feature = "bytes")]
 #[doc(inline)]
-pub use value::{from_value, to_value, Dictionary, Integer, Value, ValueSerializer};
+pub use value::{from_bytes_shared, SharedValue};
+#[doc(inline)]
+pub use value::{
+    from_value, to_value, BorrowedValue, Dictionary, Integer, LazyValue, Value, ValueSerializer,
+};
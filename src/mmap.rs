@@ -0,0 +1,112 @@
+use serde::de::{Deserialize, DeserializeOwned};
+use std::{fs::File, ops::Deref, path::Path};
+
+use crate::{from_bytes, Result};
+
+/// A memory-mapped file that can be deserialized without copying it to the heap.
+///
+/// Borrowed types such as `&[u8]`, `&str` and [`BorrowedValue`](crate::BorrowedValue) point straight into the mapping.
+pub struct Mmap {
+    map: memmap2::Mmap,
+}
+
+impl Mmap {
+    /// Memory-maps the file at the given path.
+    ///
+    /// # Safety
+    /// The file must not be modified, truncated or otherwise changed while it is mapped, see [`memmap2::Mmap`].
+    ///
+    /// # Errors
+    /// Fails if the file can't be opened or mapped.
+    pub unsafe fn open<P>(path: P) -> Result<Self>
+    where
+        P: AsRef<Path>,
+    {
+        Self::map(&File::open(path)?)
+    }
+
+    /// Memory-maps an open file.
+    ///
+    /// # Safety
+    /// See [`Mmap::open`].
+    ///
+    /// # Errors
+    /// Fails if the file can't be mapped.
+    pub unsafe fn map(file: &File) -> Result<Self> {
+        Ok(Self {
+            map: memmap2::Mmap::map(file)?,
+        })
+    }
+
+    /// Deserializes the whole mapping, see [`from_bytes`].
+    ///
+    /// # Errors
+    /// See [`from_bytes`].
+    pub fn deserialize<'a, T>(&'a self) -> Result<T>
+    where
+        T: Deserialize<'a>,
+    {
+        from_bytes(&self.map)
+    }
+}
+
+impl Deref for Mmap {
+    type Target = [u8];
+
+    fn deref(&self) -> &Self::Target {
+        &self.map
+    }
+}
+
+impl AsRef<[u8]> for Mmap {
+    fn as_ref(&self) -> &[u8] {
+        &self.map
+    }
+}
+
+/// Memory-maps the file at the given path and deserializes it.
+///
+/// The mapping is dropped before returning, so the value can't borrow from it. Use [`Mmap`] to deserialize borrowed types.
+///
+/// # Safety
+/// See [`Mmap::open`].
+///
+/// # Errors
+/// Fails if the file can't be mapped or deserialized, see [`from_bytes`].
+pub unsafe fn from_file<P, T>(path: P) -> Result<T>
+where
+    P: AsRef<Path>,
+    T: DeserializeOwned,
+{
+    Mmap::open(path)?.deserialize()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{from_file, Mmap};
+    use crate::{BorrowedValue, Value};
+
+    const TORRENT: &str = concat!(env!("CARGO_MANIFEST_DIR"), "/tests/torrents/fedora.torrent");
+
+    #[test]
+    fn deserialize_borrowed() {
+        // SAFETY: test files are never modified
+        let mmap = unsafe { Mmap::open(TORRENT) }.unwrap();
+        let value: BorrowedValue = mmap.deserialize().unwrap();
+
+        let BorrowedValue::Dictionary(dictionary) = &value else {
+            panic!("torrent is not a dictionary")
+        };
+        let Some(BorrowedValue::Dictionary(info)) = dictionary.get(&b"info"[..]) else {
+            panic!("torrent has no info")
+        };
+        let Some(BorrowedValue::ByteString(pieces)) = info.get(&b"pieces"[..]) else {
+            panic!("torrent has no pieces")
+        };
+        assert!(mmap.as_ptr_range().contains(&pieces.as_ptr()));
+
+        // SAFETY: test files are never modified
+        let owned: Value = unsafe { from_file(TORRENT) }.unwrap();
+        assert_eq!(owned, value.to_owned());
+    }
+}
@@ -0,0 +1,176 @@
+use serde::ser::{self, Impossible, Serialize};
+
+use crate::{Error, Result};
+
+/// Serializer that only accepts a single `u8`.
+///
+/// Used to find out whether the elements of a sequence can be written as a byte string.
+pub struct ByteSerializer;
+
+impl ser::Serializer for ByteSerializer {
+    type Ok = u8;
+
+    type Error = Error;
+
+    type SerializeSeq = Impossible<u8, Error>;
+
+    type SerializeTuple = Impossible<u8, Error>;
+
+    type SerializeTupleStruct = Impossible<u8, Error>;
+
+    type SerializeTupleVariant = Impossible<u8, Error>;
+
+    type SerializeMap = Impossible<u8, Error>;
+
+    type SerializeStruct = Impossible<u8, Error>;
+
+    type SerializeStructVariant = Impossible<u8, Error>;
+
+    fn serialize_u8(self, value: u8) -> Result<Self::Ok> {
+        Ok(value)
+    }
+
+    fn serialize_bool(self, _value: bool) -> Result<Self::Ok> {
+        Err(Error::InvalidType)
+    }
+
+    fn serialize_i8(self, _value: i8) -> Result<Self::Ok> {
+        Err(Error::InvalidType)
+    }
+
+    fn serialize_i16(self, _value: i16) -> Result<Self::Ok> {
+        Err(Error::InvalidType)
+    }
+
+    fn serialize_i32(self, _value: i32) -> Result<Self::Ok> {
+        Err(Error::InvalidType)
+    }
+
+    fn serialize_i64(self, _value: i64) -> Result<Self::Ok> {
+        Err(Error::InvalidType)
+    }
+
+    fn serialize_u16(self, _value: u16) -> Result<Self::Ok> {
+        Err(Error::InvalidType)
+    }
+
+    fn serialize_u32(self, _value: u32) -> Result<Self::Ok> {
+        Err(Error::InvalidType)
+    }
+
+    fn serialize_u64(self, _value: u64) -> Result<Self::Ok> {
+        Err(Error::InvalidType)
+    }
+
+    fn serialize_f32(self, _value: f32) -> Result<Self::Ok> {
+        Err(Error::InvalidType)
+    }
+
+    fn serialize_f64(self, _value: f64) -> Result<Self::Ok> {
+        Err(Error::InvalidType)
+    }
+
+    fn serialize_char(self, _value: char) -> Result<Self::Ok> {
+        Err(Error::InvalidType)
+    }
+
+    fn serialize_str(self, _value: &str) -> Result<Self::Ok> {
+        Err(Error::InvalidType)
+    }
+
+    fn serialize_bytes(self, _value: &[u8]) -> Result<Self::Ok> {
+        Err(Error::InvalidType)
+    }
+
+    fn serialize_none(self) -> Result<Self::Ok> {
+        Err(Error::InvalidType)
+    }
+
+    fn serialize_some<T>(self, _value: &T) -> Result<Self::Ok>
+    where
+        T: ?Sized + Serialize,
+    {
+        Err(Error::InvalidType)
+    }
+
+    fn serialize_unit(self) -> Result<Self::Ok> {
+        Err(Error::InvalidType)
+    }
+
+    fn serialize_unit_struct(self, _name: &'static str) -> Result<Self::Ok> {
+        Err(Error::InvalidType)
+    }
+
+    fn serialize_unit_variant(
+        self,
+        _name: &'static str,
+        _variant_index: u32,
+        _variant: &'static str,
+    ) -> Result<Self::Ok> {
+        Err(Error::InvalidType)
+    }
+
+    fn serialize_newtype_struct<T>(self, _name: &'static str, value: &T) -> Result<Self::Ok>
+    where
+        T: ?Sized + Serialize,
+    {
+        value.serialize(self)
+    }
+
+    fn serialize_newtype_variant<T>(
+        self,
+        _name: &'static str,
+        _variant_index: u32,
+        _variant: &'static str,
+        _value: &T,
+    ) -> Result<Self::Ok>
+    where
+        T: ?Sized + Serialize,
+    {
+        Err(Error::InvalidType)
+    }
+
+    fn serialize_seq(self, _len: Option<usize>) -> Result<Self::SerializeSeq> {
+        Err(Error::InvalidType)
+    }
+
+    fn serialize_tuple(self, _len: usize) -> Result<Self::SerializeTuple> {
+        Err(Error::InvalidType)
+    }
+
+    fn serialize_tuple_struct(
+        self,
+        _name: &'static str,
+        _len: usize,
+    ) -> Result<Self::SerializeTupleStruct> {
+        Err(Error::InvalidType)
+    }
+
+    fn serialize_tuple_variant(
+        self,
+        _name: &'static str,
+        _variant_index: u32,
+        _variant: &'static str,
+        _len: usize,
+    ) -> Result<Self::SerializeTupleVariant> {
+        Err(Error::InvalidType)
+    }
+
+    fn serialize_map(self, _len: Option<usize>) -> Result<Self::SerializeMap> {
+        Err(Error::InvalidType)
+    }
+
+    fn serialize_struct(self, _name: &'static str, _len: usize) -> Result<Self::SerializeStruct> {
+        Err(Error::InvalidType)
+    }
+
+    fn serialize_struct_variant(
+        self,
+        _name: &'static str,
+        _variant_index: u32,
+        _variant: &'static str,
+        _len: usize,
+    ) -> Result<Self::SerializeStructVariant> {
+        Err(Error::InvalidType)
+    }
+}
@@ -0,0 +1,289 @@
+use alloc::{format, vec::Vec};
+use core::fmt::Debug;
+
+use crate::{Error, Result};
+
+use super::{map_serializer::KeyOrderCheck, EnumRepresentation, SerializerOptions, Write};
+
+/// Length of a byte string of `len` bytes once encoded, including its length prefix.
+pub(crate) fn byte_string_len(len: usize) -> usize {
+    itoa::Buffer::new().format(len).len() + 1 + len
+}
+
+/// Writes an integer between "i" and "e"
+fn write_integer<W, I>(writer: &mut W, value: I) -> Result<()>
+where
+    W: ?Sized + Write,
+    I: itoa::Integer,
+{
+    let mut buffer = itoa::Buffer::new();
+    writer.write_all(b"i")?;
+    writer.write_all(buffer.format(value).as_bytes())?;
+    writer.write_all(b"e")
+}
+
+/// Writes a byte string prefixed by its length
+fn write_byte_string<W>(writer: &mut W, value: &[u8]) -> Result<()>
+where
+    W: ?Sized + Write,
+{
+    let mut buffer = itoa::Buffer::new();
+    writer.write_all(buffer.format(value.len()).as_bytes())?;
+    writer.write_all(b":")?;
+    writer.write_all(value)
+}
+
+/// Token writing and options shared by [`Serializer`](super::Serializer) and [`UnsortedSerializer`](super::UnsortedSerializer).
+pub trait TokenWriter: Write {
+    fn options(&self) -> &SerializerOptions;
+
+    /// Number of lists and dictionaries currently open
+    fn depth_mut(&mut self) -> &mut usize;
+
+    /// Opens a list or dictionary, failing if it would be nested deeper than allowed
+    fn open(&mut self, token: &[u8]) -> Result<()> {
+        let depth = *self.depth_mut();
+        self.options().check_depth(depth)?;
+        *self.depth_mut() = depth + 1;
+        self.write_all(token)
+    }
+
+    /// Closes the innermost list or dictionary
+    fn close(&mut self) -> Result<()> {
+        *self.depth_mut() -= 1;
+        self.write_all(b"e")
+    }
+
+    /// Writes an integer between "i" and "e"
+    fn write_integer<I: itoa::Integer>(&mut self, value: I) -> Result<()> {
+        write_integer(self, value)
+    }
+
+    /// Writes a byte string prefixed by its length
+    fn write_byte_string(&mut self, value: &[u8]) -> Result<()> {
+        write_byte_string(self, value)
+    }
+
+    /// Writes a `bool` as an integer, if types that bencode can't represent are converted
+    fn write_bool(&mut self, value: bool) -> Result<()> {
+        self.options().check_non_bencode("bool")?;
+        self.write_integer(u8::from(value))
+    }
+
+    /// Writes a `char` as a byte string, if types that bencode can't represent are converted
+    fn write_char(&mut self, value: char) -> Result<()> {
+        self.options().check_non_bencode("char")?;
+        self.write_byte_string(value.encode_utf8(&mut [0; 4]).as_bytes())
+    }
+
+    /// Writes a float as a byte string, if types that bencode can't represent are converted
+    fn write_float<F: Debug>(&mut self, name: &'static str, value: F) -> Result<()> {
+        self.options().check_non_bencode(name)?;
+        self.write_byte_string(format!("{value:?}").as_bytes())
+    }
+
+    /// Opens the list or dictionary holding an enum variant, up to its payload
+    fn write_variant_key(&mut self, variant: &str) -> Result<()> {
+        match self.options().enum_representation {
+            EnumRepresentation::Dictionary => self.open(b"d")?,
+            EnumRepresentation::List => self.open(b"l")?,
+        }
+        self.write_byte_string(variant.as_bytes())
+    }
+}
+
+/// Writes bencode token by token, without going through serde.
+///
+/// Integers and byte strings are formatted like [`UnsortedSerializer`](super::UnsortedSerializer) does and written directly to the writer,
+/// which should be buffered unless it is a `Vec<u8>`. Nesting is always validated, so [`end`](Self::end) fails
+/// with [`Error::InvalidStructure`] without an open list or dictionary, and so does [`finish`](Self::finish) while one is still open.
+///
+/// In [checked](Self::checked) mode, the default in debug builds, dictionaries must also alternate keys and values,
+/// and keys must be sorted, failing with [`Error::KeyOutOfOrder`] or [`Error::DuplicateKey`] otherwise.
+///
+/// ```
+/// use bde::Encoder;
+///
+/// // A KRPC ping response
+/// let mut encoder = Encoder::new(Vec::new()).checked(true);
+/// encoder.begin_dict()?;
+/// encoder.key("r")?;
+/// encoder.begin_dict()?;
+/// encoder.key("id")?;
+/// encoder.bytes("mnopqrstuvwxyz123456")?;
+/// encoder.end()?;
+/// encoder.key("t")?;
+/// encoder.bytes("aa")?;
+/// encoder.key("y")?;
+/// encoder.bytes("r")?;
+/// encoder.end()?;
+///
+/// assert_eq!(
+///     b"d1:rd2:id20:mnopqrstuvwxyz123456e1:t2:aa1:y1:re",
+///     &encoder.finish()?[..]
+/// );
+/// # Ok::<(), bde::Error>(())
+/// ```
+pub struct Encoder<W> {
+    writer: W,
+    checked: bool,
+    /// Lists and dictionaries currently open, innermost last
+    open: Vec<Frame>,
+}
+
+/// A list or dictionary opened by an [`Encoder`]
+enum Frame {
+    List,
+    Dictionary {
+        /// Only used in checked mode
+        keys: KeyOrderCheck,
+        /// Whether a key is waiting for its value
+        pending_key: bool,
+    },
+}
+
+impl<W> Encoder<W>
+where
+    W: Write,
+{
+    pub fn new(writer: W) -> Self {
+        Self {
+            writer,
+            checked: cfg!(debug_assertions),
+            open: Vec::new(),
+        }
+    }
+
+    /// Checks that dictionaries alternate keys and values and that keys are sorted, enabled by default in debug builds.
+    pub fn checked(mut self, enabled: bool) -> Self {
+        self.checked = enabled;
+        self
+    }
+
+    /// Writes an integer of any primitive integer type.
+    ///
+    /// # Errors
+    /// Returns an error if the writer fails or, in checked mode, if a dictionary expects a key instead.
+    pub fn int<I: itoa::Integer>(&mut self, value: I) -> Result<()> {
+        self.value()?;
+        write_integer(&mut self.writer, value)
+    }
+
+    /// Writes a byte string.
+    ///
+    /// # Errors
+    /// Returns an error if the writer fails or, in checked mode, if a dictionary expects a key instead.
+    pub fn bytes(&mut self, value: impl AsRef<[u8]>) -> Result<()> {
+        self.value()?;
+        write_byte_string(&mut self.writer, value.as_ref())
+    }
+
+    /// Opens a list, closed by [`end`](Self::end).
+    ///
+    /// # Errors
+    /// Returns an error if the writer fails or, in checked mode, if a dictionary expects a key instead.
+    pub fn begin_list(&mut self) -> Result<()> {
+        self.value()?;
+        self.writer.write_all(b"l")?;
+        self.open.push(Frame::List);
+        Ok(())
+    }
+
+    /// Opens a dictionary, closed by [`end`](Self::end).
+    ///
+    /// # Errors
+    /// Returns an error if the writer fails or, in checked mode, if a dictionary expects a key instead.
+    pub fn begin_dict(&mut self) -> Result<()> {
+        self.value()?;
+        self.writer.write_all(b"d")?;
+        self.open.push(Frame::Dictionary {
+            keys: KeyOrderCheck::default(),
+            pending_key: false,
+        });
+        Ok(())
+    }
+
+    /// Writes the key of the next entry of the innermost dictionary.
+    ///
+    /// # Errors
+    /// Returns an error if the innermost open value isn't a dictionary or the writer fails.
+    /// In checked mode, also fails if the previous key has no value or this key isn't greater than the previous one.
+    pub fn key(&mut self, key: impl AsRef<[u8]>) -> Result<()> {
+        let key = key.as_ref();
+
+        let Some(Frame::Dictionary { keys, pending_key }) = self.open.last_mut() else {
+            return Err(Error::InvalidStructure("key outside of a dictionary"));
+        };
+
+        if self.checked {
+            if *pending_key {
+                return Err(Error::InvalidStructure("key without a value"));
+            }
+            keys.check(key)?;
+        }
+
+        *pending_key = true;
+        write_byte_string(&mut self.writer, key)
+    }
+
+    /// Closes the innermost list or dictionary.
+    ///
+    /// # Errors
+    /// Returns an error if nothing is open or the writer fails.
+    /// In checked mode, also fails if the last key of a dictionary has no value.
+    /// Nothing is closed on failure.
+    pub fn end(&mut self) -> Result<()> {
+        match self.open.last() {
+            None => {
+                return Err(Error::InvalidStructure(
+                    "end without an open list or dictionary",
+                ))
+            }
+            Some(Frame::Dictionary {
+                pending_key: true, ..
+            }) if self.checked => return Err(Error::InvalidStructure("key without a value")),
+            Some(_) => {}
+        }
+
+        self.writer.write_all(b"e")?;
+        self.open.pop();
+        Ok(())
+    }
+
+    /// Number of lists and dictionaries currently open
+    pub fn depth(&self) -> usize {
+        self.open.len()
+    }
+
+    pub fn get_ref(&self) -> &W {
+        &self.writer
+    }
+
+    pub fn get_mut(&mut self) -> &mut W {
+        &mut self.writer
+    }
+
+    /// Returns the writer once every list and dictionary is closed.
+    ///
+    /// # Errors
+    /// Returns an error if a list or dictionary is still open.
+    pub fn finish(self) -> Result<W> {
+        if self.open.is_empty() {
+            Ok(self.writer)
+        } else {
+            Err(Error::InvalidStructure("unclosed list or dictionary"))
+        }
+    }
+
+    /// Consumes the value expected after a key, failing in checked mode if a key is expected instead
+    fn value(&mut self) -> Result<()> {
+        if let Some(Frame::Dictionary { pending_key, .. }) = self.open.last_mut() {
+            if self.checked && !*pending_key {
+                return Err(Error::InvalidStructure("value without a key"));
+            }
+            *pending_key = false;
+        }
+
+        Ok(())
+    }
+}
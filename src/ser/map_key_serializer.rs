@@ -41,36 +41,36 @@ impl ser::Serializer for MapKeySerializer {
         Err(Error::MapKeyMustBeByteString)
     }
 
-    fn serialize_i8(self, _value: i8) -> Result<Self::Ok> {
-        Err(Error::MapKeyMustBeByteString)
+    fn serialize_i8(self, value: i8) -> Result<Self::Ok> {
+        Ok(ByteString::from(itoa::Buffer::new().format(value)))
     }
 
-    fn serialize_i16(self, _value: i16) -> Result<Self::Ok> {
-        Err(Error::MapKeyMustBeByteString)
+    fn serialize_i16(self, value: i16) -> Result<Self::Ok> {
+        Ok(ByteString::from(itoa::Buffer::new().format(value)))
     }
 
-    fn serialize_i32(self, _value: i32) -> Result<Self::Ok> {
-        Err(Error::MapKeyMustBeByteString)
+    fn serialize_i32(self, value: i32) -> Result<Self::Ok> {
+        Ok(ByteString::from(itoa::Buffer::new().format(value)))
     }
 
-    fn serialize_i64(self, _value: i64) -> Result<Self::Ok> {
-        Err(Error::MapKeyMustBeByteString)
+    fn serialize_i64(self, value: i64) -> Result<Self::Ok> {
+        Ok(ByteString::from(itoa::Buffer::new().format(value)))
     }
 
-    fn serialize_u8(self, _value: u8) -> Result<Self::Ok> {
-        Err(Error::MapKeyMustBeByteString)
+    fn serialize_u8(self, value: u8) -> Result<Self::Ok> {
+        Ok(ByteString::from(itoa::Buffer::new().format(value)))
     }
 
-    fn serialize_u16(self, _value: u16) -> Result<Self::Ok> {
-        Err(Error::MapKeyMustBeByteString)
+    fn serialize_u16(self, value: u16) -> Result<Self::Ok> {
+        Ok(ByteString::from(itoa::Buffer::new().format(value)))
     }
 
-    fn serialize_u32(self, _value: u32) -> Result<Self::Ok> {
-        Err(Error::MapKeyMustBeByteString)
+    fn serialize_u32(self, value: u32) -> Result<Self::Ok> {
+        Ok(ByteString::from(itoa::Buffer::new().format(value)))
     }
 
-    fn serialize_u64(self, _value: u64) -> Result<Self::Ok> {
-        Err(Error::MapKeyMustBeByteString)
+    fn serialize_u64(self, value: u64) -> Result<Self::Ok> {
+        Ok(ByteString::from(itoa::Buffer::new().format(value)))
     }
 
     fn serialize_f32(self, _value: f32) -> Result<Self::Ok> {
@@ -89,9 +89,9 @@ impl ser::Serializer for MapKeySerializer {
         Err(Error::MapKeyMustBeByteString)
     }
 
-    fn serialize_some<T: ?Sized>(self, _value: &T) -> Result<Self::Ok>
+    fn serialize_some<T>(self, _value: &T) -> Result<Self::Ok>
     where
-        T: Serialize,
+        T: ?Sized + Serialize,
     {
         Err(Error::MapKeyMustBeByteString)
     }
@@ -108,23 +108,19 @@ impl ser::Serializer for MapKeySerializer {
         self,
         _name: &'static str,
         _variant_index: u32,
-        _variant: &'static str,
+        variant: &'static str,
     ) -> Result<Self::Ok> {
-        Err(Error::MapKeyMustBeByteString)
+        self.serialize_str(variant)
     }
 
-    fn serialize_newtype_struct<T: ?Sized>(
-        self,
-        _name: &'static str,
-        _value: &T,
-    ) -> Result<Self::Ok>
+    fn serialize_newtype_struct<T>(self, _name: &'static str, value: &T) -> Result<Self::Ok>
     where
-        T: Serialize,
+        T: ?Sized + Serialize,
     {
-        Err(Error::MapKeyMustBeByteString)
+        value.serialize(self)
     }
 
-    fn serialize_newtype_variant<T: ?Sized>(
+    fn serialize_newtype_variant<T>(
         self,
         _name: &'static str,
         _variant_index: u32,
@@ -132,7 +128,7 @@ impl ser::Serializer for MapKeySerializer {
         _value: &T,
     ) -> Result<Self::Ok>
     where
-        T: Serialize,
+        T: ?Sized + Serialize,
     {
         Err(Error::MapKeyMustBeByteString)
     }
@@ -1,162 +1,443 @@
+use alloc::{collections::BTreeSet, vec::Vec};
+use core::{cmp::Ordering, ops::Range};
 use serde::ser::{self, Serialize, SerializeMap};
-use std::{collections::BTreeMap, io::Write};
 
-use crate::{value::ValueSerializer, Dictionary, Error, Result};
+use crate::{ByteString, Error, Result};
 
-use super::{map_key_serializer::MapKeySerializer, Serializer, UnsortedSerializer};
+use super::{
+    encoder::TokenWriter, map_key_serializer::MapKeySerializer, none_serializer::is_none, KeyOrder,
+    Serializer, UnsortedSerializer, Write,
+};
 
+/// Checks that the keys of a dictionary written as they arrive are sorted.
+#[derive(Default)]
+pub(crate) struct KeyOrderCheck {
+    previous: Option<Vec<u8>>,
+}
+
+impl KeyOrderCheck {
+    /// Fails if a key isn't greater than the previous one
+    pub(crate) fn check(&mut self, key: &[u8]) -> Result<()> {
+        let Some(previous) = &mut self.previous else {
+            self.previous = Some(key.to_vec());
+            return Ok(());
+        };
+
+        match previous.as_slice().cmp(key) {
+            Ordering::Less => {
+                previous.clear();
+                previous.extend_from_slice(key);
+                Ok(())
+            }
+            Ordering::Equal => Err(Error::DuplicateKey(ByteString::from(key))),
+            Ordering::Greater => Err(Error::KeyOutOfOrder {
+                previous: ByteString::from(previous.as_slice()),
+                key: ByteString::from(key),
+            }),
+        }
+    }
+}
+
+/// Checks that the keys of a dictionary written as they arrive are unique, whatever their order.
+#[derive(Default)]
+pub(crate) struct UniqueKeyCheck {
+    keys: BTreeSet<Vec<u8>>,
+}
+
+impl UniqueKeyCheck {
+    /// Fails if a key was already seen
+    pub(crate) fn check(&mut self, key: &[u8]) -> Result<()> {
+        if self.keys.contains(key) {
+            return Err(Error::DuplicateKey(ByteString::from(key)));
+        }
+
+        self.keys.insert(key.to_vec());
+        Ok(())
+    }
+}
+
+/// Encodes dictionary entries into the buffer of a [`Serializer`], reordering them at the end if needed.
+///
+/// Unless keys are sorted, entries are written directly like [`UnsortedMapSerializer`] does.
 pub struct MapSerializer<'a, W: 'a> {
     serializer: &'a mut Serializer<W>,
-    dictionary: Dictionary,
+    /// Offset in the buffer where the first entry starts
+    start: usize,
+    entries: Vec<Entry>,
+    /// Whether every key so far was greater than the previous one
+    sorted: bool,
+    /// Key passed to `serialize_key`, held back until we know its value isn't None.
+    pending_key: Option<ByteString>,
+    /// Whether this is the payload of a struct variant, which is wrapped in a list or dictionary
+    variant: bool,
+    /// Only used when verifying key order
+    keys: KeyOrderCheck,
+}
+
+/// Location of an encoded entry in the buffer
+struct Entry {
+    /// The whole entry, key included
+    bytes: Range<usize>,
+    /// The key without its length
+    key: Range<usize>,
 }
 
-impl<'a, W> MapSerializer<'a, W> {
-    pub fn new(serializer: &'a mut Serializer<W>) -> Self {
-        Self {
+impl<'a, W> MapSerializer<'a, W>
+where
+    W: Write,
+{
+    pub fn new(serializer: &'a mut Serializer<W>) -> Result<Self> {
+        serializer.open(b"d")?;
+
+        if serializer.options.key_order == KeyOrder::Sort {
+            serializer.sorting += 1;
+        }
+
+        Ok(Self {
+            start: serializer.output.buffer.len(),
             serializer,
-            dictionary: BTreeMap::new(),
+            entries: Vec::new(),
+            sorted: true,
+            pending_key: None,
+            variant: false,
+            keys: KeyOrderCheck::default(),
+        })
+    }
+
+    /// Starts the payload of a struct variant, written as `{variant: {fields}}` or `[variant, {fields}]`
+    pub fn new_variant(serializer: &'a mut Serializer<W>, variant: &str) -> Result<Self> {
+        serializer.write_variant_key(variant)?;
+
+        let mut map = Self::new(serializer)?;
+        map.variant = true;
+        Ok(map)
+    }
+
+    fn push_entry<V>(&mut self, key: ByteString, value: &V) -> Result<()>
+    where
+        V: ?Sized + Serialize,
+    {
+        // Bencode has no null value, None entries are left out
+        if is_none(value) {
+            return Ok(());
+        }
+
+        match self.serializer.options.key_order {
+            KeyOrder::Sort => {}
+            KeyOrder::Trust => {
+                self.serializer.write_byte_string(&key)?;
+                return value.serialize(&mut *self.serializer);
+            }
+            KeyOrder::Verify => {
+                self.keys.check(&key)?;
+                self.serializer.write_byte_string(&key)?;
+                return value.serialize(&mut *self.serializer);
+            }
         }
+
+        if let Some(previous) = self.entries.last() {
+            match self.serializer.output.buffer[previous.key.clone()].cmp(&key) {
+                Ordering::Less => {}
+                Ordering::Equal => return Err(Error::DuplicateKey(key)),
+                Ordering::Greater => self.sorted = false,
+            }
+        }
+
+        let start = self.serializer.output.buffer.len();
+        self.serializer.write_byte_string(&key)?;
+        let key_end = self.serializer.output.buffer.len();
+        let key_range = key_end - key.len()..key_end;
+
+        value.serialize(&mut *self.serializer)?;
+
+        self.entries.push(Entry {
+            bytes: start..self.serializer.output.buffer.len(),
+            key: key_range,
+        });
+
+        Ok(())
+    }
+
+    fn finish(mut self) -> Result<()> {
+        if self.serializer.options.key_order == KeyOrder::Sort {
+            if !self.sorted {
+                self.sort_entries()?;
+            }
+
+            self.serializer.close()?;
+            self.serializer.end_sorting()?;
+        } else {
+            self.serializer.close()?;
+        }
+
+        if self.variant {
+            self.serializer.close()?;
+        }
+
+        Ok(())
+    }
+
+    /// Sorts the entries by key, failing if two of them are equal
+    fn sort_entries(&mut self) -> Result<()> {
+        let buffer = &self.serializer.output.buffer;
+
+        self.entries
+            .sort_unstable_by(|a, b| buffer[a.key.clone()].cmp(&buffer[b.key.clone()]));
+
+        if let Some(pair) = self
+            .entries
+            .windows(2)
+            .find(|pair| buffer[pair[0].key.clone()] == buffer[pair[1].key.clone()])
+        {
+            return Err(Error::DuplicateKey(ByteString::from(
+                &buffer[pair[0].key.clone()],
+            )));
+        }
+
+        let entries = self.serializer.output.buffer.split_off(self.start);
+        for entry in &self.entries {
+            let bytes = entry.bytes.start - self.start..entry.bytes.end - self.start;
+            self.serializer
+                .output
+                .buffer
+                .extend_from_slice(&entries[bytes]);
+        }
+
+        Ok(())
     }
 }
 
 impl<'a, W> ser::SerializeMap for MapSerializer<'a, W>
 where
-    W: std::io::Write,
+    W: Write,
 {
     type Ok = ();
 
     type Error = Error;
 
-    fn serialize_key<T: ?Sized>(&mut self, _key: &T) -> Result<()>
+    fn serialize_key<T>(&mut self, key: &T) -> Result<()>
     where
-        T: Serialize,
+        T: ?Sized + Serialize,
     {
-        unreachable!()
+        self.pending_key = Some(key.serialize(MapKeySerializer::new())?);
+        Ok(())
     }
 
-    fn serialize_value<T: ?Sized>(&mut self, _value: &T) -> Result<()>
+    fn serialize_value<T>(&mut self, value: &T) -> Result<()>
     where
-        T: Serialize,
+        T: ?Sized + Serialize,
     {
-        unreachable!()
+        let key = self.pending_key.take().ok_or(Error::Syntax)?;
+        self.push_entry(key, value)
     }
 
-    fn serialize_entry<K: ?Sized, V: ?Sized>(
-        &mut self,
-        key: &K,
-        value: &V,
-    ) -> Result<(), Self::Error>
+    fn serialize_entry<K, V>(&mut self, key: &K, value: &V) -> Result<(), Self::Error>
     where
-        K: Serialize,
-        V: Serialize,
+        K: ?Sized + Serialize,
+        V: ?Sized + Serialize,
     {
         let key = key.serialize(MapKeySerializer::new())?;
-        let value = value.serialize(ValueSerializer::new())?;
-
-        self.dictionary.insert(key, value);
-
-        Ok(())
+        self.push_entry(key, value)
     }
 
     fn end(self) -> Result<Self::Ok> {
-        for (key, value) in self.dictionary {
-            key.serialize(&mut *self.serializer)?;
-            value.serialize(&mut *self.serializer)?;
-        }
+        self.finish()
+    }
+}
 
-        self.serializer.write_all(b"e")?;
+impl<'a, W> ser::SerializeStruct for MapSerializer<'a, W>
+where
+    W: Write,
+{
+    type Ok = ();
 
-        Ok(())
+    type Error = Error;
+
+    fn serialize_field<T>(&mut self, key: &'static str, value: &T) -> Result<(), Self::Error>
+    where
+        T: ?Sized + Serialize,
+    {
+        self.serialize_entry(key, value)
+    }
+
+    fn end(self) -> Result<Self::Ok, Self::Error> {
+        self.finish()
     }
 }
 
-impl<'a, W> ser::SerializeStruct for MapSerializer<'a, W>
+impl<'a, W> ser::SerializeStructVariant for MapSerializer<'a, W>
 where
-    W: std::io::Write,
+    W: Write,
 {
     type Ok = ();
 
     type Error = Error;
 
-    fn serialize_field<T: ?Sized>(
-        &mut self,
-        key: &'static str,
-        value: &T,
-    ) -> Result<(), Self::Error>
+    fn serialize_field<T>(&mut self, key: &'static str, value: &T) -> Result<(), Self::Error>
     where
-        T: Serialize,
+        T: ?Sized + Serialize,
     {
         self.serialize_entry(key, value)
     }
 
     fn end(self) -> Result<Self::Ok, Self::Error> {
-        SerializeMap::end(self)
+        self.finish()
     }
 }
 
 pub struct UnsortedMapSerializer<'a, W: 'a> {
     pub serializer: &'a mut UnsortedSerializer<W>,
+    /// Key passed to `serialize_key`, held back until we know its value isn't None.
+    pending_key: Option<ByteString>,
+    /// Whether this is the payload of a struct variant, which is wrapped in a list or dictionary
+    variant: bool,
+    /// Only used when verifying key order
+    keys: KeyOrderCheck,
+    /// Only used when trusting key order but rejecting duplicate keys
+    unique_keys: UniqueKeyCheck,
 }
 
-impl<'a, W> UnsortedMapSerializer<'a, W> {
-    pub fn new(serializer: &'a mut UnsortedSerializer<W>) -> Self {
-        Self { serializer }
+impl<'a, W> UnsortedMapSerializer<'a, W>
+where
+    W: Write,
+{
+    pub fn new(serializer: &'a mut UnsortedSerializer<W>) -> Result<Self> {
+        serializer.open(b"d")?;
+
+        Ok(Self {
+            serializer,
+            pending_key: None,
+            variant: false,
+            keys: KeyOrderCheck::default(),
+            unique_keys: UniqueKeyCheck::default(),
+        })
+    }
+
+    /// Starts the payload of a struct variant, written as `{variant: {fields}}` or `[variant, {fields}]`
+    pub fn new_variant(serializer: &'a mut UnsortedSerializer<W>, variant: &str) -> Result<Self> {
+        serializer.write_variant_key(variant)?;
+
+        let mut map = Self::new(serializer)?;
+        map.variant = true;
+        Ok(map)
+    }
+
+    /// Checks that a key is greater than the previous one unless the serializer trusts key order
+    ///
+    /// Keys can't be sorted without buffering, so [`KeyOrder::Sort`] verifies them too.
+    fn check_key(&mut self, key: &[u8]) -> Result<()> {
+        match self.serializer.options().key_order {
+            KeyOrder::Trust if self.serializer.reject_duplicate_keys => self.unique_keys.check(key),
+            KeyOrder::Trust => Ok(()),
+            KeyOrder::Sort | KeyOrder::Verify => self.keys.check(key),
+        }
+    }
+
+    fn finish(self) -> Result<()> {
+        self.serializer.close()?;
+
+        if self.variant {
+            self.serializer.close()?;
+        }
+
+        Ok(())
     }
 }
 
 impl<'a, W> ser::SerializeMap for UnsortedMapSerializer<'a, W>
 where
-    W: std::io::Write,
+    W: Write,
 {
     type Ok = ();
 
     type Error = Error;
 
-    fn serialize_key<T: ?Sized>(&mut self, key: &T) -> Result<()>
+    fn serialize_key<T>(&mut self, key: &T) -> Result<()>
     where
-        T: Serialize,
+        T: ?Sized + Serialize,
     {
-        let key = key.serialize(MapKeySerializer::new())?;
-        key.serialize(&mut *self.serializer)
+        self.pending_key = Some(key.serialize(MapKeySerializer::new())?);
+        Ok(())
     }
 
-    fn serialize_value<T: ?Sized>(&mut self, value: &T) -> Result<()>
+    fn serialize_value<T>(&mut self, value: &T) -> Result<()>
     where
-        T: Serialize,
+        T: ?Sized + Serialize,
     {
+        let key = self.pending_key.take().ok_or(Error::Syntax)?;
+
+        // Bencode has no null value, None entries are left out
+        if is_none(value) {
+            return Ok(());
+        }
+
+        self.check_key(&key)?;
+        key.serialize(&mut *self.serializer)?;
         value.serialize(&mut *self.serializer)
     }
 
-    fn end(self) -> Result<Self::Ok> {
-        self.serializer.write_all(b"e")?;
+    fn serialize_entry<K, V>(&mut self, key: &K, value: &V) -> Result<()>
+    where
+        K: ?Sized + Serialize,
+        V: ?Sized + Serialize,
+    {
+        if is_none(value) {
+            return Ok(());
+        }
 
-        Ok(())
+        let key = key.serialize(MapKeySerializer::new())?;
+        self.check_key(&key)?;
+        key.serialize(&mut *self.serializer)?;
+        value.serialize(&mut *self.serializer)
+    }
+
+    fn end(self) -> Result<Self::Ok> {
+        self.finish()
     }
 }
 
 impl<'a, W> ser::SerializeStruct for UnsortedMapSerializer<'a, W>
 where
-    W: std::io::Write,
+    W: Write,
 {
     type Ok = ();
 
     type Error = Error;
 
-    fn serialize_field<T: ?Sized>(
-        &mut self,
-        key: &'static str,
-        value: &T,
-    ) -> Result<(), Self::Error>
+    fn serialize_field<T>(&mut self, key: &'static str, value: &T) -> Result<(), Self::Error>
     where
-        T: Serialize,
+        T: ?Sized + Serialize,
     {
+        if is_none(value) {
+            return Ok(());
+        }
+
+        self.check_key(key.as_bytes())?;
         key.serialize(&mut *self.serializer)?;
         value.serialize(&mut *self.serializer)
     }
 
     fn end(self) -> Result<Self::Ok, Self::Error> {
-        self.serializer.write_all(b"e")?;
+        self.finish()
+    }
+}
 
-        Ok(())
+impl<'a, W> ser::SerializeStructVariant for UnsortedMapSerializer<'a, W>
+where
+    W: Write,
+{
+    type Ok = ();
+
+    type Error = Error;
+
+    fn serialize_field<T>(&mut self, key: &'static str, value: &T) -> Result<(), Self::Error>
+    where
+        T: ?Sized + Serialize,
+    {
+        ser::SerializeStruct::serialize_field(self, key, value)
+    }
+
+    fn end(self) -> Result<Self::Ok, Self::Error> {
+        self.finish()
     }
 }
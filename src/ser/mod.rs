@@ -1,18 +1,36 @@
+use alloc::vec::Vec;
 use serde::ser::Serialize;
 
 use crate::Result;
 
+pub(crate) mod byte_serializer;
+pub(crate) mod encoder;
 pub(crate) mod map_key_serializer;
-mod map_serializer;
+pub(crate) mod map_serializer;
+pub(crate) mod none_serializer;
+mod options;
+#[cfg(feature = "std")]
+pub(crate) mod read_bytes;
+mod seq_serializer;
 mod serializer;
 mod unsorted_serializer;
+#[cfg(feature = "std")]
+mod vectored;
+mod write;
 
+pub use encoder::Encoder;
+pub use options::{EnumRepresentation, KeyOrder, NonBencodeTypes, SerializerOptions};
+#[cfg(feature = "std")]
+pub use read_bytes::ReadBytes;
 pub use serializer::Serializer;
 pub use unsorted_serializer::UnsortedSerializer;
+#[cfg(feature = "std")]
+pub use vectored::VectoredOutput;
+pub use write::Write;
 
 pub fn to_writer_unsorted<W, T>(writer: W, value: &T) -> Result<()>
 where
-    W: std::io::Write,
+    W: Write,
     T: ?Sized + Serialize,
 {
     let mut ser = UnsortedSerializer::new(writer);
@@ -23,14 +41,15 @@ pub fn to_bytes_unsorted<T>(value: &T) -> Result<Vec<u8>>
 where
     T: ?Sized + Serialize,
 {
-    let mut writer = Vec::with_capacity(128);
-    to_writer_unsorted(&mut writer, value)?;
-    Ok(writer)
+    let mut ser =
+        UnsortedSerializer::in_memory(SerializerOptions::new().key_order(KeyOrder::Trust));
+    value.serialize(&mut ser)?;
+    Ok(ser.into_bytes())
 }
 
 pub fn to_writer<W, T>(writer: W, value: &T) -> Result<()>
 where
-    W: std::io::Write,
+    W: Write,
     T: ?Sized + Serialize,
 {
     let mut ser = Serializer::new(writer);
@@ -41,7 +60,690 @@ pub fn to_bytes<T>(value: &T) -> Result<Vec<u8>>
 where
     T: ?Sized + Serialize,
 {
-    let mut writer = Vec::with_capacity(128);
-    to_writer(&mut writer, value)?;
-    Ok(writer)
+    SerializerOptions::new().to_bytes(value)
+}
+
+/// Returns the length of the output of [`to_bytes`] for a value, without writing it anywhere.
+///
+/// The value is serialized as usual, but only the length of every token is counted.
+/// Dictionaries aren't sorted, their keys are only kept to detect duplicates.
+///
+/// # Errors
+/// Returns the same errors as [`to_bytes`].
+pub fn encoded_len<T>(value: &T) -> Result<usize>
+where
+    T: ?Sized + Serialize,
+{
+    let mut len = 0;
+    value.serialize(
+        &mut UnsortedSerializer::new(ByteCounter(&mut len))
+            .buffer_size(0)
+            .reject_duplicate_keys(),
+    )?;
+    Ok(len)
+}
+
+/// Writer that only counts the bytes written to it
+struct ByteCounter<'a>(&'a mut usize);
+
+impl Write for ByteCounter<'_> {
+    #[inline]
+    fn write_all(&mut self, buf: &[u8]) -> Result<()> {
+        *self.0 += buf.len();
+        Ok(())
+    }
+}
+
+/// Serializes a value at the end of a [`BufMut`](bytes::BufMut).
+#[cfg(feature = "bytes")]
+pub fn to_buf<B, T>(buf: &mut B, value: &T) -> Result<()>
+where
+    B: bytes::BufMut,
+    T: ?Sized + Serialize,
+{
+    to_writer(bytes::BufMut::writer(buf), value)
+}
+
+#[cfg(test)]
+mod tests {
+    use serde::ser::Serialize;
+    use serde_derive::Serialize;
+
+    use super::{
+        to_bytes, to_bytes_unsorted, to_writer, EnumRepresentation, KeyOrder, NonBencodeTypes,
+        Serializer, SerializerOptions, UnsortedSerializer, Write,
+    };
+    use crate::{to_value, ByteString, Error, Result, Value, ValueSerializer};
+
+    fn to_bytes_u8_seq_as_bytes<T: Serialize>(value: &T) -> Vec<u8> {
+        let mut writer = Vec::new();
+        value
+            .serialize(&mut Serializer::new(&mut writer).u8_seq_as_bytes(true))
+            .unwrap();
+        writer
+    }
+
+    #[test]
+    fn u8_seq_is_a_list_by_default() {
+        assert_eq!(b"li1ei2ee".to_vec(), to_bytes(&vec![1u8, 2]).unwrap());
+    }
+
+    #[test]
+    fn u8_seq_as_bytes() {
+        #[derive(Serialize)]
+        struct Node {
+            id: [u8; 4],
+            ports: Vec<u16>,
+        }
+
+        let node = Node {
+            id: *b"spam",
+            ports: vec![6881],
+        };
+
+        assert_eq!(b"4:spam".to_vec(), to_bytes_u8_seq_as_bytes(b"spam"));
+        assert_eq!(b"0:".to_vec(), to_bytes_u8_seq_as_bytes(&Vec::<u8>::new()));
+        assert_eq!(
+            b"d2:id4:spam5:portsli6881eee".to_vec(),
+            to_bytes_u8_seq_as_bytes(&node)
+        );
+
+        let mut writer = Vec::new();
+        (1u8, 300u16)
+            .serialize(&mut UnsortedSerializer::new(&mut writer).u8_seq_as_bytes(true))
+            .unwrap();
+        assert_eq!(b"li1ei300ee".to_vec(), writer);
+
+        assert_eq!(
+            Value::ByteString(ByteString::from("spam")),
+            b"spam"
+                .serialize(ValueSerializer::new().u8_seq_as_bytes(true))
+                .unwrap()
+        );
+    }
+
+    #[test]
+    fn none_fields_are_skipped() {
+        #[derive(Serialize)]
+        struct Torrent {
+            comment: Option<String>,
+            length: u64,
+            private: Option<u8>,
+        }
+
+        let torrent = Torrent {
+            comment: None,
+            length: 10,
+            private: Some(1),
+        };
+
+        assert_eq!(
+            b"d6:lengthi10e7:privatei1ee".to_vec(),
+            to_bytes(&torrent).unwrap()
+        );
+        assert_eq!(
+            b"d6:lengthi10e7:privatei1ee".to_vec(),
+            to_bytes_unsorted(&torrent).unwrap()
+        );
+
+        let map = std::collections::BTreeMap::from([("a", None), ("b", Some(1))]);
+        assert_eq!(b"d1:bi1ee".to_vec(), to_bytes(&map).unwrap());
+        assert_eq!(b"d1:bi1ee".to_vec(), to_bytes_unsorted(&map).unwrap());
+        assert_eq!(
+            Value::Dictionary(
+                [(ByteString::from("b"), Value::Integer(1i64.into()))]
+                    .into_iter()
+                    .collect()
+            ),
+            to_value(&map).unwrap()
+        );
+    }
+
+    #[test]
+    fn none_in_seq() {
+        let list = vec![Some(1), None, Some(2)];
+
+        assert!(matches!(to_bytes(&list), Err(Error::Unsupported("None"))));
+
+        let mut writer = Vec::new();
+        list.serialize(&mut Serializer::new(&mut writer).skip_none_in_seq(true))
+            .unwrap();
+        assert_eq!(b"li1ei2ee".to_vec(), writer);
+
+        assert_eq!(
+            Value::List(vec![
+                Value::Integer(1i64.into()),
+                Value::Integer(2i64.into())
+            ]),
+            list.serialize(ValueSerializer::new().skip_none_in_seq(true))
+                .unwrap()
+        );
+    }
+
+    #[test]
+    fn nested_maps_are_sorted() {
+        #[derive(Serialize)]
+        struct File {
+            path: Vec<String>,
+            length: u64,
+        }
+
+        #[derive(Serialize)]
+        struct Wrapper(File);
+
+        let map = std::collections::HashMap::from([("b", 2), ("a", 1), ("c", 3)]);
+        let file = File {
+            path: vec!["spam".into()],
+            length: 4,
+        };
+
+        assert_eq!(
+            b"ld1:ai1e1:bi2e1:ci3eee".to_vec(),
+            to_bytes(&vec![&map]).unwrap()
+        );
+        assert_eq!(
+            b"d1:ai1e1:bi2e1:ci3ee".to_vec(),
+            to_bytes(&Some(&map)).unwrap()
+        );
+        assert_eq!(
+            b"d6:lengthi4e4:pathl4:spamee".to_vec(),
+            to_bytes(&Wrapper(file)).unwrap()
+        );
+        assert_eq!(
+            b"d1:xd1:ai1e1:bi2e1:ci3ee1:yd1:ai1e1:bi2e1:ci3eee".to_vec(),
+            to_bytes(&std::collections::HashMap::from([("y", &map), ("x", &map)])).unwrap()
+        );
+    }
+
+    #[test]
+    fn sorted_output_matches_value() {
+        for torrent in std::fs::read_dir("tests/torrents").unwrap() {
+            let bytes = std::fs::read(torrent.unwrap().path()).unwrap();
+            let value: Value = crate::from_bytes(&bytes).unwrap();
+
+            assert_eq!(bytes, to_bytes(&value).unwrap());
+        }
+    }
+
+    #[test]
+    fn enum_variants() {
+        #[derive(Serialize)]
+        enum Message {
+            Ping,
+            Piece(u32),
+            Request(u32, u32),
+            Reject { length: u32, index: u32 },
+        }
+
+        let cases: [(Message, &[u8]); 4] = [
+            (Message::Ping, b"4:Ping"),
+            (Message::Piece(1), b"d5:Piecei1ee"),
+            (Message::Request(1, 2), b"d7:Requestli1ei2eee"),
+            (
+                Message::Reject {
+                    length: 2,
+                    index: 1,
+                },
+                b"d6:Rejectd5:indexi1e6:lengthi2eee",
+            ),
+        ];
+
+        for (message, expected) in cases {
+            assert_eq!(expected, to_bytes(&message).unwrap());
+            assert_eq!(expected, to_bytes(&to_value(&message).unwrap()).unwrap());
+        }
+
+        assert_eq!(
+            b"d6:Rejectd6:lengthi2e5:indexi1eee".to_vec(),
+            to_bytes_unsorted(&Message::Reject {
+                length: 2,
+                index: 1
+            })
+            .unwrap()
+        );
+        assert_eq!(
+            b"ld1:ad5:Piecei3ee1:bd6:Rejectd5:indexi1e6:lengthi2eeeee".to_vec(),
+            to_bytes(&vec![std::collections::HashMap::from([
+                (
+                    "b",
+                    Message::Reject {
+                        length: 2,
+                        index: 1
+                    }
+                ),
+                ("a", Message::Piece(3)),
+            ])])
+            .unwrap()
+        );
+    }
+
+    #[test]
+    fn verify_key_order() {
+        #[derive(Serialize)]
+        struct Sorted {
+            a: u8,
+            b: std::collections::BTreeMap<&'static str, u8>,
+        }
+
+        #[derive(Serialize)]
+        struct Unsorted {
+            b: u8,
+            a: u8,
+        }
+
+        fn verified<T: Serialize>(value: &T) -> crate::Result<Vec<u8>> {
+            let mut writer = Vec::new();
+            value.serialize(&mut UnsortedSerializer::new(&mut writer).verify_key_order(true))?;
+            Ok(writer)
+        }
+
+        let sorted = Sorted {
+            a: 1,
+            b: [("a", 2), ("b", 3)].into(),
+        };
+        assert_eq!(to_bytes(&sorted).unwrap(), verified(&sorted).unwrap());
+
+        assert!(matches!(
+            verified(&Unsorted { b: 1, a: 2 }),
+            Err(Error::KeyOutOfOrder { previous, key }) if previous == "b" && key == "a"
+        ));
+        assert!(to_bytes_unsorted(&Unsorted { b: 1, a: 2 }).is_ok());
+
+        let mut writer = Vec::new();
+        let result = serde::Serializer::collect_map(
+            &mut UnsortedSerializer::new(&mut writer).verify_key_order(true),
+            [("a", 1), ("a", 2)],
+        );
+        assert!(matches!(result, Err(Error::DuplicateKey(key)) if key == "a"));
+    }
+
+    #[test]
+    fn duplicate_keys() {
+        #[derive(Serialize)]
+        struct Torrent {
+            name: &'static str,
+            #[serde(flatten)]
+            extra: std::collections::BTreeMap<&'static str, &'static str>,
+        }
+
+        let torrent = Torrent {
+            name: "spam",
+            extra: [("comment", "eggs"), ("name", "ham")].into(),
+        };
+
+        assert!(matches!(to_bytes(&torrent), Err(Error::DuplicateKey(key)) if key == "name"));
+        assert!(matches!(to_value(&torrent), Err(Error::DuplicateKey(key)) if key == "name"));
+
+        let entries = [("b", 1), ("a", 2), ("b", 3)];
+        let mut writer = Vec::new();
+        let result = serde::Serializer::collect_map(&mut Serializer::new(&mut writer), entries);
+        assert!(matches!(result, Err(Error::DuplicateKey(key)) if key == "b"));
+        assert!(matches!(
+            serde::Serializer::collect_map(ValueSerializer::new(), entries),
+            Err(Error::DuplicateKey(key)) if key == "b"
+        ));
+    }
+
+    #[test]
+    fn encoded_len() {
+        #[derive(Serialize)]
+        struct Node {
+            port: u16,
+            id: &'static str,
+            offsets: Vec<i64>,
+            extra: std::collections::HashMap<&'static str, Option<u64>>,
+        }
+
+        let node = Node {
+            port: 6881,
+            id: "spam",
+            offsets: vec![i64::MIN, -1, 0, 10, i64::MAX],
+            extra: [("a", None), ("b", Some(u64::MAX))].into(),
+        };
+
+        assert_eq!(
+            to_bytes(&node).unwrap().len(),
+            super::encoded_len(&node).unwrap()
+        );
+        assert_eq!(
+            to_bytes(&node).unwrap().len(),
+            to_value(&node).unwrap().encoded_len()
+        );
+        assert!(matches!(
+            super::encoded_len(&1.0),
+            Err(Error::Unsupported("f64"))
+        ));
+
+        #[derive(Serialize)]
+        struct Flattened {
+            b: u8,
+            #[serde(flatten)]
+            extra: std::collections::HashMap<&'static str, u8>,
+        }
+
+        let mut flattened = Flattened {
+            b: 1,
+            extra: [("c", 2), ("a", 3)].into(),
+        };
+        assert_eq!(
+            to_bytes(&flattened).unwrap().len(),
+            super::encoded_len(&flattened).unwrap()
+        );
+        flattened.extra.insert("b", 4);
+        for result in [
+            to_bytes(&flattened).map(|bytes| bytes.len()),
+            super::encoded_len(&flattened),
+        ] {
+            assert!(matches!(result, Err(Error::DuplicateKey(key)) if key == "b"));
+        }
+
+        for torrent in std::fs::read_dir("tests/torrents").unwrap() {
+            let bytes = std::fs::read(torrent.unwrap().path()).unwrap();
+            let value: Value = crate::from_bytes(&bytes).unwrap();
+
+            assert_eq!(bytes.len(), super::encoded_len(&value).unwrap());
+            assert_eq!(bytes.len(), value.encoded_len());
+        }
+    }
+
+    #[test]
+    fn serializer_options() {
+        #[derive(Serialize)]
+        struct Unsorted {
+            b: u8,
+            a: u8,
+        }
+
+        #[derive(Serialize)]
+        enum Message {
+            Request(u32, u32),
+            Reject { index: u32 },
+        }
+
+        fn unsorted_bytes<T: Serialize>(options: SerializerOptions, value: &T) -> Result<Vec<u8>> {
+            let mut writer = Vec::new();
+            value.serialize(&mut UnsortedSerializer::with_options(&mut writer, options))?;
+            Ok(writer)
+        }
+
+        let unsorted = Unsorted { b: 1, a: 2 };
+        let options = SerializerOptions::new();
+
+        assert_eq!(
+            b"d1:ai2e1:bi1ee".to_vec(),
+            options.to_bytes(&unsorted).unwrap()
+        );
+        let trust = options.key_order(KeyOrder::Trust);
+        assert_eq!(
+            b"d1:bi1e1:ai2ee".to_vec(),
+            trust.to_bytes(&unsorted).unwrap()
+        );
+        assert_eq!(
+            b"d1:bi1e1:ai2ee".to_vec(),
+            unsorted_bytes(trust, &unsorted).unwrap()
+        );
+        let verify = options.key_order(KeyOrder::Verify);
+        for result in [
+            verify.to_bytes(&unsorted),
+            unsorted_bytes(verify, &unsorted),
+            unsorted_bytes(options, &unsorted),
+            verify.to_value(&unsorted).map(|_| Vec::new()),
+        ] {
+            assert!(matches!(result, Err(Error::KeyOutOfOrder { .. })));
+        }
+
+        // Sorting isn't possible without buffering, keys are verified instead
+        let map: std::collections::HashMap<String, u8> =
+            ('a'..='z').map(|key| (key.to_string(), 0)).collect();
+        assert!(options.to_bytes(&map).is_ok());
+        assert!(matches!(
+            unsorted_bytes(options, &map),
+            Err(Error::KeyOutOfOrder { .. })
+        ));
+        let single = std::collections::HashMap::from([("a", 1)]);
+        assert_eq!(
+            b"d1:ai1ee".to_vec(),
+            unsorted_bytes(options, &single).unwrap()
+        );
+
+        let list = options.enum_representation(EnumRepresentation::List);
+        let messages = [Message::Request(1, 2), Message::Reject { index: 3 }];
+        let expected = b"ll7:Requestli1ei2eeel6:Rejectd5:indexi3eeee".to_vec();
+        assert_eq!(expected, list.to_bytes(&messages).unwrap());
+        assert_eq!(expected, unsorted_bytes(list, &messages).unwrap());
+        assert_eq!(
+            expected,
+            to_bytes(&list.to_value(&messages).unwrap()).unwrap()
+        );
+
+        let value = (true, 'é', 0.1f32, 1e300f64);
+        assert!(matches!(to_bytes(&value), Err(Error::Unsupported("bool"))));
+        let convert = options.non_bencode_types(NonBencodeTypes::Convert);
+        let expected = b"li1e2:\xc3\xa93:0.15:1e300e".to_vec();
+        assert_eq!(expected, convert.to_bytes(&value).unwrap());
+        assert_eq!(expected, unsorted_bytes(convert, &value).unwrap());
+        assert_eq!(
+            expected,
+            to_bytes(&convert.to_value(&value).unwrap()).unwrap()
+        );
+        assert!(matches!(
+            convert.to_bytes(&()),
+            Err(Error::Unsupported("unit"))
+        ));
+
+        let nested = vec![Some(std::collections::BTreeMap::from([("a", vec![1])]))];
+        let shallow = options.max_depth(Some(2));
+        assert!(options.max_depth(Some(3)).to_bytes(&nested).is_ok());
+        assert!(options.max_depth(Some(3)).to_value(&nested).is_ok());
+        assert!(matches!(
+            shallow.to_bytes(&nested),
+            Err(Error::MaxDepthExceeded(2))
+        ));
+        assert!(matches!(
+            unsorted_bytes(shallow, &nested),
+            Err(Error::MaxDepthExceeded(2))
+        ));
+        assert!(matches!(
+            shallow.to_value(&nested),
+            Err(Error::MaxDepthExceeded(2))
+        ));
+        assert!(matches!(
+            options.max_depth(Some(1)).to_bytes(&messages[0]),
+            Err(Error::MaxDepthExceeded(1))
+        ));
+
+        let len = to_bytes(&nested).unwrap().len();
+        assert!(options.max_size(Some(len)).to_bytes(&nested).is_ok());
+        let small = options.max_size(Some(len - 1));
+        assert!(matches!(
+            small.to_bytes(&nested),
+            Err(Error::MaxSizeExceeded(_))
+        ));
+        assert!(matches!(
+            unsorted_bytes(small, &nested),
+            Err(Error::MaxSizeExceeded(_))
+        ));
+    }
+
+    #[test]
+    fn buffered_output() {
+        struct Chunks<'a>(&'a mut Vec<Vec<u8>>);
+
+        impl Write for Chunks<'_> {
+            fn write_all(&mut self, buf: &[u8]) -> crate::Result<()> {
+                self.0.push(buf.to_vec());
+                Ok(())
+            }
+        }
+
+        let bytes = include_bytes!("../../tests/torrents/debian.torrent");
+        let value: Value = crate::from_bytes(bytes).unwrap();
+
+        for buffer_size in [0, 1, 100, 8 * 1024, usize::MAX] {
+            let mut chunks = Vec::new();
+            value
+                .serialize(&mut Serializer::new(Chunks(&mut chunks)).buffer_size(buffer_size))
+                .unwrap();
+            assert_eq!(bytes.to_vec(), chunks.concat());
+
+            let mut unsorted_chunks = Vec::new();
+            (1, &value, "spam")
+                .serialize(
+                    &mut UnsortedSerializer::new(Chunks(&mut unsorted_chunks))
+                        .buffer_size(buffer_size),
+                )
+                .unwrap();
+            assert_eq!(
+                to_bytes_unsorted(&(1, &value, "spam")).unwrap(),
+                unsorted_chunks.concat()
+            );
+
+            if buffer_size >= bytes.len() {
+                assert_eq!(1, chunks.len());
+                assert_eq!(1, unsorted_chunks.len());
+            }
+        }
+
+        // Every top-level value is written out as soon as it is complete
+        let mut chunks = Vec::new();
+        let mut serializer = UnsortedSerializer::new(Chunks(&mut chunks));
+        vec![1, 2].serialize(&mut serializer).unwrap();
+        "spam".serialize(&mut serializer).unwrap();
+        drop(serializer);
+        assert_eq!(b"li1ei2ee4:spam".to_vec(), chunks.concat());
+    }
+
+    #[test]
+    fn custom_writer() {
+        struct Counter<'a>(&'a mut usize);
+
+        impl Write for Counter<'_> {
+            fn write_all(&mut self, buf: &[u8]) -> crate::Result<()> {
+                *self.0 += buf.len();
+                Ok(())
+            }
+        }
+
+        let value = (1u8, "spam", vec![-3i64]);
+        let mut len = 0;
+        to_writer(Counter(&mut len), &value).unwrap();
+
+        assert_eq!(to_bytes(&value).unwrap().len(), len);
+    }
+
+    #[cfg(feature = "std")]
+    #[test]
+    fn read_bytes() {
+        use super::ReadBytes;
+
+        #[derive(Serialize)]
+        struct File<R: std::io::Read> {
+            path: &'static str,
+            content: ReadBytes<R>,
+        }
+
+        let content = vec![0xAA; 20 * 1024];
+        let expected = [
+            &b"d7:content20480:"[..],
+            &content,
+            &b"4:path8:spam.txte"[..],
+        ]
+        .concat();
+
+        let file = File {
+            path: "spam.txt",
+            content: ReadBytes::new(content.len() as u64, &content[..]),
+        };
+        assert_eq!(expected, to_bytes(&file).unwrap());
+
+        let mut unsorted = Vec::new();
+        super::to_writer_unsorted(&mut unsorted, &(ReadBytes::new(4, &b"eggs"[..]), "spam"))
+            .unwrap();
+        assert_eq!(b"l4:eggs4:spame", &unsorted[..]);
+
+        assert_eq!(
+            Value::ByteString(ByteString::from(content.clone())),
+            to_value(ReadBytes::new(content.len() as u64, &content[..])).unwrap()
+        );
+
+        assert!(matches!(
+            to_bytes(&ReadBytes::new(10, &b"eggs"[..])),
+            Err(Error::ReaderTooShort {
+                expected: 10,
+                found: 4
+            })
+        ));
+        assert!(matches!(
+            to_bytes_unsorted(&ReadBytes::new(3, &b"eggs"[..])),
+            Err(Error::ReaderTooLong(3))
+        ));
+        assert!(matches!(
+            to_value(ReadBytes::new(0, &b"eggs"[..])),
+            Err(Error::ReaderTooLong(0))
+        ));
+        assert_eq!(b"0:", &to_bytes(&ReadBytes::new(0, &b""[..])).unwrap()[..]);
+    }
+
+    #[test]
+    fn encoder() {
+        use super::Encoder;
+
+        let mut encoder = Encoder::new(Vec::new());
+        encoder.begin_dict().unwrap();
+        encoder.key("a").unwrap();
+        encoder.int(i64::MIN).unwrap();
+        encoder.key("b").unwrap();
+        encoder.begin_list().unwrap();
+        encoder.int(u128::MAX).unwrap();
+        encoder.bytes(b"spam").unwrap();
+        encoder.begin_dict().unwrap();
+        assert_eq!(3, encoder.depth());
+        encoder.end().unwrap();
+        encoder.end().unwrap();
+        encoder.end().unwrap();
+        encoder.int(0u8).unwrap();
+
+        assert_eq!(
+            &b"d1:ai-9223372036854775808e1:bli340282366920938463463374607431768211455e4:spamdeeei0e"[..],
+            &encoder.finish().unwrap()[..]
+        );
+
+        // Nesting is always validated
+        let mut encoder = Encoder::new(Vec::new()).checked(false);
+        assert!(matches!(encoder.end(), Err(Error::InvalidStructure(_))));
+        assert!(matches!(encoder.key("a"), Err(Error::InvalidStructure(_))));
+        encoder.begin_list().unwrap();
+        assert!(matches!(encoder.key("a"), Err(Error::InvalidStructure(_))));
+        encoder.end().unwrap();
+        encoder.begin_dict().unwrap();
+        encoder.key("b").unwrap();
+        encoder.key("a").unwrap();
+        encoder.int(1).unwrap();
+        assert!(matches!(encoder.finish(), Err(Error::InvalidStructure(_))));
+
+        // Checked mode also validates dictionaries
+        let mut encoder = Encoder::new(Vec::new()).checked(true);
+        encoder.begin_dict().unwrap();
+        assert!(matches!(encoder.int(1), Err(Error::InvalidStructure(_))));
+        encoder.key("b").unwrap();
+        assert!(matches!(encoder.key("c"), Err(Error::InvalidStructure(_))));
+        assert!(matches!(encoder.end(), Err(Error::InvalidStructure(_))));
+        // The dictionary is still open after a failed end
+        assert_eq!(1, encoder.depth());
+        encoder.bytes("spam").unwrap();
+        encoder.end().unwrap();
+        assert_eq!(b"d1:b4:spame", &encoder.finish().unwrap()[..]);
+
+        let mut encoder = Encoder::new(Vec::new()).checked(true);
+        encoder.begin_dict().unwrap();
+        encoder.key("b").unwrap();
+        encoder.bytes("spam").unwrap();
+        assert!(matches!(encoder.key("b"), Err(Error::DuplicateKey(_))));
+        assert!(matches!(encoder.key("a"), Err(Error::KeyOutOfOrder { .. })));
+        encoder.key("c").unwrap();
+        encoder.begin_list().unwrap();
+        encoder.end().unwrap();
+        encoder.end().unwrap();
+        assert_eq!(b"d1:b4:spam1:clee", &encoder.finish().unwrap()[..]);
+    }
 }
@@ -0,0 +1,184 @@
+use serde::ser::{self, Impossible, Serialize};
+
+use crate::{Error, Result};
+
+/// Serializer that checks whether a value is `None`.
+///
+/// Compound values are rejected as soon as they start, so probing a value never walks its content.
+pub struct NoneSerializer;
+
+impl ser::Serializer for NoneSerializer {
+    type Ok = bool;
+
+    type Error = Error;
+
+    type SerializeSeq = Impossible<bool, Error>;
+
+    type SerializeTuple = Impossible<bool, Error>;
+
+    type SerializeTupleStruct = Impossible<bool, Error>;
+
+    type SerializeTupleVariant = Impossible<bool, Error>;
+
+    type SerializeMap = Impossible<bool, Error>;
+
+    type SerializeStruct = Impossible<bool, Error>;
+
+    type SerializeStructVariant = Impossible<bool, Error>;
+
+    fn serialize_none(self) -> Result<Self::Ok> {
+        Ok(true)
+    }
+
+    fn serialize_u8(self, _value: u8) -> Result<Self::Ok> {
+        Ok(false)
+    }
+
+    fn serialize_bool(self, _value: bool) -> Result<Self::Ok> {
+        Ok(false)
+    }
+
+    fn serialize_i8(self, _value: i8) -> Result<Self::Ok> {
+        Ok(false)
+    }
+
+    fn serialize_i16(self, _value: i16) -> Result<Self::Ok> {
+        Ok(false)
+    }
+
+    fn serialize_i32(self, _value: i32) -> Result<Self::Ok> {
+        Ok(false)
+    }
+
+    fn serialize_i64(self, _value: i64) -> Result<Self::Ok> {
+        Ok(false)
+    }
+
+    fn serialize_u16(self, _value: u16) -> Result<Self::Ok> {
+        Ok(false)
+    }
+
+    fn serialize_u32(self, _value: u32) -> Result<Self::Ok> {
+        Ok(false)
+    }
+
+    fn serialize_u64(self, _value: u64) -> Result<Self::Ok> {
+        Ok(false)
+    }
+
+    fn serialize_f32(self, _value: f32) -> Result<Self::Ok> {
+        Ok(false)
+    }
+
+    fn serialize_f64(self, _value: f64) -> Result<Self::Ok> {
+        Ok(false)
+    }
+
+    fn serialize_char(self, _value: char) -> Result<Self::Ok> {
+        Ok(false)
+    }
+
+    fn serialize_str(self, _value: &str) -> Result<Self::Ok> {
+        Ok(false)
+    }
+
+    fn serialize_bytes(self, _value: &[u8]) -> Result<Self::Ok> {
+        Ok(false)
+    }
+
+    fn serialize_some<T>(self, _value: &T) -> Result<Self::Ok>
+    where
+        T: ?Sized + Serialize,
+    {
+        Ok(false)
+    }
+
+    fn serialize_unit(self) -> Result<Self::Ok> {
+        Ok(false)
+    }
+
+    fn serialize_unit_struct(self, _name: &'static str) -> Result<Self::Ok> {
+        Ok(false)
+    }
+
+    fn serialize_unit_variant(
+        self,
+        _name: &'static str,
+        _variant_index: u32,
+        _variant: &'static str,
+    ) -> Result<Self::Ok> {
+        Ok(false)
+    }
+
+    fn serialize_newtype_struct<T>(self, _name: &'static str, value: &T) -> Result<Self::Ok>
+    where
+        T: ?Sized + Serialize,
+    {
+        value.serialize(self)
+    }
+
+    fn serialize_newtype_variant<T>(
+        self,
+        _name: &'static str,
+        _variant_index: u32,
+        _variant: &'static str,
+        _value: &T,
+    ) -> Result<Self::Ok>
+    where
+        T: ?Sized + Serialize,
+    {
+        Ok(false)
+    }
+
+    fn serialize_seq(self, _len: Option<usize>) -> Result<Self::SerializeSeq> {
+        Err(Error::InvalidType)
+    }
+
+    fn serialize_tuple(self, _len: usize) -> Result<Self::SerializeTuple> {
+        Err(Error::InvalidType)
+    }
+
+    fn serialize_tuple_struct(
+        self,
+        _name: &'static str,
+        _len: usize,
+    ) -> Result<Self::SerializeTupleStruct> {
+        Err(Error::InvalidType)
+    }
+
+    fn serialize_tuple_variant(
+        self,
+        _name: &'static str,
+        _variant_index: u32,
+        _variant: &'static str,
+        _len: usize,
+    ) -> Result<Self::SerializeTupleVariant> {
+        Err(Error::InvalidType)
+    }
+
+    fn serialize_map(self, _len: Option<usize>) -> Result<Self::SerializeMap> {
+        Err(Error::InvalidType)
+    }
+
+    fn serialize_struct(self, _name: &'static str, _len: usize) -> Result<Self::SerializeStruct> {
+        Err(Error::InvalidType)
+    }
+
+    fn serialize_struct_variant(
+        self,
+        _name: &'static str,
+        _variant_index: u32,
+        _variant: &'static str,
+        _len: usize,
+    ) -> Result<Self::SerializeStructVariant> {
+        Err(Error::InvalidType)
+    }
+}
+
+/// Returns true if the value serializes as `None`.
+pub fn is_none<T>(value: &T) -> bool
+where
+    T: ?Sized + Serialize,
+{
+    matches!(value.serialize(NoneSerializer), Ok(true))
+}
@@ -0,0 +1,203 @@
+use alloc::vec::Vec;
+use serde::Serialize;
+
+use crate::{Error, Result, Value, ValueSerializer};
+
+use super::{Serializer, Write};
+
+/// How a serializer orders the keys of dictionaries.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum KeyOrder {
+    /// Buffers dictionaries and sorts their keys, as bencode requires.
+    #[default]
+    Sort,
+    /// Writes keys in the order they are serialized, trusting them to be sorted.
+    Trust,
+    /// Writes keys in the order they are serialized,
+    /// failing with [`Error::KeyOutOfOrder`] or [`Error::DuplicateKey`] as soon as one isn't greater than the previous one.
+    Verify,
+}
+
+/// How newtype, tuple and struct enum variants are encoded.
+///
+/// Unit variants are always encoded as the name of the variant.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum EnumRepresentation {
+    /// A dictionary with a single entry, `{variant: payload}`.
+    #[default]
+    Dictionary,
+    /// A list of the name of the variant and its payload, `[variant, payload]`.
+    List,
+}
+
+/// How types that bencode can't represent are encoded.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum NonBencodeTypes {
+    /// Fails with [`Error::Unsupported`].
+    #[default]
+    Reject,
+    /// Encodes `bool` as the integer 0 or 1, `char` as its UTF-8 byte string,
+    /// and floats as the byte string of their shortest representation, like `0.1` or `1e300`.
+    ///
+    /// Unit values are still rejected.
+    Convert,
+}
+
+/// Settings shared by [`Serializer`], [`UnsortedSerializer`](super::UnsortedSerializer) and [`ValueSerializer`].
+///
+/// ```
+/// use bde::ser::{KeyOrder, SerializerOptions};
+/// use serde_derive::Serialize;
+///
+/// #[derive(Serialize)]
+/// struct Ping<'a> {
+///     id: &'a str,
+///     port: u16,
+/// }
+///
+/// // Stream the output without buffering, but fail on unsorted keys or packets too large for a datagram
+/// let options = SerializerOptions::new()
+///     .key_order(KeyOrder::Verify)
+///     .max_size(Some(1472));
+///
+/// let ping = Ping { id: "spam", port: 6881 };
+/// assert_eq!(b"d2:id4:spam4:porti6881ee", &options.to_bytes(&ping).unwrap()[..]);
+/// assert!(options.to_bytes(&vec![0u8; 1500]).is_err());
+/// ```
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct SerializerOptions {
+    pub(crate) key_order: KeyOrder,
+    pub(crate) u8_seq_as_bytes: bool,
+    pub(crate) skip_none_in_seq: bool,
+    pub(crate) enum_representation: EnumRepresentation,
+    pub(crate) non_bencode_types: NonBencodeTypes,
+    pub(crate) max_size: Option<usize>,
+    pub(crate) max_depth: Option<usize>,
+}
+
+impl SerializerOptions {
+    pub const fn new() -> Self {
+        Self {
+            key_order: KeyOrder::Sort,
+            u8_seq_as_bytes: false,
+            skip_none_in_seq: false,
+            enum_representation: EnumRepresentation::Dictionary,
+            non_bencode_types: NonBencodeTypes::Reject,
+            max_size: None,
+            max_depth: None,
+        }
+    }
+
+    /// Sets how the keys of dictionaries are ordered, see [`KeyOrder`].
+    ///
+    /// [`ValueSerializer`] always sorts keys, but still fails on unsorted keys with [`KeyOrder::Verify`].
+    pub const fn key_order(mut self, key_order: KeyOrder) -> Self {
+        self.key_order = key_order;
+        self
+    }
+
+    /// Encodes sequences, tuples and arrays made only of `u8` as byte strings instead of lists of integers.
+    ///
+    /// See [`UnsortedSerializer::u8_seq_as_bytes`](super::UnsortedSerializer::u8_seq_as_bytes).
+    pub const fn u8_seq_as_bytes(mut self, enabled: bool) -> Self {
+        self.u8_seq_as_bytes = enabled;
+        self
+    }
+
+    /// Skips `None` elements of sequences and tuples instead of failing with [`Error::Unsupported`].
+    ///
+    /// See [`UnsortedSerializer::skip_none_in_seq`](super::UnsortedSerializer::skip_none_in_seq).
+    pub const fn skip_none_in_seq(mut self, enabled: bool) -> Self {
+        self.skip_none_in_seq = enabled;
+        self
+    }
+
+    /// Sets how enum variants with a payload are encoded, see [`EnumRepresentation`].
+    pub const fn enum_representation(mut self, representation: EnumRepresentation) -> Self {
+        self.enum_representation = representation;
+        self
+    }
+
+    /// Sets how types that bencode can't represent are encoded, see [`NonBencodeTypes`].
+    pub const fn non_bencode_types(mut self, policy: NonBencodeTypes) -> Self {
+        self.non_bencode_types = policy;
+        self
+    }
+
+    /// Fails with [`Error::MaxSizeExceeded`] before the output grows past this many bytes.
+    ///
+    /// [`ValueSerializer`] doesn't produce any bytes and ignores this setting.
+    pub const fn max_size(mut self, max_size: Option<usize>) -> Self {
+        self.max_size = max_size;
+        self
+    }
+
+    /// Fails with [`Error::MaxDepthExceeded`] when lists and dictionaries are nested deeper than this.
+    ///
+    /// A depth of 0 only allows byte strings and integers.
+    /// The dictionary or list wrapping an enum variant counts as one level.
+    pub const fn max_depth(mut self, max_depth: Option<usize>) -> Self {
+        self.max_depth = max_depth;
+        self
+    }
+
+    /// Serializes a value into a writer with these options.
+    ///
+    /// # Errors
+    /// Returns an error if the value can't be serialized with these options or the writer fails.
+    pub fn to_writer<W, T>(&self, writer: W, value: &T) -> Result<()>
+    where
+        W: Write,
+        T: ?Sized + Serialize,
+    {
+        value.serialize(&mut Serializer::with_options(writer, *self))
+    }
+
+    /// Serializes a value into a byte vector with these options.
+    ///
+    /// # Errors
+    /// Returns an error if the value can't be serialized with these options.
+    pub fn to_bytes<T>(&self, value: &T) -> Result<Vec<u8>>
+    where
+        T: ?Sized + Serialize,
+    {
+        let mut ser = Serializer::in_memory(*self);
+        value.serialize(&mut ser)?;
+        Ok(ser.into_bytes())
+    }
+
+    /// Converts a value into a [`Value`] with these options.
+    ///
+    /// # Errors
+    /// Returns an error if the value can't be serialized with these options.
+    pub fn to_value<T>(&self, value: &T) -> Result<Value>
+    where
+        T: ?Sized + Serialize,
+    {
+        value.serialize(ValueSerializer::with_options(*self))
+    }
+
+    /// Fails if the output would grow to `size` bytes while it is limited to less
+    pub(crate) fn check_size(&self, size: usize) -> Result<()> {
+        match self.max_size {
+            Some(max_size) if size > max_size => Err(Error::MaxSizeExceeded(max_size)),
+            _ => Ok(()),
+        }
+    }
+
+    /// Fails if a list or dictionary can't be opened inside `depth` others
+    pub(crate) fn check_depth(&self, depth: usize) -> Result<()> {
+        match self.max_depth {
+            Some(max_depth) if depth >= max_depth => Err(Error::MaxDepthExceeded(max_depth)),
+            _ => Ok(()),
+        }
+    }
+
+    /// Fails unless types that bencode can't represent are converted
+    pub(crate) fn check_non_bencode(&self, name: &'static str) -> Result<()> {
+        match self.non_bencode_types {
+            NonBencodeTypes::Reject => Err(Error::Unsupported(name)),
+            NonBencodeTypes::Convert => Ok(()),
+        }
+    }
+}
@@ -0,0 +1,382 @@
+use serde::ser::{self, Impossible, Serialize};
+
+use crate::{Error, Result};
+
+use super::Write;
+
+/// Name of the newtype struct [`ReadBytes`] serializes as, recognized by the serializers of this crate.
+pub(crate) const NAME: &str = "$bde::private::ReadBytes";
+
+/// A byte string of a known length, copied from a reader while it is serialized instead of being loaded in memory first.
+///
+/// [`Serializer`](crate::Serializer), [`UnsortedSerializer`](crate::UnsortedSerializer) and [`ValueSerializer`](crate::ValueSerializer)
+/// write the length prefix followed by exactly `len` bytes from the reader, failing with [`Error::ReaderTooShort`]
+/// if it ends early or [`Error::ReaderTooLong`] if it has more to give.
+/// The reader is consumed by serialization, so a `ReadBytes` can only be serialized once.
+///
+/// [`Serializer`](crate::Serializer) still holds the content in memory when it is part of a dictionary being sorted,
+/// use [`UnsortedSerializer`](crate::UnsortedSerializer) or [`KeyOrder::Trust`](super::KeyOrder::Trust) to stream it.
+/// Other serializers see a sequence of the length followed by chunks of bytes.
+///
+/// ```
+/// use bde::ReadBytes;
+/// use serde_derive::Serialize;
+///
+/// #[derive(Serialize)]
+/// struct Archive {
+///     content: ReadBytes<&'static [u8]>,
+///     name: &'static str,
+/// }
+///
+/// // Usually a `File` and its length from `metadata`
+/// let archive = Archive {
+///     content: ReadBytes::new(4, &b"eggs"[..]),
+///     name: "spam.txt",
+/// };
+///
+/// assert_eq!(b"d7:content4:eggs4:name8:spam.txte", &bde::to_bytes(&archive).unwrap()[..]);
+/// ```
+pub struct ReadBytes<R> {
+    len: u64,
+    reader: core::cell::RefCell<R>,
+}
+
+impl<R> ReadBytes<R>
+where
+    R: std::io::Read,
+{
+    /// Creates a byte string of `len` bytes read from `reader`.
+    pub const fn new(len: u64, reader: R) -> Self {
+        Self {
+            len,
+            reader: core::cell::RefCell::new(reader),
+        }
+    }
+
+    /// Length of the byte string
+    pub const fn len(&self) -> u64 {
+        self.len
+    }
+
+    pub const fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// Returns the reader, positioned after whatever serialization read from it.
+    pub fn into_inner(self) -> R {
+        self.reader.into_inner()
+    }
+}
+
+impl<R> Serialize for ReadBytes<R>
+where
+    R: std::io::Read,
+{
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: ser::Serializer,
+    {
+        serializer.serialize_newtype_struct(NAME, &Chunks(self))
+    }
+}
+
+/// The content of a [`ReadBytes`], as its length followed by chunks read until the length is reached.
+///
+/// If the reader has more bytes, a last chunk holds some of them for the serializer to fail on.
+struct Chunks<'a, R>(&'a ReadBytes<R>);
+
+impl<R> Serialize for Chunks<'_, R>
+where
+    R: std::io::Read,
+{
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: ser::Serializer,
+    {
+        use ser::{Error as _, SerializeSeq};
+        use std::io::ErrorKind;
+
+        let mut reader = self.0.reader.try_borrow_mut().map_err(S::Error::custom)?;
+        let mut buffer = [0; super::write::BUFFER_SIZE];
+        let mut remaining = self.0.len;
+
+        let mut seq = serializer.serialize_seq(None)?;
+        seq.serialize_element(&self.0.len)?;
+
+        // Reads one byte past the end to find out whether the reader is too long
+        loop {
+            let len = usize::try_from(remaining)
+                .map_or(buffer.len(), |remaining| remaining.clamp(1, buffer.len()));
+
+            match reader.read(&mut buffer[..len]) {
+                Ok(0) => break,
+                Ok(read) => {
+                    seq.serialize_element(&Chunk(&buffer[..read]))?;
+
+                    if remaining == 0 {
+                        break;
+                    }
+                    remaining = remaining.saturating_sub(read as u64);
+                }
+                Err(error) if error.kind() == ErrorKind::Interrupted => {}
+                Err(error) => return Err(S::Error::custom(error)),
+            }
+        }
+
+        seq.end()
+    }
+}
+
+struct Chunk<'a>(&'a [u8]);
+
+impl Serialize for Chunk<'_> {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: ser::Serializer,
+    {
+        serializer.serialize_bytes(self.0)
+    }
+}
+
+/// Serializer for the content of a [`ReadBytes`], writing it as a byte string.
+///
+/// The length prefix is left out when `prefix` is false, for [`ValueSerializer`](crate::ValueSerializer).
+pub(crate) struct ReadBytesSerializer<'a, W: ?Sized> {
+    writer: &'a mut W,
+    prefix: bool,
+    /// Bytes still expected, unknown until the length is serialized
+    remaining: Option<u64>,
+    len: u64,
+}
+
+impl<'a, W> ReadBytesSerializer<'a, W>
+where
+    W: ?Sized + Write,
+{
+    pub(crate) fn new(writer: &'a mut W, prefix: bool) -> Self {
+        Self {
+            writer,
+            prefix,
+            remaining: None,
+            len: 0,
+        }
+    }
+}
+
+impl<W> ser::SerializeSeq for &mut ReadBytesSerializer<'_, W>
+where
+    W: ?Sized + Write,
+{
+    type Ok = ();
+
+    type Error = Error;
+
+    fn serialize_element<T>(&mut self, value: &T) -> Result<()>
+    where
+        T: ?Sized + Serialize,
+    {
+        value.serialize(&mut **self)
+    }
+
+    fn end(self) -> Result<()> {
+        match self.remaining {
+            Some(0) => Ok(()),
+            Some(remaining) => Err(Error::ReaderTooShort {
+                expected: self.len,
+                found: self.len - remaining,
+            }),
+            None => Err(Error::InvalidType),
+        }
+    }
+}
+
+impl<W> ser::Serializer for &mut ReadBytesSerializer<'_, W>
+where
+    W: ?Sized + Write,
+{
+    type Ok = ();
+
+    type Error = Error;
+
+    type SerializeSeq = Self;
+
+    type SerializeTuple = Impossible<(), Error>;
+
+    type SerializeTupleStruct = Impossible<(), Error>;
+
+    type SerializeTupleVariant = Impossible<(), Error>;
+
+    type SerializeMap = Impossible<(), Error>;
+
+    type SerializeStruct = Impossible<(), Error>;
+
+    type SerializeStructVariant = Impossible<(), Error>;
+
+    fn serialize_u64(self, value: u64) -> Result<Self::Ok> {
+        if self.remaining.is_some() {
+            return Err(Error::InvalidType);
+        }
+
+        if self.prefix {
+            self.writer
+                .write_all(itoa::Buffer::new().format(value).as_bytes())?;
+            self.writer.write_all(b":")?;
+        }
+
+        self.remaining = Some(value);
+        self.len = value;
+        Ok(())
+    }
+
+    fn serialize_bytes(self, value: &[u8]) -> Result<Self::Ok> {
+        let remaining = self.remaining.ok_or(Error::InvalidType)?;
+
+        match remaining.checked_sub(value.len() as u64) {
+            Some(remaining) => {
+                self.remaining = Some(remaining);
+                self.writer.write_all(value)
+            }
+            None => Err(Error::ReaderTooLong(self.len)),
+        }
+    }
+
+    fn serialize_seq(self, _len: Option<usize>) -> Result<Self::SerializeSeq> {
+        Ok(self)
+    }
+
+    fn serialize_bool(self, _value: bool) -> Result<Self::Ok> {
+        Err(Error::InvalidType)
+    }
+
+    fn serialize_i8(self, _value: i8) -> Result<Self::Ok> {
+        Err(Error::InvalidType)
+    }
+
+    fn serialize_i16(self, _value: i16) -> Result<Self::Ok> {
+        Err(Error::InvalidType)
+    }
+
+    fn serialize_i32(self, _value: i32) -> Result<Self::Ok> {
+        Err(Error::InvalidType)
+    }
+
+    fn serialize_i64(self, _value: i64) -> Result<Self::Ok> {
+        Err(Error::InvalidType)
+    }
+
+    fn serialize_u8(self, _value: u8) -> Result<Self::Ok> {
+        Err(Error::InvalidType)
+    }
+
+    fn serialize_u16(self, _value: u16) -> Result<Self::Ok> {
+        Err(Error::InvalidType)
+    }
+
+    fn serialize_u32(self, _value: u32) -> Result<Self::Ok> {
+        Err(Error::InvalidType)
+    }
+
+    fn serialize_f32(self, _value: f32) -> Result<Self::Ok> {
+        Err(Error::InvalidType)
+    }
+
+    fn serialize_f64(self, _value: f64) -> Result<Self::Ok> {
+        Err(Error::InvalidType)
+    }
+
+    fn serialize_char(self, _value: char) -> Result<Self::Ok> {
+        Err(Error::InvalidType)
+    }
+
+    fn serialize_str(self, _value: &str) -> Result<Self::Ok> {
+        Err(Error::InvalidType)
+    }
+
+    fn serialize_none(self) -> Result<Self::Ok> {
+        Err(Error::InvalidType)
+    }
+
+    fn serialize_some<T>(self, _value: &T) -> Result<Self::Ok>
+    where
+        T: ?Sized + Serialize,
+    {
+        Err(Error::InvalidType)
+    }
+
+    fn serialize_unit(self) -> Result<Self::Ok> {
+        Err(Error::InvalidType)
+    }
+
+    fn serialize_unit_struct(self, _name: &'static str) -> Result<Self::Ok> {
+        Err(Error::InvalidType)
+    }
+
+    fn serialize_unit_variant(
+        self,
+        _name: &'static str,
+        _variant_index: u32,
+        _variant: &'static str,
+    ) -> Result<Self::Ok> {
+        Err(Error::InvalidType)
+    }
+
+    fn serialize_newtype_struct<T>(self, _name: &'static str, _value: &T) -> Result<Self::Ok>
+    where
+        T: ?Sized + Serialize,
+    {
+        Err(Error::InvalidType)
+    }
+
+    fn serialize_newtype_variant<T>(
+        self,
+        _name: &'static str,
+        _variant_index: u32,
+        _variant: &'static str,
+        _value: &T,
+    ) -> Result<Self::Ok>
+    where
+        T: ?Sized + Serialize,
+    {
+        Err(Error::InvalidType)
+    }
+
+    fn serialize_tuple(self, _len: usize) -> Result<Self::SerializeTuple> {
+        Err(Error::InvalidType)
+    }
+
+    fn serialize_tuple_struct(
+        self,
+        _name: &'static str,
+        _len: usize,
+    ) -> Result<Self::SerializeTupleStruct> {
+        Err(Error::InvalidType)
+    }
+
+    fn serialize_tuple_variant(
+        self,
+        _name: &'static str,
+        _variant_index: u32,
+        _variant: &'static str,
+        _len: usize,
+    ) -> Result<Self::SerializeTupleVariant> {
+        Err(Error::InvalidType)
+    }
+
+    fn serialize_map(self, _len: Option<usize>) -> Result<Self::SerializeMap> {
+        Err(Error::InvalidType)
+    }
+
+    fn serialize_struct(self, _name: &'static str, _len: usize) -> Result<Self::SerializeStruct> {
+        Err(Error::InvalidType)
+    }
+
+    fn serialize_struct_variant(
+        self,
+        _name: &'static str,
+        _variant_index: u32,
+        _variant: &'static str,
+        _len: usize,
+    ) -> Result<Self::SerializeStructVariant> {
+        Err(Error::InvalidType)
+    }
+}
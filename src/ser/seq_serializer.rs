@@ -0,0 +1,169 @@
+use alloc::vec::Vec;
+use serde::ser::{self, Serialize};
+
+use crate::{Error, Result};
+
+use super::{byte_serializer::ByteSerializer, encoder::TokenWriter, none_serializer::is_none};
+
+/// Serializes sequences for both [`Serializer`](super::Serializer) and [`UnsortedSerializer`](super::UnsortedSerializer).
+pub struct SeqSerializer<'a, S: 'a> {
+    serializer: &'a mut S,
+    /// Elements collected so far while every one of them has been a `u8`.
+    ///
+    /// Only used when the serializer encodes `u8` sequences as byte strings.
+    bytes: Option<Vec<u8>>,
+    /// Whether this is the payload of a tuple variant, which is wrapped in a list or dictionary
+    variant: bool,
+}
+
+impl<'a, S> SeqSerializer<'a, S>
+where
+    S: TokenWriter,
+    for<'b> &'b mut S: ser::Serializer<Ok = (), Error = Error>,
+{
+    pub fn new(serializer: &'a mut S, len: Option<usize>) -> Result<Self> {
+        let bytes = if serializer.options().u8_seq_as_bytes {
+            Some(Vec::with_capacity(len.unwrap_or(0)))
+        } else {
+            serializer.open(b"l")?;
+            None
+        };
+
+        Ok(Self {
+            serializer,
+            bytes,
+            variant: false,
+        })
+    }
+
+    /// Starts the payload of a tuple variant, written as `{variant: [fields]}` or `[variant, [fields]]`
+    pub fn new_variant(serializer: &'a mut S, variant: &str, len: usize) -> Result<Self> {
+        serializer.write_variant_key(variant)?;
+
+        let mut seq = Self::new(serializer, Some(len))?;
+        seq.variant = true;
+        Ok(seq)
+    }
+
+    fn serialize_element<T>(&mut self, value: &T) -> Result<()>
+    where
+        T: ?Sized + Serialize,
+    {
+        if self.serializer.options().skip_none_in_seq && is_none(value) {
+            return Ok(());
+        }
+
+        if let Some(bytes) = &mut self.bytes {
+            if let Ok(byte) = value.serialize(ByteSerializer) {
+                bytes.push(byte);
+                return Ok(());
+            }
+
+            // Not a byte sequence after all, write the collected bytes as a list of integers
+            self.serializer.open(b"l")?;
+            for byte in self.bytes.take().unwrap_or_default() {
+                self.serializer.write_integer(byte)?;
+            }
+        }
+
+        value.serialize(&mut *self.serializer)
+    }
+
+    fn end(self) -> Result<()> {
+        if let Some(bytes) = self.bytes {
+            self.serializer.write_byte_string(&bytes)?;
+        } else {
+            self.serializer.close()?;
+        }
+
+        if self.variant {
+            self.serializer.close()?;
+        }
+
+        Ok(())
+    }
+}
+
+impl<'a, S> ser::SerializeSeq for SeqSerializer<'a, S>
+where
+    S: TokenWriter,
+    for<'b> &'b mut S: ser::Serializer<Ok = (), Error = Error>,
+{
+    type Ok = ();
+
+    type Error = Error;
+
+    fn serialize_element<T>(&mut self, value: &T) -> Result<()>
+    where
+        T: ?Sized + Serialize,
+    {
+        SeqSerializer::serialize_element(self, value)
+    }
+
+    fn end(self) -> Result<Self::Ok> {
+        SeqSerializer::end(self)
+    }
+}
+
+impl<'a, S> ser::SerializeTuple for SeqSerializer<'a, S>
+where
+    S: TokenWriter,
+    for<'b> &'b mut S: ser::Serializer<Ok = (), Error = Error>,
+{
+    type Ok = ();
+
+    type Error = Error;
+
+    fn serialize_element<T>(&mut self, value: &T) -> Result<()>
+    where
+        T: ?Sized + Serialize,
+    {
+        SeqSerializer::serialize_element(self, value)
+    }
+
+    fn end(self) -> Result<Self::Ok> {
+        SeqSerializer::end(self)
+    }
+}
+
+impl<'a, S> ser::SerializeTupleStruct for SeqSerializer<'a, S>
+where
+    S: TokenWriter,
+    for<'b> &'b mut S: ser::Serializer<Ok = (), Error = Error>,
+{
+    type Ok = ();
+
+    type Error = Error;
+
+    fn serialize_field<T>(&mut self, value: &T) -> Result<()>
+    where
+        T: ?Sized + Serialize,
+    {
+        SeqSerializer::serialize_element(self, value)
+    }
+
+    fn end(self) -> Result<Self::Ok> {
+        SeqSerializer::end(self)
+    }
+}
+
+impl<'a, S> ser::SerializeTupleVariant for SeqSerializer<'a, S>
+where
+    S: TokenWriter,
+    for<'b> &'b mut S: ser::Serializer<Ok = (), Error = Error>,
+{
+    type Ok = ();
+
+    type Error = Error;
+
+    fn serialize_field<T>(&mut self, value: &T) -> Result<()>
+    where
+        T: ?Sized + Serialize,
+    {
+        SeqSerializer::serialize_element(self, value)
+    }
+
+    fn end(self) -> Result<Self::Ok> {
+        SeqSerializer::end(self)
+    }
+}
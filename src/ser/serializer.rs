@@ -1,206 +1,307 @@
+use alloc::vec::Vec;
 use serde::{ser, Serialize};
-use std::io::Write;
 
 use crate::{Error, Result};
 
-use super::{map_serializer::MapSerializer, unsorted_serializer::UnsortedSerializer};
-
+#[cfg(feature = "std")]
+use super::read_bytes::{self, ReadBytesSerializer};
+use super::{
+    encoder::TokenWriter, map_serializer::MapSerializer, seq_serializer::SeqSerializer,
+    write::Output, SerializerOptions, Write,
+};
+
+/// Serializer that writes dictionary keys in sorted order.
+///
+/// Dictionaries are encoded into an internal buffer, reordered only if their keys didn't arrive sorted,
+/// and written out once the outermost one is complete.
+/// The rest of the output is buffered too, see [`buffer_size`](Self::buffer_size).
+/// A key appearing twice in the same dictionary fails with [`Error::DuplicateKey`].
+///
+/// With [`KeyOrder::Trust`](super::KeyOrder::Trust) or [`KeyOrder::Verify`](super::KeyOrder::Verify) dictionaries are written as they arrive,
+/// like [`UnsortedSerializer`](super::UnsortedSerializer) does.
 pub struct Serializer<W> {
-    unsorted_serializer: UnsortedSerializer<W>,
+    pub(super) output: Output<W>,
+    pub(super) options: SerializerOptions,
+    /// Number of dictionaries being sorted, the buffer is held until it is zero
+    pub(super) sorting: usize,
+    /// Number of lists and dictionaries being written
+    depth: usize,
+    /// Number of bytes written so far, including those still in the buffer
+    size: usize,
 }
 
 impl<W> Serializer<W>
 where
-    W: Write + Sized,
+    W: Write,
 {
     pub const fn new(writer: W) -> Self {
+        Self::with_options(writer, SerializerOptions::new())
+    }
+
+    /// Creates a serializer with the given options.
+    pub const fn with_options(writer: W, options: SerializerOptions) -> Self {
+        Self::with_output(Output::new(writer), options)
+    }
+
+    const fn with_output(output: Output<W>, options: SerializerOptions) -> Self {
         Self {
-            unsorted_serializer: UnsortedSerializer::new(writer),
+            output,
+            options,
+            sorting: 0,
+            depth: 0,
+            size: 0,
+        }
+    }
+
+    /// Sets how many bytes are buffered before they are written to the writer, 8 KiB by default.
+    ///
+    /// The buffer is also written out whenever a top-level value is complete, so nothing is left behind
+    /// once serialization succeeds. Use 0 to write every token directly, if the writer is already buffered.
+    /// Dictionaries being sorted are always held until the outermost one is complete.
+    pub fn buffer_size(mut self, buffer_size: usize) -> Self {
+        self.output.set_buffer_size(buffer_size);
+        self
+    }
+
+    /// Encodes sequences, tuples and arrays made only of `u8` as byte strings instead of lists of integers.
+    ///
+    /// See [`UnsortedSerializer::u8_seq_as_bytes`](super::UnsortedSerializer::u8_seq_as_bytes).
+    pub fn u8_seq_as_bytes(mut self, enabled: bool) -> Self {
+        self.options = self.options.u8_seq_as_bytes(enabled);
+        self
+    }
+
+    /// Skips `None` elements of sequences and tuples instead of failing with [`Error::Unsupported`].
+    ///
+    /// See [`UnsortedSerializer::skip_none_in_seq`](super::UnsortedSerializer::skip_none_in_seq).
+    pub fn skip_none_in_seq(mut self, enabled: bool) -> Self {
+        self.options = self.options.skip_none_in_seq(enabled);
+        self
+    }
+
+    /// Releases the buffer once the outermost sorted dictionary is complete
+    pub(super) fn end_sorting(&mut self) -> Result<()> {
+        self.sorting -= 1;
+        self.flush_complete()
+    }
+
+    /// Writes the buffer out once a top-level value is complete
+    fn flush_complete(&mut self) -> Result<()> {
+        if self.depth == 0 && self.sorting == 0 {
+            self.output.flush()?;
         }
+
+        Ok(())
+    }
+}
+
+impl Serializer<Vec<u8>> {
+    /// Serializer keeping the output in its buffer, which avoids copying it into another vector
+    pub(super) fn in_memory(options: SerializerOptions) -> Self {
+        Self::with_output(Output::in_memory(), options)
+    }
+
+    pub(super) fn into_bytes(self) -> Vec<u8> {
+        self.output.into_buffer()
     }
 }
 
 impl<W> Write for Serializer<W>
 where
-    W: Write + Sized,
+    W: Write,
 {
     #[inline]
-    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
-        self.unsorted_serializer.write(buf)
+    fn write_all(&mut self, buf: &[u8]) -> Result<()> {
+        self.options.check_size(self.size + buf.len())?;
+        self.size += buf.len();
+
+        self.output.write(buf, self.sorting > 0)?;
+        self.flush_complete()
+    }
+}
+
+impl<W> TokenWriter for Serializer<W>
+where
+    W: Write,
+{
+    fn options(&self) -> &SerializerOptions {
+        &self.options
     }
 
-    #[inline]
-    fn flush(&mut self) -> std::io::Result<()> {
-        self.unsorted_serializer.flush()
+    fn depth_mut(&mut self) -> &mut usize {
+        &mut self.depth
     }
 }
 
 impl<'a, W> ser::Serializer for &'a mut Serializer<W>
 where
-    W: std::io::Write,
+    W: Write,
 {
     type Ok = ();
 
     type Error = Error;
-    type SerializeSeq = &'a mut UnsortedSerializer<W>;
-    type SerializeTuple = &'a mut UnsortedSerializer<W>;
-    type SerializeTupleStruct = &'a mut UnsortedSerializer<W>;
-    type SerializeTupleVariant = &'a mut UnsortedSerializer<W>;
+    type SerializeSeq = SeqSerializer<'a, Serializer<W>>;
+    type SerializeTuple = SeqSerializer<'a, Serializer<W>>;
+    type SerializeTupleStruct = SeqSerializer<'a, Serializer<W>>;
+    type SerializeTupleVariant = SeqSerializer<'a, Serializer<W>>;
     type SerializeMap = MapSerializer<'a, W>;
     type SerializeStruct = MapSerializer<'a, W>;
-    type SerializeStructVariant = &'a mut UnsortedSerializer<W>;
+    type SerializeStructVariant = MapSerializer<'a, W>;
 
     fn serialize_bool(self, value: bool) -> Result<Self::Ok> {
-        self.unsorted_serializer.serialize_bool(value)
+        self.write_bool(value)
     }
 
     fn serialize_i8(self, value: i8) -> Result<Self::Ok> {
-        self.unsorted_serializer.serialize_i8(value)
+        self.write_integer(value)
     }
 
     fn serialize_i16(self, value: i16) -> Result<Self::Ok> {
-        self.unsorted_serializer.serialize_i16(value)
+        self.write_integer(value)
     }
 
     fn serialize_i32(self, value: i32) -> Result<Self::Ok> {
-        self.unsorted_serializer.serialize_i32(value)
+        self.write_integer(value)
     }
 
     fn serialize_i64(self, value: i64) -> Result<Self::Ok> {
-        self.unsorted_serializer.serialize_i64(value)
+        self.write_integer(value)
     }
 
     fn serialize_u8(self, value: u8) -> Result<Self::Ok> {
-        self.unsorted_serializer.serialize_u8(value)
+        self.write_integer(value)
     }
 
     fn serialize_u16(self, value: u16) -> Result<Self::Ok> {
-        self.unsorted_serializer.serialize_u16(value)
+        self.write_integer(value)
     }
 
     fn serialize_u32(self, value: u32) -> Result<Self::Ok> {
-        self.unsorted_serializer.serialize_u32(value)
+        self.write_integer(value)
     }
 
     fn serialize_u64(self, value: u64) -> Result<Self::Ok> {
-        self.unsorted_serializer.serialize_u64(value)
+        self.write_integer(value)
     }
 
     fn serialize_f32(self, value: f32) -> Result<Self::Ok> {
-        self.unsorted_serializer.serialize_f32(value)
+        self.write_float("f32", value)
     }
 
     fn serialize_f64(self, value: f64) -> Result<Self::Ok> {
-        self.unsorted_serializer.serialize_f64(value)
+        self.write_float("f64", value)
     }
 
     fn serialize_char(self, value: char) -> Result<Self::Ok> {
-        self.unsorted_serializer.serialize_char(value)
+        self.write_char(value)
     }
 
     fn serialize_str(self, value: &str) -> Result<Self::Ok> {
-        self.unsorted_serializer.serialize_str(value)
+        self.write_byte_string(value.as_bytes())
     }
 
     fn serialize_bytes(self, value: &[u8]) -> Result<Self::Ok> {
-        self.unsorted_serializer.serialize_bytes(value)
+        self.write_byte_string(value)
     }
 
     fn serialize_none(self) -> Result<Self::Ok> {
-        self.unsorted_serializer.serialize_none()
+        Err(Error::Unsupported("None"))
     }
 
-    fn serialize_some<T: ?Sized>(self, value: &T) -> Result<Self::Ok>
+    fn serialize_some<T>(self, value: &T) -> Result<Self::Ok>
     where
-        T: Serialize,
+        T: ?Sized + Serialize,
     {
-        self.unsorted_serializer.serialize_some(value)
+        value.serialize(self)
     }
 
     fn serialize_unit(self) -> Result<Self::Ok> {
-        self.unsorted_serializer.serialize_unit()
+        Err(Error::Unsupported("unit"))
     }
 
-    fn serialize_unit_struct(self, name: &'static str) -> Result<Self::Ok> {
-        self.unsorted_serializer.serialize_unit_struct(name)
+    fn serialize_unit_struct(self, _name: &'static str) -> Result<Self::Ok> {
+        self.serialize_unit()
     }
 
     fn serialize_unit_variant(
         self,
-        name: &'static str,
-        variant_index: u32,
+        _name: &'static str,
+        _variant_index: u32,
         variant: &'static str,
     ) -> Result<Self::Ok> {
-        self.unsorted_serializer
-            .serialize_unit_variant(name, variant_index, variant)
+        self.serialize_str(variant)
     }
 
-    fn serialize_newtype_struct<T: ?Sized>(self, name: &'static str, value: &T) -> Result<Self::Ok>
+    // Only ReadBytes, which needs std, is recognized by name
+    #[cfg_attr(not(feature = "std"), allow(unused_variables))]
+    fn serialize_newtype_struct<T>(self, name: &'static str, value: &T) -> Result<Self::Ok>
     where
-        T: Serialize,
+        T: ?Sized + Serialize,
     {
-        self.unsorted_serializer
-            .serialize_newtype_struct(name, value)
+        #[cfg(feature = "std")]
+        if name == read_bytes::NAME {
+            return value.serialize(&mut ReadBytesSerializer::new(self, true));
+        }
+
+        value.serialize(self)
     }
 
-    fn serialize_newtype_variant<T: ?Sized>(
+    fn serialize_newtype_variant<T>(
         self,
-        name: &'static str,
-        variant_index: u32,
+        _name: &'static str,
+        _variant_index: u32,
         variant: &'static str,
         value: &T,
     ) -> Result<Self::Ok>
     where
-        T: Serialize,
+        T: ?Sized + Serialize,
     {
-        self.unsorted_serializer
-            .serialize_newtype_variant(name, variant_index, variant, value)
+        self.write_variant_key(variant)?;
+        value.serialize(&mut *self)?;
+        self.close()
     }
 
     fn serialize_seq(self, len: Option<usize>) -> Result<Self::SerializeSeq> {
-        self.unsorted_serializer.serialize_seq(len)
+        SeqSerializer::new(self, len)
     }
 
     fn serialize_tuple(self, len: usize) -> Result<Self::SerializeTuple> {
-        self.unsorted_serializer.serialize_tuple(len)
+        self.serialize_seq(Some(len))
     }
 
     fn serialize_tuple_struct(
         self,
-        name: &'static str,
+        _name: &'static str,
         len: usize,
     ) -> Result<Self::SerializeTupleStruct> {
-        self.unsorted_serializer.serialize_tuple_struct(name, len)
+        self.serialize_seq(Some(len))
     }
 
     fn serialize_tuple_variant(
         self,
-        name: &'static str,
-        variant_index: u32,
+        _name: &'static str,
+        _variant_index: u32,
         variant: &'static str,
         len: usize,
     ) -> Result<Self::SerializeTupleVariant> {
-        self.unsorted_serializer
-            .serialize_tuple_variant(name, variant_index, variant, len)
+        SeqSerializer::new_variant(self, variant, len)
     }
 
     fn serialize_map(self, _len: Option<usize>) -> Result<Self::SerializeMap> {
-        self.write_all(b"d")?;
-        Ok(MapSerializer::new(self))
+        MapSerializer::new(self)
     }
 
     fn serialize_struct(self, _name: &'static str, _len: usize) -> Result<Self::SerializeStruct> {
-        self.serialize_map(None) // There is no reason to pass along the len since we are using a BTreeSet
+        self.serialize_map(None)
     }
 
     fn serialize_struct_variant(
         self,
-        name: &'static str,
-        variant_index: u32,
+        _name: &'static str,
+        _variant_index: u32,
         variant: &'static str,
-        len: usize,
+        _len: usize,
     ) -> Result<Self::SerializeStructVariant> {
-        self.unsorted_serializer
-            .serialize_struct_variant(name, variant_index, variant, len)
+        MapSerializer::new_variant(self, variant)
     }
 }
@@ -1,127 +1,231 @@
+use alloc::vec::Vec;
 use serde::{ser, Serialize};
-use std::io::Write;
 
 use crate::{Error, Result};
 
-use super::map_serializer::UnsortedMapSerializer;
+#[cfg(feature = "std")]
+use super::read_bytes::{self, ReadBytesSerializer};
+use super::{
+    encoder::TokenWriter, map_serializer::UnsortedMapSerializer, seq_serializer::SeqSerializer,
+    write::Output, KeyOrder, SerializerOptions, Write,
+};
 
+/// Serializer that writes dictionary keys in the order they are serialized.
+///
+/// The output is buffered, see [`buffer_size`](Self::buffer_size).
 pub struct UnsortedSerializer<W> {
-    writer: W,
+    output: Output<W>,
+    options: SerializerOptions,
+    /// Number of lists and dictionaries being written
+    depth: usize,
+    /// Number of bytes written so far
+    size: usize,
+    /// Whether dictionaries fail on duplicate keys even when their order is trusted
+    pub(super) reject_duplicate_keys: bool,
 }
 
 impl<W> UnsortedSerializer<W>
 where
-    W: Write + Sized,
+    W: Write,
 {
     pub const fn new(writer: W) -> Self {
-        Self { writer }
+        Self::with_options(writer, SerializerOptions::new().key_order(KeyOrder::Trust))
+    }
+
+    /// Creates a serializer with the given options.
+    ///
+    /// Dictionaries are never buffered, so [`KeyOrder::Sort`] behaves like [`KeyOrder::Verify`]
+    /// and fails on keys that would need sorting instead of writing them out of order.
+    pub const fn with_options(writer: W, options: SerializerOptions) -> Self {
+        Self::with_output(Output::new(writer), options)
+    }
+
+    const fn with_output(output: Output<W>, options: SerializerOptions) -> Self {
+        Self {
+            output,
+            options,
+            depth: 0,
+            size: 0,
+            reject_duplicate_keys: false,
+        }
+    }
+
+    /// Sets how many bytes are buffered before they are written to the writer, 8 KiB by default.
+    ///
+    /// See [`Serializer::buffer_size`](super::Serializer::buffer_size).
+    pub fn buffer_size(mut self, buffer_size: usize) -> Self {
+        self.output.set_buffer_size(buffer_size);
+        self
+    }
+
+    /// Encodes sequences, tuples and arrays made only of `u8` as byte strings instead of lists of integers.
+    ///
+    /// With this enabled `Vec<u8>` and `[u8; N]` produce the same output as `serde_bytes`.
+    /// Empty sequences are written as an empty byte string.
+    pub fn u8_seq_as_bytes(mut self, enabled: bool) -> Self {
+        self.options = self.options.u8_seq_as_bytes(enabled);
+        self
+    }
+
+    /// Skips `None` elements of sequences and tuples instead of failing with [`Error::Unsupported`].
+    ///
+    /// Bencode has no null value, so a `None` can't be written anywhere.
+    /// Struct fields and map values that are `None` are always left out, elements of sequences are only left out when this is enabled.
+    /// Note that skipping elements changes the length of tuples.
+    pub fn skip_none_in_seq(mut self, enabled: bool) -> Self {
+        self.options = self.options.skip_none_in_seq(enabled);
+        self
+    }
+
+    /// Fails as soon as a dictionary key isn't greater than the previous key of the same dictionary.
+    ///
+    /// Keys are still written as they arrive, without any buffering, but output that `from_bytes` would reject
+    /// with [`Error::UnsortedKeys`] becomes an [`Error::KeyOutOfOrder`] or [`Error::DuplicateKey`] error instead.
+    /// Use [`Serializer`](super::Serializer) for types whose keys may not be sorted, like `HashMap`
+    /// or structs whose fields aren't declared in alphabetical order.
+    ///
+    /// This is the same as setting [`KeyOrder::Verify`] in the options.
+    pub fn verify_key_order(mut self, enabled: bool) -> Self {
+        let key_order = if enabled {
+            KeyOrder::Verify
+        } else {
+            KeyOrder::Trust
+        };
+        self.options = self.options.key_order(key_order);
+        self
     }
 }
 
-impl<W> Write for UnsortedSerializer<W>
+impl<W> UnsortedSerializer<W> {
+    /// Fails on duplicate keys in any order like [`Serializer`](super::Serializer) does,
+    /// at the cost of keeping a copy of every key of the dictionaries being written.
+    pub(super) fn reject_duplicate_keys(mut self) -> Self {
+        self.reject_duplicate_keys = true;
+        self
+    }
+}
+
+impl UnsortedSerializer<Vec<u8>> {
+    /// Serializer keeping the output in its buffer, which avoids copying it into another vector
+    pub(super) fn in_memory(options: SerializerOptions) -> Self {
+        Self::with_output(Output::in_memory(), options)
+    }
+
+    pub(super) fn into_bytes(self) -> Vec<u8> {
+        self.output.into_buffer()
+    }
+}
+
+impl<W> TokenWriter for UnsortedSerializer<W>
 where
-    W: Write + Sized,
+    W: Write,
 {
-    #[inline]
-    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
-        self.writer.write(buf)
+    fn options(&self) -> &SerializerOptions {
+        &self.options
     }
 
+    fn depth_mut(&mut self) -> &mut usize {
+        &mut self.depth
+    }
+}
+
+impl<W> Write for UnsortedSerializer<W>
+where
+    W: Write,
+{
     #[inline]
-    fn flush(&mut self) -> std::io::Result<()> {
-        self.writer.flush()
+    fn write_all(&mut self, buf: &[u8]) -> Result<()> {
+        self.options.check_size(self.size + buf.len())?;
+        self.size += buf.len();
+        self.output.write(buf, false)?;
+
+        // Write the buffer out once a top-level value is complete
+        if self.depth == 0 {
+            self.output.flush()?;
+        }
+
+        Ok(())
     }
 }
 
 impl<'a, W> ser::Serializer for &'a mut UnsortedSerializer<W>
 where
-    W: std::io::Write,
+    W: Write,
 {
     type Ok = ();
     type Error = Error;
 
-    type SerializeSeq = Self;
-    type SerializeTuple = Self;
-    type SerializeTupleStruct = Self;
-    type SerializeTupleVariant = Self;
+    type SerializeSeq = SeqSerializer<'a, UnsortedSerializer<W>>;
+    type SerializeTuple = SeqSerializer<'a, UnsortedSerializer<W>>;
+    type SerializeTupleStruct = SeqSerializer<'a, UnsortedSerializer<W>>;
+    type SerializeTupleVariant = SeqSerializer<'a, UnsortedSerializer<W>>;
     type SerializeMap = UnsortedMapSerializer<'a, W>;
     type SerializeStruct = UnsortedMapSerializer<'a, W>;
-    type SerializeStructVariant = Self;
+    type SerializeStructVariant = UnsortedMapSerializer<'a, W>;
 
-    fn serialize_bool(self, _value: bool) -> Result<Self::Ok> {
-        Err(Error::Unsupported("bool"))
+    fn serialize_bool(self, value: bool) -> Result<Self::Ok> {
+        self.write_bool(value)
     }
 
     fn serialize_i8(self, value: i8) -> Result<Self::Ok> {
-        write!(self, "i{value}e")?;
-        Ok(())
+        self.write_integer(value)
     }
 
     fn serialize_i16(self, value: i16) -> Result<Self::Ok> {
-        write!(self, "i{value}e")?;
-        Ok(())
+        self.write_integer(value)
     }
 
     fn serialize_i32(self, value: i32) -> Result<Self::Ok> {
-        write!(self, "i{value}e")?;
-        Ok(())
+        self.write_integer(value)
     }
 
     fn serialize_i64(self, value: i64) -> Result<Self::Ok> {
-        write!(self, "i{value}e")?;
-        Ok(())
+        self.write_integer(value)
     }
 
     fn serialize_u8(self, value: u8) -> Result<Self::Ok> {
-        write!(self, "i{value}e")?;
-        Ok(())
+        self.write_integer(value)
     }
 
     fn serialize_u16(self, value: u16) -> Result<Self::Ok> {
-        write!(self, "i{value}e")?;
-        Ok(())
+        self.write_integer(value)
     }
 
     fn serialize_u32(self, value: u32) -> Result<Self::Ok> {
-        write!(self, "i{value}e")?;
-        Ok(())
+        self.write_integer(value)
     }
 
     fn serialize_u64(self, value: u64) -> Result<Self::Ok> {
-        write!(self, "i{value}e")?;
-        Ok(())
+        self.write_integer(value)
     }
 
-    fn serialize_f32(self, _value: f32) -> Result<Self::Ok> {
-        Err(Error::Unsupported("f32"))
+    fn serialize_f32(self, value: f32) -> Result<Self::Ok> {
+        self.write_float("f32", value)
     }
 
-    fn serialize_f64(self, _value: f64) -> Result<Self::Ok> {
-        Err(Error::Unsupported("f64"))
+    fn serialize_f64(self, value: f64) -> Result<Self::Ok> {
+        self.write_float("f64", value)
     }
 
-    fn serialize_char(self, _value: char) -> Result<Self::Ok> {
-        Err(Error::Unsupported("char"))
+    fn serialize_char(self, value: char) -> Result<Self::Ok> {
+        self.write_char(value)
     }
 
     fn serialize_str(self, value: &str) -> Result<Self::Ok> {
-        write!(self, "{0}:{value}", value.len())?;
-        Ok(())
+        self.write_byte_string(value.as_bytes())
     }
 
     fn serialize_bytes(self, value: &[u8]) -> Result<Self::Ok> {
-        write!(self, "{0}:", value.len())?;
-        self.writer.write_all(value)?;
-        Ok(())
+        self.write_byte_string(value)
     }
 
     fn serialize_none(self) -> Result<Self::Ok> {
         Err(Error::Unsupported("None"))
     }
 
-    fn serialize_some<T: ?Sized>(self, value: &T) -> Result<Self::Ok>
+    fn serialize_some<T>(self, value: &T) -> Result<Self::Ok>
     where
-        T: Serialize,
+        T: ?Sized + Serialize,
     {
         value.serialize(self)
     }
@@ -143,33 +247,41 @@ where
         self.serialize_str(variant)
     }
 
-    fn serialize_newtype_struct<T: ?Sized>(self, _name: &'static str, value: &T) -> Result<Self::Ok>
+    // Only ReadBytes, which needs std, is recognized by name
+    #[cfg_attr(not(feature = "std"), allow(unused_variables))]
+    fn serialize_newtype_struct<T>(self, name: &'static str, value: &T) -> Result<Self::Ok>
     where
-        T: Serialize,
+        T: ?Sized + Serialize,
     {
+        #[cfg(feature = "std")]
+        if name == read_bytes::NAME {
+            return value.serialize(&mut ReadBytesSerializer::new(self, true));
+        }
+
         value.serialize(self)
     }
 
-    fn serialize_newtype_variant<T: ?Sized>(
+    fn serialize_newtype_variant<T>(
         self,
         _name: &'static str,
         _variant_index: u32,
-        _variant: &'static str,
-        _value: &T,
+        variant: &'static str,
+        value: &T,
     ) -> Result<Self::Ok>
     where
-        T: Serialize,
+        T: ?Sized + Serialize,
     {
-        todo!()
+        self.write_variant_key(variant)?;
+        value.serialize(&mut *self)?;
+        self.close()
     }
 
-    fn serialize_seq(self, _len: Option<usize>) -> Result<Self::SerializeSeq> {
-        self.write_all(b"l")?;
-        Ok(self)
+    fn serialize_seq(self, len: Option<usize>) -> Result<Self::SerializeSeq> {
+        SeqSerializer::new(self, len)
     }
 
-    fn serialize_tuple(self, _len: usize) -> Result<Self::SerializeTuple> {
-        self.serialize_seq(None)
+    fn serialize_tuple(self, len: usize) -> Result<Self::SerializeTuple> {
+        self.serialize_seq(Some(len))
     }
 
     fn serialize_tuple_struct(
@@ -184,15 +296,14 @@ where
         self,
         _name: &'static str,
         _variant_index: u32,
-        _variant: &'static str,
-        _len: usize,
+        variant: &'static str,
+        len: usize,
     ) -> Result<Self::SerializeTupleVariant> {
-        self.serialize_seq(None)
+        SeqSerializer::new_variant(self, variant, len)
     }
 
     fn serialize_map(self, _len: Option<usize>) -> Result<Self::SerializeMap> {
-        self.write_all(b"d")?;
-        Ok(UnsortedMapSerializer::new(self))
+        UnsortedMapSerializer::new(self)
     }
 
     fn serialize_struct(self, _name: &'static str, _len: usize) -> Result<Self::SerializeStruct> {
@@ -203,115 +314,9 @@ where
         self,
         _name: &'static str,
         _variant_index: u32,
-        _variant: &'static str,
+        variant: &'static str,
         _len: usize,
     ) -> Result<Self::SerializeStructVariant> {
-        todo!()
-    }
-}
-
-impl<'a, W> ser::SerializeSeq for &'a mut UnsortedSerializer<W>
-where
-    W: std::io::Write,
-{
-    type Ok = ();
-
-    type Error = Error;
-
-    fn serialize_element<T: ?Sized>(&mut self, value: &T) -> Result<()>
-    where
-        T: Serialize,
-    {
-        value.serialize(&mut **self)
-    }
-
-    fn end(self) -> Result<Self::Ok> {
-        self.write_all(b"e")?;
-        Ok(())
-    }
-}
-
-impl<'a, W> ser::SerializeTuple for &'a mut UnsortedSerializer<W>
-where
-    W: std::io::Write,
-{
-    type Ok = ();
-
-    type Error = Error;
-
-    fn serialize_element<T: ?Sized>(&mut self, value: &T) -> Result<()>
-    where
-        T: Serialize,
-    {
-        value.serialize(&mut **self)
-    }
-
-    fn end(self) -> Result<Self::Ok> {
-        self.write_all(b"e")?;
-        Ok(())
-    }
-}
-
-impl<'a, W> ser::SerializeTupleStruct for &'a mut UnsortedSerializer<W>
-where
-    W: std::io::Write,
-{
-    type Ok = ();
-
-    type Error = Error;
-
-    fn serialize_field<T: ?Sized>(&mut self, value: &T) -> Result<()>
-    where
-        T: Serialize,
-    {
-        value.serialize(&mut **self)
-    }
-
-    fn end(self) -> Result<Self::Ok> {
-        self.write_all(b"e")?;
-        Ok(())
-    }
-}
-
-impl<'a, W> ser::SerializeTupleVariant for &'a mut UnsortedSerializer<W> {
-    type Ok = ();
-
-    type Error = Error;
-
-    fn serialize_field<T: ?Sized>(&mut self, _value: &T) -> Result<(), Self::Error>
-    where
-        T: Serialize,
-    {
-        todo!()
-    }
-
-    fn end(self) -> Result<Self::Ok, Self::Error> {
-        todo!()
-    }
-}
-
-impl<'a, W> ser::SerializeStructVariant for &'a mut UnsortedSerializer<W>
-where
-    W: std::io::Write,
-{
-    type Ok = ();
-
-    type Error = Error;
-
-    fn serialize_field<T: ?Sized>(
-        &mut self,
-        key: &'static str,
-        value: &T,
-    ) -> Result<(), Self::Error>
-    where
-        T: Serialize,
-    {
-        key.serialize(&mut **self)?;
-        value.serialize(&mut **self)
-    }
-
-    fn end(self) -> Result<Self::Ok, Self::Error> {
-        self.write_all(b"e")?;
-        Ok(())
+        UnsortedMapSerializer::new_variant(self, variant)
     }
 }
@@ -0,0 +1,328 @@
+use std::{
+    collections::BTreeMap,
+    io::{self, IoSlice},
+};
+
+use crate::{BorrowedValue, ByteString, Integer, Result, Value};
+
+/// Byte strings shorter than this are copied by default, see [`VectoredOutput::copy_threshold`].
+const COPY_THRESHOLD: usize = 256;
+
+/// The encoding of values as a list of [`IoSlice`]s, ready for [`write_vectored`](io::Write::write_vectored).
+///
+/// Length prefixes, integers and the tokens around lists and dictionaries are generated into an internal buffer,
+/// while byte strings are borrowed from the encoded values, so large payloads such as the pieces of a torrent are never copied.
+///
+/// # Limitations
+/// Only [`Value`], [`BorrowedValue`] and byte strings or integers pushed one by one can be encoded.
+/// There is no serde [`Serializer`](serde::Serializer) for this output: serde only lends byte strings
+/// for the duration of a `serialize_bytes` call, so they could not be borrowed past it.
+/// A type implementing [`Serialize`](serde::Serialize) can be converted with [`to_value`](crate::to_value) first,
+/// which copies its byte strings once. To avoid any copy, build a [`BorrowedValue`] borrowing them instead.
+///
+/// ```
+/// use bde::{ser::VectoredOutput, BorrowedValue, Integer};
+///
+/// let pieces = vec![0xAA; 20 * 1024];
+/// let value = BorrowedValue::List(vec![
+///     BorrowedValue::ByteString(&pieces),
+///     BorrowedValue::Integer(Integer::from(1024u64)),
+/// ]);
+///
+/// let mut output = VectoredOutput::new();
+/// output.push_borrowed_value(&value);
+///
+/// // The length prefix, the pieces themselves and everything after them
+/// assert_eq!(3, output.io_slices().len());
+///
+/// let mut bytes = Vec::new();
+/// output.write_to(&mut bytes).unwrap();
+/// assert_eq!(bde::to_bytes(&value).unwrap(), bytes);
+/// ```
+#[derive(Debug)]
+pub struct VectoredOutput<'a> {
+    /// Every generated byte, and the small byte strings that were copied
+    buffer: Vec<u8>,
+    /// Borrowed byte strings, each following the generated bytes up to its offset in `buffer`
+    borrowed: Vec<(usize, &'a [u8])>,
+    copy_threshold: usize,
+    len: usize,
+}
+
+/// Work left while encoding a value without recursion
+enum Pending<'a, T> {
+    Value(&'a T),
+    Key(&'a [u8]),
+    End,
+}
+
+/// A value borrowed as one of the four bencode types
+enum Node<'a, T: Encode> {
+    ByteString(&'a [u8]),
+    Integer(&'a Integer),
+    List(&'a [T]),
+    Dictionary(&'a BTreeMap<T::Key, T>),
+}
+
+/// Values that can be encoded into a [`VectoredOutput`]
+trait Encode: Sized {
+    type Key: AsRef<[u8]>;
+
+    fn node(&self) -> Node<'_, Self>;
+}
+
+impl Encode for Value {
+    type Key = ByteString;
+
+    fn node(&self) -> Node<'_, Self> {
+        match self {
+            Self::ByteString(byte_string) => Node::ByteString(byte_string),
+            Self::Integer(integer) => Node::Integer(integer),
+            Self::List(list) => Node::List(list),
+            Self::Dictionary(dictionary) => Node::Dictionary(dictionary),
+        }
+    }
+}
+
+impl<'v> Encode for BorrowedValue<'v> {
+    type Key = &'v [u8];
+
+    fn node(&self) -> Node<'_, Self> {
+        match self {
+            Self::ByteString(byte_string) => Node::ByteString(byte_string),
+            Self::Integer(integer) => Node::Integer(integer),
+            Self::List(list) => Node::List(list),
+            Self::Dictionary(dictionary) => Node::Dictionary(dictionary),
+        }
+    }
+}
+
+impl<'a> VectoredOutput<'a> {
+    pub const fn new() -> Self {
+        Self {
+            buffer: Vec::new(),
+            borrowed: Vec::new(),
+            copy_threshold: COPY_THRESHOLD,
+            len: 0,
+        }
+    }
+
+    /// Copies byte strings shorter than this many bytes instead of borrowing them, 256 by default.
+    ///
+    /// Each borrowed byte string takes its own [`IoSlice`] and operating systems limit how many
+    /// a single call can write, so borrowing only pays off for large byte strings.
+    pub const fn copy_threshold(mut self, copy_threshold: usize) -> Self {
+        self.copy_threshold = copy_threshold;
+        self
+    }
+
+    /// Appends the encoding of a value.
+    ///
+    /// Nested values are visited without recursion, like [`Value::encoded_len`] does.
+    pub fn push_value(&mut self, value: &'a Value) {
+        self.push(value);
+    }
+
+    /// Appends the encoding of a [`BorrowedValue`], borrowing its byte strings for as long as the value itself.
+    pub fn push_borrowed_value(&mut self, value: &'a BorrowedValue<'_>) {
+        self.push(value);
+    }
+
+    /// Appends a byte string, borrowing it unless it is shorter than the [`copy_threshold`](Self::copy_threshold).
+    pub fn push_byte_string(&mut self, byte_string: &'a [u8]) {
+        self.push_generated(itoa::Buffer::new().format(byte_string.len()).as_bytes());
+        self.push_generated(b":");
+
+        if byte_string.is_empty() || byte_string.len() < self.copy_threshold {
+            self.push_generated(byte_string);
+        } else {
+            self.borrowed.push((self.buffer.len(), byte_string));
+            self.len += byte_string.len();
+        }
+    }
+
+    /// Appends an integer.
+    pub fn push_integer(&mut self, integer: &Integer) {
+        let mut buffer = itoa::Buffer::new();
+        let digits = match integer.as_u64() {
+            Some(n) => buffer.format(n),
+            None => buffer.format(integer.as_i64().unwrap_or_default()),
+        };
+
+        self.push_generated(b"i");
+        self.push_generated(digits.as_bytes());
+        self.push_generated(b"e");
+    }
+
+    /// Total number of bytes of the encoded output
+    pub const fn len(&self) -> usize {
+        self.len
+    }
+
+    pub const fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// Returns the encoded output as slices to be written in order.
+    ///
+    /// Generated bytes between two borrowed byte strings are merged into a single slice.
+    pub fn io_slices(&self) -> Vec<IoSlice<'_>> {
+        let mut slices = Vec::with_capacity(self.borrowed.len() * 2 + 1);
+        let mut start = 0;
+
+        for &(end, byte_string) in &self.borrowed {
+            if start < end {
+                slices.push(IoSlice::new(&self.buffer[start..end]));
+            }
+            slices.push(IoSlice::new(byte_string));
+            start = end;
+        }
+
+        if start < self.buffer.len() {
+            slices.push(IoSlice::new(&self.buffer[start..]));
+        }
+
+        slices
+    }
+
+    /// Writes the whole output with as few calls to [`write_vectored`](io::Write::write_vectored) as the writer allows.
+    ///
+    /// # Errors
+    /// Returns an error if the writer fails or stops accepting bytes.
+    pub fn write_to<W>(&self, mut writer: W) -> Result<()>
+    where
+        W: io::Write,
+    {
+        let mut slices = self.io_slices();
+        let mut slices = &mut slices[..];
+
+        while !slices.is_empty() {
+            match writer.write_vectored(slices) {
+                Ok(0) => return Err(io::Error::from(io::ErrorKind::WriteZero).into()),
+                Ok(written) => IoSlice::advance_slices(&mut slices, written),
+                Err(error) if error.kind() == io::ErrorKind::Interrupted => {}
+                Err(error) => return Err(error.into()),
+            }
+        }
+
+        Ok(())
+    }
+
+    fn push<T: Encode>(&mut self, value: &'a T) {
+        let mut stack = vec![Pending::Value(value)];
+
+        while let Some(pending) = stack.pop() {
+            match pending {
+                Pending::Value(value) => match value.node() {
+                    Node::ByteString(byte_string) => self.push_byte_string(byte_string),
+                    Node::Integer(integer) => self.push_integer(integer),
+                    Node::List(list) => {
+                        self.push_generated(b"l");
+                        stack.push(Pending::End);
+                        stack.extend(list.iter().rev().map(Pending::Value));
+                    }
+                    Node::Dictionary(dictionary) => {
+                        self.push_generated(b"d");
+                        stack.push(Pending::End);
+                        for (key, value) in dictionary.iter().rev() {
+                            stack.push(Pending::Value(value));
+                            stack.push(Pending::Key(key.as_ref()));
+                        }
+                    }
+                },
+                Pending::Key(key) => self.push_byte_string(key),
+                Pending::End => self.push_generated(b"e"),
+            }
+        }
+    }
+
+    fn push_generated(&mut self, bytes: &[u8]) {
+        self.buffer.extend_from_slice(bytes);
+        self.len += bytes.len();
+    }
+}
+
+impl Default for VectoredOutput<'_> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io;
+
+    use super::VectoredOutput;
+    use crate::{from_bytes, to_bytes, BorrowedValue, Integer, Value};
+
+    #[test]
+    fn matches_to_bytes() {
+        let bytes = include_bytes!("../../tests/torrents/debian.torrent");
+        let value: Value = from_bytes(bytes).unwrap();
+        let borrowed: BorrowedValue = from_bytes(bytes).unwrap();
+
+        for copy_threshold in [0, 1, 256, usize::MAX] {
+            let mut output = VectoredOutput::new().copy_threshold(copy_threshold);
+            output.push_value(&value);
+            output.push_borrowed_value(&borrowed);
+            assert_eq!(bytes.len() * 2, output.len());
+
+            let mut written = Vec::new();
+            output.write_to(&mut written).unwrap();
+            assert_eq!([&bytes[..], &bytes[..]].concat(), written);
+        }
+
+        let value = Value::List(vec![
+            Value::Integer(Integer::from(-42i64)),
+            Value::Integer(Integer::from(u64::MAX)),
+        ]);
+        let mut output = VectoredOutput::new();
+        output.push_value(&value);
+        let mut written = Vec::new();
+        output.write_to(&mut written).unwrap();
+        assert_eq!(to_bytes(&value).unwrap(), written);
+    }
+
+    #[test]
+    fn borrows_large_byte_strings() {
+        let pieces = vec![0xAA; 1024];
+        let mut output = VectoredOutput::new();
+        output.push_byte_string(b"pieces");
+        output.push_byte_string(&pieces);
+        output.push_byte_string(&pieces);
+
+        let slices = output.io_slices();
+        assert_eq!(4, slices.len());
+        assert_eq!(b"6:pieces1024:", &*slices[0]);
+        assert_eq!(pieces.as_ptr(), slices[1].as_ptr());
+        assert_eq!(b"1024:", &*slices[2]);
+        assert_eq!(pieces.as_ptr(), slices[3].as_ptr());
+    }
+
+    #[test]
+    fn partial_writes() {
+        /// Accepts at most 3 bytes per call
+        struct Trickle(Vec<u8>);
+
+        impl io::Write for Trickle {
+            fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+                let len = buf.len().min(3);
+                self.0.extend_from_slice(&buf[..len]);
+                Ok(len)
+            }
+
+            fn flush(&mut self) -> io::Result<()> {
+                Ok(())
+            }
+        }
+
+        let data = vec![7; 300];
+        let mut output = VectoredOutput::new().copy_threshold(10);
+        output.push_byte_string(b"spam");
+        output.push_byte_string(&data);
+
+        let mut writer = Trickle(Vec::new());
+        output.write_to(&mut writer).unwrap();
+        assert_eq!([&b"4:spam300:"[..], &data].concat(), writer.0);
+    }
+}
@@ -0,0 +1,121 @@
+use alloc::vec::Vec;
+
+use crate::Result;
+
+/// Default number of bytes a serializer buffers before writing them out.
+pub(crate) const BUFFER_SIZE: usize = 8 * 1024;
+
+/// A destination for encoded bytes.
+///
+/// With the `std` feature every [`std::io::Write`] is a `Write`,
+/// without it `Vec<u8>` and `&mut Vec<u8>` are, and anything else can implement it.
+pub trait Write {
+    /// Writes the whole buffer.
+    ///
+    /// # Errors
+    /// Returns an error if the buffer couldn't be written completely.
+    fn write_all(&mut self, buf: &[u8]) -> Result<()>;
+}
+
+#[cfg(feature = "std")]
+impl<W> Write for W
+where
+    W: ?Sized + std::io::Write,
+{
+    #[inline]
+    fn write_all(&mut self, buf: &[u8]) -> Result<()> {
+        std::io::Write::write_all(self, buf).map_err(Into::into)
+    }
+}
+
+#[cfg(not(feature = "std"))]
+impl Write for Vec<u8> {
+    #[inline]
+    fn write_all(&mut self, buf: &[u8]) -> Result<()> {
+        self.extend_from_slice(buf);
+        Ok(())
+    }
+}
+
+#[cfg(not(feature = "std"))]
+impl Write for &mut Vec<u8> {
+    #[inline]
+    fn write_all(&mut self, buf: &[u8]) -> Result<()> {
+        self.extend_from_slice(buf);
+        Ok(())
+    }
+}
+
+/// Buffers the output of a serializer to write it out in large chunks.
+pub(crate) struct Output<W> {
+    /// Where the buffer is written out, everything stays in the buffer without it
+    writer: Option<W>,
+    pub(crate) buffer: Vec<u8>,
+    buffer_size: usize,
+}
+
+impl<W> Output<W>
+where
+    W: Write,
+{
+    pub(crate) const fn new(writer: W) -> Self {
+        Self {
+            writer: Some(writer),
+            buffer: Vec::new(),
+            buffer_size: BUFFER_SIZE,
+        }
+    }
+
+    /// Keeps the whole output in the buffer, to be taken with [`into_buffer`](Self::into_buffer)
+    pub(crate) fn in_memory() -> Self {
+        Self {
+            writer: None,
+            buffer: Vec::with_capacity(128),
+            buffer_size: usize::MAX,
+        }
+    }
+
+    pub(crate) fn set_buffer_size(&mut self, buffer_size: usize) {
+        if self.writer.is_some() {
+            self.buffer_size = buffer_size;
+        }
+    }
+
+    pub(crate) fn into_buffer(self) -> Vec<u8> {
+        self.buffer
+    }
+
+    /// Appends bytes to the buffer, writing it out first if it is full and not `held`.
+    ///
+    /// Chunks at least as large as the buffer skip it when nothing is held.
+    #[inline]
+    pub(crate) fn write(&mut self, buf: &[u8], held: bool) -> Result<()> {
+        if !held && self.buffer.len() + buf.len() > self.buffer_size {
+            if let Some(writer) = &mut self.writer {
+                if !self.buffer.is_empty() {
+                    writer.write_all(&self.buffer)?;
+                    self.buffer.clear();
+                }
+
+                if buf.len() >= self.buffer_size {
+                    return writer.write_all(buf);
+                }
+            }
+        }
+
+        self.buffer.extend_from_slice(buf);
+        Ok(())
+    }
+
+    /// Writes out everything in the buffer
+    pub(crate) fn flush(&mut self) -> Result<()> {
+        if let Some(writer) = &mut self.writer {
+            if !self.buffer.is_empty() {
+                writer.write_all(&self.buffer)?;
+                self.buffer.clear();
+            }
+        }
+
+        Ok(())
+    }
+}
@@ -0,0 +1,200 @@
+use alloc::{collections::BTreeMap, string::String, vec::Vec};
+use core::fmt::Debug;
+use serde::{de::Visitor, Deserialize, Serialize};
+
+use crate::{ByteString, Integer, Value};
+
+/// Like [`Value`] but byte strings borrow from the input instead of being copied.
+///
+/// Deserializing a `BorrowedValue` from [`from_bytes`](crate::from_bytes) never copies byte strings,
+/// which is useful for large values such as the pieces of a torrent.
+#[derive(PartialEq, Eq, PartialOrd, Ord)]
+pub enum BorrowedValue<'a> {
+    /// Represents a bencode byte string.
+    ByteString(&'a [u8]),
+    /// Represents a bencode integer.
+    Integer(Integer),
+    /// Represents a bencode list.
+    List(Vec<BorrowedValue<'a>>),
+    /// Represents a bencode dictionary.
+    Dictionary(BTreeMap<&'a [u8], BorrowedValue<'a>>),
+}
+
+impl<'a> Debug for BorrowedValue<'a> {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        match self {
+            Self::ByteString(value) => f
+                .debug_tuple("ByteString")
+                .field(&String::from_utf8_lossy(value))
+                .finish(),
+            Self::Integer(value) => Debug::fmt(value, f),
+            Self::List(value) => {
+                f.write_str("List(")?;
+                Debug::fmt(value, f)?;
+                f.write_str(")")
+            }
+            Self::Dictionary(value) => {
+                f.write_str("Dictionary(")?;
+                f.debug_map()
+                    .entries(
+                        value
+                            .iter()
+                            .map(|(key, value)| (String::from_utf8_lossy(key), value)),
+                    )
+                    .finish()?;
+                f.write_str(")")
+            }
+        }
+    }
+}
+
+impl<'a> BorrowedValue<'a> {
+    /// Copies all the borrowed byte strings into an owned [`Value`].
+    pub fn to_owned(&self) -> Value {
+        match self {
+            Self::ByteString(byte_string) => Value::ByteString(ByteString::from(*byte_string)),
+            Self::Integer(integer) => Value::Integer(*integer),
+            Self::List(list) => Value::List(list.iter().map(Self::to_owned).collect()),
+            Self::Dictionary(dictionary) => Value::Dictionary(
+                dictionary
+                    .iter()
+                    .map(|(key, value)| (ByteString::from(*key), value.to_owned()))
+                    .collect(),
+            ),
+        }
+    }
+}
+
+impl<'a> From<BorrowedValue<'a>> for Value {
+    fn from(value: BorrowedValue<'a>) -> Self {
+        value.to_owned()
+    }
+}
+
+impl<'a> Serialize for BorrowedValue<'a> {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: serde::Serializer,
+    {
+        match self {
+            Self::ByteString(byte_string) => serializer.serialize_bytes(byte_string),
+            Self::Integer(integer) => integer.serialize(serializer),
+            Self::List(list) => list.serialize(serializer),
+            Self::Dictionary(dictionary) => serializer.collect_map(
+                dictionary
+                    .iter()
+                    .map(|(key, value)| (Self::ByteString(key), value)),
+            ),
+        }
+    }
+}
+
+impl<'de: 'a, 'a> Deserialize<'de> for BorrowedValue<'a> {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: serde::Deserializer<'de>,
+    {
+        struct BorrowedValueVisitor;
+
+        impl<'de> Visitor<'de> for BorrowedValueVisitor {
+            type Value = BorrowedValue<'de>;
+
+            fn expecting(&self, formatter: &mut core::fmt::Formatter) -> core::fmt::Result {
+                formatter.write_str("any valid Bencode value borrowed from the input")
+            }
+
+            fn visit_i64<E>(self, value: i64) -> Result<Self::Value, E>
+            where
+                E: serde::de::Error,
+            {
+                Ok(BorrowedValue::Integer(value.into()))
+            }
+
+            fn visit_u64<E>(self, value: u64) -> Result<Self::Value, E>
+            where
+                E: serde::de::Error,
+            {
+                Ok(BorrowedValue::Integer(value.into()))
+            }
+
+            fn visit_borrowed_str<E>(self, value: &'de str) -> Result<Self::Value, E>
+            where
+                E: serde::de::Error,
+            {
+                Ok(BorrowedValue::ByteString(value.as_bytes()))
+            }
+
+            fn visit_borrowed_bytes<E>(self, value: &'de [u8]) -> Result<Self::Value, E>
+            where
+                E: serde::de::Error,
+            {
+                Ok(BorrowedValue::ByteString(value))
+            }
+
+            fn visit_some<D>(self, deserializer: D) -> Result<Self::Value, D::Error>
+            where
+                D: serde::Deserializer<'de>,
+            {
+                Deserialize::deserialize(deserializer)
+            }
+
+            fn visit_newtype_struct<D>(self, deserializer: D) -> Result<Self::Value, D::Error>
+            where
+                D: serde::Deserializer<'de>,
+            {
+                Deserialize::deserialize(deserializer)
+            }
+
+            fn visit_seq<A>(self, mut seq: A) -> Result<Self::Value, A::Error>
+            where
+                A: serde::de::SeqAccess<'de>,
+            {
+                let mut vec = Vec::new();
+
+                while let Some(elem) = seq.next_element()? {
+                    vec.push(elem);
+                }
+
+                Ok(BorrowedValue::List(vec))
+            }
+
+            fn visit_map<A>(self, mut map: A) -> Result<Self::Value, A::Error>
+            where
+                A: serde::de::MapAccess<'de>,
+            {
+                let mut dict = BTreeMap::new();
+
+                while let Some((key, value)) = map.next_entry()? {
+                    dict.insert(key, value);
+                }
+
+                Ok(BorrowedValue::Dictionary(dict))
+            }
+        }
+
+        deserializer.deserialize_any(BorrowedValueVisitor)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::BorrowedValue;
+    use crate::{from_bytes, to_bytes, Value};
+
+    #[test]
+    fn borrows_from_the_input() {
+        let bytes = include_bytes!("../../tests/torrents/debian.torrent");
+        let value: BorrowedValue = from_bytes(bytes).unwrap();
+
+        let BorrowedValue::Dictionary(dictionary) = &value else {
+            panic!("torrent is not a dictionary")
+        };
+        let Some(BorrowedValue::ByteString(announce)) = dictionary.get(&b"announce"[..]) else {
+            panic!("torrent has no announce")
+        };
+        assert!(bytes.as_ptr_range().contains(&announce.as_ptr()));
+
+        assert_eq!(bytes.to_vec(), to_bytes(&value).unwrap());
+        assert_eq!(from_bytes::<Value>(bytes).unwrap(), value.to_owned());
+    }
+}
@@ -1,8 +1,176 @@
-use std::collections::BTreeMap;
+use alloc::{collections::BTreeMap, string::String, vec::Vec};
+use core::mem;
+
+use serde::{
+    de::{
+        value::{
+            BorrowedBytesDeserializer, BorrowedStrDeserializer, MapDeserializer, SeqDeserializer,
+        },
+        DeserializeSeed, IntoDeserializer, MapAccess, SeqAccess, Visitor,
+    },
+    Deserialize,
+};
+
+use crate::{
+    byte_string::{ByteString, ByteStringVisitor},
+    de::visit_byte_seq,
+    value::Value,
+    Error,
+};
+
+/// Name of the newtype struct [`Value`] deserializes as, recognized by [`Deserializer`](crate::Deserializer).
+///
+/// It answers with a single-entry map from this name to the raw encoded value,
+/// which is then decoded without recursion by [`Value::from_bytes`].
+pub(crate) const NAME: &str = "$bde::private::Value";
+
+/// Key of the single entry of a [`RawValue`]
+///
+/// [`KeySeed`] recognizes it by address rather than by content, so other
+/// deserializers producing a [`NAME`] key can't switch [`Value`] into raw mode.
+static RAW_VALUE_KEY: &str = NAME;
+
+/// The single-entry map [`Deserializer`](crate::Deserializer) visits when asked for a [`NAME`] newtype
+pub(crate) struct RawValue<'de> {
+    raw: &'de [u8],
+    key_visited: bool,
+}
+
+impl<'de> RawValue<'de> {
+    pub(crate) const fn new(raw: &'de [u8]) -> Self {
+        Self {
+            raw,
+            key_visited: false,
+        }
+    }
+}
+
+impl<'de> MapAccess<'de> for RawValue<'de> {
+    type Error = Error;
+
+    fn next_key_seed<K>(&mut self, seed: K) -> Result<Option<K::Value>, Error>
+    where
+        K: DeserializeSeed<'de>,
+    {
+        if self.key_visited {
+            return Ok(None);
+        }
+
+        self.key_visited = true;
+        seed.deserialize(BorrowedStrDeserializer::new(RAW_VALUE_KEY))
+            .map(Some)
+    }
+
+    fn next_value_seed<V>(&mut self, seed: V) -> Result<V::Value, Error>
+    where
+        V: DeserializeSeed<'de>,
+    {
+        seed.deserialize(BorrowedBytesDeserializer::new(self.raw))
+    }
+}
+
+/// First key of a map visited as a [`Value`]
+enum Key {
+    /// The key of a [`RawValue`]
+    Raw,
+    Entry(ByteString),
+}
+
+/// Tells the key of a [`RawValue`] apart from ordinary dictionary keys
+struct KeySeed;
+
+impl<'de> DeserializeSeed<'de> for KeySeed {
+    type Value = Key;
+
+    fn deserialize<D>(self, deserializer: D) -> Result<Key, D::Error>
+    where
+        D: serde::Deserializer<'de>,
+    {
+        deserializer.deserialize_byte_buf(self)
+    }
+}
+
+impl<'de> Visitor<'de> for KeySeed {
+    type Value = Key;
+
+    fn expecting(&self, formatter: &mut core::fmt::Formatter) -> core::fmt::Result {
+        ByteStringVisitor.expecting(formatter)
+    }
+
+    fn visit_seq<A>(self, seq: A) -> Result<Key, A::Error>
+    where
+        A: SeqAccess<'de>,
+    {
+        ByteStringVisitor.visit_seq(seq).map(Key::Entry)
+    }
+
+    fn visit_bytes<E>(self, v: &[u8]) -> Result<Key, E>
+    where
+        E: serde::de::Error,
+    {
+        ByteStringVisitor.visit_bytes(v).map(Key::Entry)
+    }
+
+    fn visit_byte_buf<E>(self, v: Vec<u8>) -> Result<Key, E>
+    where
+        E: serde::de::Error,
+    {
+        ByteStringVisitor.visit_byte_buf(v).map(Key::Entry)
+    }
+
+    fn visit_borrowed_str<E>(self, v: &'de str) -> Result<Key, E>
+    where
+        E: serde::de::Error,
+    {
+        if core::ptr::eq(v, RAW_VALUE_KEY) {
+            return Ok(Key::Raw);
+        }
+        self.visit_str(v)
+    }
+
+    fn visit_str<E>(self, v: &str) -> Result<Key, E>
+    where
+        E: serde::de::Error,
+    {
+        ByteStringVisitor.visit_str(v).map(Key::Entry)
+    }
+
+    fn visit_string<E>(self, v: String) -> Result<Key, E>
+    where
+        E: serde::de::Error,
+    {
+        ByteStringVisitor.visit_string(v).map(Key::Entry)
+    }
+}
+
+/// Decodes the raw value of a [`RawValue`]
+struct RawValueSeed;
+
+impl<'de> DeserializeSeed<'de> for RawValueSeed {
+    type Value = Value;
+
+    fn deserialize<D>(self, deserializer: D) -> Result<Value, D::Error>
+    where
+        D: serde::Deserializer<'de>,
+    {
+        deserializer.deserialize_bytes(self)
+    }
+}
 
-use serde::{de::Visitor, Deserialize};
+impl<'de> Visitor<'de> for RawValueSeed {
+    type Value = Value;
+
+    fn expecting(&self, formatter: &mut core::fmt::Formatter) -> core::fmt::Result {
+        formatter.write_str("an encoded Bencode value")
+    }
 
-use crate::{byte_string::ByteString, value::Value, Error};
+    fn visit_bytes<E>(self, value: &[u8]) -> Result<Value, E>
+    where
+        E: serde::de::Error,
+    {
+        Value::from_bytes(value).map_err(E::custom)
+    }
+}
 
 impl<'de> Deserialize<'de> for Value {
     fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
@@ -14,7 +182,7 @@ impl<'de> Deserialize<'de> for Value {
         impl<'de> Visitor<'de> for ValueVisitor {
             type Value = Value;
 
-            fn expecting(&self, formatter: &mut std::fmt::Formatter) -> std::fmt::Result {
+            fn expecting(&self, formatter: &mut core::fmt::Formatter) -> core::fmt::Result {
                 formatter.write_str("any valid Bencode value")
             }
 
@@ -57,7 +225,7 @@ impl<'de> Deserialize<'de> for Value {
             where
                 D: serde::Deserializer<'de>,
             {
-                Deserialize::deserialize(deserializer)
+                deserializer.deserialize_any(self)
             }
 
             fn visit_seq<A>(self, mut seq: A) -> Result<Self::Value, A::Error>
@@ -79,6 +247,13 @@ impl<'de> Deserialize<'de> for Value {
             {
                 let mut dict = BTreeMap::new();
 
+                let key = match map.next_key_seed(KeySeed)? {
+                    None => return Ok(Value::Dictionary(dict)),
+                    Some(Key::Raw) => return map.next_value_seed(RawValueSeed),
+                    Some(Key::Entry(key)) => key,
+                };
+                dict.insert(key, map.next_value()?);
+
                 while let Some((key, value)) = map.next_entry()? {
                     dict.insert(key, value);
                 }
@@ -87,7 +262,7 @@ impl<'de> Deserialize<'de> for Value {
             }
         }
 
-        deserializer.deserialize_any(ValueVisitor)
+        deserializer.deserialize_newtype_struct(NAME, ValueVisitor)
     }
 }
 
@@ -98,11 +273,14 @@ impl<'de> serde::Deserializer<'de> for Value {
     where
         V: Visitor<'de>,
     {
-        match self {
-            Value::ByteString(byte_string) => visitor.visit_bytes(&byte_string),
-            Value::Integer(_integer) => todo!(),
-            Value::List(_list) => todo!(),
-            Value::Dictionary(_dictionary) => todo!(),
+        match &self {
+            Value::ByteString(byte_string) => visitor.visit_bytes(byte_string),
+            Value::Integer(integer) => match integer.as_u64() {
+                Some(integer) => visitor.visit_u64(integer),
+                None => visitor.visit_i64(integer.as_i64().ok_or(Error::OutOfBound)?),
+            },
+            Value::List(_) => self.deserialize_seq(visitor),
+            Value::Dictionary(_) => self.deserialize_map(visitor),
         }
     }
 
@@ -113,60 +291,84 @@ impl<'de> serde::Deserializer<'de> for Value {
         todo!()
     }
 
-    fn deserialize_i8<V>(self, _visitor: V) -> Result<V::Value, Self::Error>
+    fn deserialize_i8<V>(self, visitor: V) -> Result<V::Value, Self::Error>
     where
         V: Visitor<'de>,
     {
-        todo!()
+        match self {
+            Value::Integer(_) => self.deserialize_any(visitor),
+            _ => Err(Error::InvalidType),
+        }
     }
 
-    fn deserialize_i16<V>(self, _visitor: V) -> Result<V::Value, Self::Error>
+    fn deserialize_i16<V>(self, visitor: V) -> Result<V::Value, Self::Error>
     where
         V: Visitor<'de>,
     {
-        todo!()
+        match self {
+            Value::Integer(_) => self.deserialize_any(visitor),
+            _ => Err(Error::InvalidType),
+        }
     }
 
-    fn deserialize_i32<V>(self, _visitor: V) -> Result<V::Value, Self::Error>
+    fn deserialize_i32<V>(self, visitor: V) -> Result<V::Value, Self::Error>
     where
         V: Visitor<'de>,
     {
-        todo!()
+        match self {
+            Value::Integer(_) => self.deserialize_any(visitor),
+            _ => Err(Error::InvalidType),
+        }
     }
 
-    fn deserialize_i64<V>(self, _visitor: V) -> Result<V::Value, Self::Error>
+    fn deserialize_i64<V>(self, visitor: V) -> Result<V::Value, Self::Error>
     where
         V: Visitor<'de>,
     {
-        todo!()
+        match self {
+            Value::Integer(_) => self.deserialize_any(visitor),
+            _ => Err(Error::InvalidType),
+        }
     }
 
-    fn deserialize_u8<V>(self, _visitor: V) -> Result<V::Value, Self::Error>
+    fn deserialize_u8<V>(self, visitor: V) -> Result<V::Value, Self::Error>
     where
         V: Visitor<'de>,
     {
-        todo!()
+        match self {
+            Value::Integer(_) => self.deserialize_any(visitor),
+            _ => Err(Error::InvalidType),
+        }
     }
 
-    fn deserialize_u16<V>(self, _visitor: V) -> Result<V::Value, Self::Error>
+    fn deserialize_u16<V>(self, visitor: V) -> Result<V::Value, Self::Error>
     where
         V: Visitor<'de>,
     {
-        todo!()
+        match self {
+            Value::Integer(_) => self.deserialize_any(visitor),
+            _ => Err(Error::InvalidType),
+        }
     }
 
-    fn deserialize_u32<V>(self, _visitor: V) -> Result<V::Value, Self::Error>
+    fn deserialize_u32<V>(self, visitor: V) -> Result<V::Value, Self::Error>
     where
         V: Visitor<'de>,
     {
-        todo!()
+        match self {
+            Value::Integer(_) => self.deserialize_any(visitor),
+            _ => Err(Error::InvalidType),
+        }
     }
 
-    fn deserialize_u64<V>(self, _visitor: V) -> Result<V::Value, Self::Error>
+    fn deserialize_u64<V>(self, visitor: V) -> Result<V::Value, Self::Error>
     where
         V: Visitor<'de>,
     {
-        todo!()
+        match self {
+            Value::Integer(_) => self.deserialize_any(visitor),
+            _ => Err(Error::InvalidType),
+        }
     }
 
     fn deserialize_f32<V>(self, _visitor: V) -> Result<V::Value, Self::Error>
@@ -211,12 +413,12 @@ impl<'de> serde::Deserializer<'de> for Value {
         self.deserialize_byte_buf(visitor)
     }
 
-    fn deserialize_byte_buf<V>(self, visitor: V) -> Result<V::Value, Self::Error>
+    fn deserialize_byte_buf<V>(mut self, visitor: V) -> Result<V::Value, Self::Error>
     where
         V: Visitor<'de>,
     {
-        match self {
-            Value::ByteString(value) => visitor.visit_byte_buf(value.inner()),
+        match &mut self {
+            Value::ByteString(value) => visitor.visit_byte_buf(mem::take(value).inner()),
             _ => Err(Error::InvalidType),
         }
     }
@@ -249,45 +451,74 @@ impl<'de> serde::Deserializer<'de> for Value {
     fn deserialize_newtype_struct<V>(
         self,
         _name: &'static str,
-        _visitor: V,
+        visitor: V,
     ) -> Result<V::Value, Self::Error>
     where
         V: Visitor<'de>,
     {
-        todo!()
+        visitor.visit_newtype_struct(self)
     }
 
-    fn deserialize_seq<V>(self, _visitor: V) -> Result<V::Value, Self::Error>
+    fn deserialize_seq<V>(mut self, visitor: V) -> Result<V::Value, Self::Error>
     where
         V: Visitor<'de>,
     {
-        todo!()
+        match &mut self {
+            Value::List(list) => {
+                let mut seq = SeqDeserializer::new(mem::take(list).into_iter());
+                let value = visitor.visit_seq(&mut seq)?;
+                seq.end()?;
+                Ok(value)
+            }
+            // Byte strings are valid sources for sequences of u8
+            Value::ByteString(byte_string) => visit_byte_seq(byte_string, visitor),
+            _ => Err(Error::InvalidType),
+        }
     }
 
-    fn deserialize_tuple<V>(self, _len: usize, _visitor: V) -> Result<V::Value, Self::Error>
+    fn deserialize_tuple<V>(self, len: usize, visitor: V) -> Result<V::Value, Self::Error>
     where
         V: Visitor<'de>,
     {
-        todo!()
+        match &self {
+            Value::ByteString(byte_string) if byte_string.len() != len => {
+                Err(Error::ByteStringLengthMismatch {
+                    expected: len,
+                    found: byte_string.len(),
+                })
+            }
+            _ => self.deserialize_seq(visitor),
+        }
     }
 
     fn deserialize_tuple_struct<V>(
         self,
         _name: &'static str,
-        _len: usize,
-        _visitor: V,
+        len: usize,
+        visitor: V,
     ) -> Result<V::Value, Self::Error>
     where
         V: Visitor<'de>,
     {
-        todo!()
+        self.deserialize_tuple(len, visitor)
     }
 
-    fn deserialize_map<V>(self, _visitor: V) -> Result<V::Value, Self::Error>
+    fn deserialize_map<V>(mut self, visitor: V) -> Result<V::Value, Self::Error>
     where
         V: Visitor<'de>,
     {
-        todo!()
+        match &mut self {
+            Value::Dictionary(dict) => {
+                let entries = mem::take(dict)
+                    .into_iter()
+                    .map(|(key, value)| (Value::ByteString(key), value));
+                let mut map = MapDeserializer::new(entries);
+                let value = visitor.visit_map(&mut map)?;
+                map.end()?;
+                Ok(value)
+            }
+            _ => Err(Error::InvalidType),
+        }
     }
 
     fn deserialize_struct<V>(
@@ -328,3 +559,11 @@ impl<'de> serde::Deserializer<'de> for Value {
         todo!()
     }
 }
+
+impl<'de> IntoDeserializer<'de, Error> for Value {
+    type Deserializer = Self;
+
+    fn into_deserializer(self) -> Self::Deserializer {
+        self
+    }
+}
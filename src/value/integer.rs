@@ -1,19 +1,19 @@
+use core::fmt::{Debug, Display};
 use serde::Serialize;
-use std::fmt::{Debug, Display};
 
-#[derive(PartialEq, Eq, PartialOrd, Ord)]
+#[derive(Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
 pub struct Integer {
     inner: IntegerType,
 }
 
 impl Debug for Integer {
-    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
         write!(f, "Integer({})", self)
     }
 }
 
 impl Display for Integer {
-    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
         match self.inner {
             IntegerType::Negative(n) => f.write_str(itoa::Buffer::new().format(n)),
             IntegerType::Positive(n) => f.write_str(itoa::Buffer::new().format(n)),
@@ -21,7 +21,7 @@ impl Display for Integer {
     }
 }
 
-#[derive(PartialEq, Eq, PartialOrd, Ord)]
+#[derive(Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
 enum IntegerType {
     Negative(i64),
     Positive(u64),
@@ -46,7 +46,7 @@ impl From<u64> for Integer {
 impl Integer {
     pub const fn is_i64(&self) -> bool {
         match self.inner {
-            IntegerType::Positive(n) => n <= i64::max_value() as u64,
+            IntegerType::Positive(n) => n <= i64::MAX as u64,
             IntegerType::Negative(_) => true,
         }
     }
@@ -74,6 +74,16 @@ impl Integer {
             IntegerType::Negative(_) => None,
         }
     }
+
+    /// Length of this integer once encoded, including the surrounding "i" and "e"
+    pub(crate) fn encoded_len(&self) -> usize {
+        let digits = match self.inner {
+            IntegerType::Negative(n) => itoa::Buffer::new().format(n).len(),
+            IntegerType::Positive(n) => itoa::Buffer::new().format(n).len(),
+        };
+
+        digits + 2
+    }
 }
 
 impl Serialize for Integer {
@@ -0,0 +1,233 @@
+use core::{cmp::Ordering, fmt::Debug};
+use serde::Deserialize;
+
+use crate::{from_bytes, Deserializer, Result, Value};
+
+/// A validated bencode value that is only decoded when needed.
+///
+/// Creating a `LazyValue` checks the whole input once, then [`get`](Self::get) and [`index`](Self::index)
+/// find sub-elements by skipping over the encoded bytes, without building any list or dictionary.
+/// Any element can be decoded into a typed value with [`deserialize`](Self::deserialize)
+/// or into an owned [`Value`] with [`to_value`](Self::to_value).
+///
+/// ```
+/// use bde::LazyValue;
+///
+/// let torrent = LazyValue::new(b"d8:announce3:url4:infod6:lengthi42e4:name4:spamee").unwrap();
+///
+/// let name = torrent.get("info").and_then(|info| info.get("name"));
+/// assert_eq!(Some(&b"spam"[..]), name.and_then(|name| name.as_byte_string()));
+/// ```
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub struct LazyValue<'a> {
+    bytes: &'a [u8],
+}
+
+impl<'a> LazyValue<'a> {
+    /// Validates a bencode value without decoding it.
+    ///
+    /// # Errors
+    /// Returns the same errors as [`from_bytes`] if the input isn't valid bencode.
+    pub fn new(bytes: &'a [u8]) -> Result<Self> {
+        let mut deserializer = Deserializer::from_bytes(bytes);
+        deserializer.skip_value()?;
+        deserializer.check_trailing_bytes()?;
+        Ok(Self { bytes })
+    }
+
+    /// Returns the encoded bytes of this value.
+    pub const fn as_bytes(&self) -> &'a [u8] {
+        self.bytes
+    }
+
+    pub fn is_byte_string(&self) -> bool {
+        matches!(self.bytes.first(), Some(b'0'..=b'9'))
+    }
+
+    pub fn is_integer(&self) -> bool {
+        matches!(self.bytes.first(), Some(b'i'))
+    }
+
+    pub fn is_list(&self) -> bool {
+        matches!(self.bytes.first(), Some(b'l'))
+    }
+
+    pub fn is_dictionary(&self) -> bool {
+        matches!(self.bytes.first(), Some(b'd'))
+    }
+
+    pub fn as_byte_string(&self) -> Option<&'a [u8]> {
+        if self.is_byte_string() {
+            self.deserialize().ok()
+        } else {
+            None
+        }
+    }
+
+    pub fn as_integer(&self) -> Option<i64> {
+        if self.is_integer() {
+            self.deserialize().ok()
+        } else {
+            None
+        }
+    }
+
+    /// Returns the value of a dictionary entry.
+    ///
+    /// Entries are scanned in order and the search stops as soon as a greater key is found,
+    /// so only the values of the entries before the key are skipped.
+    /// Returns `None` if this isn't a dictionary or the key isn't there.
+    pub fn get(&self, key: impl AsRef<[u8]>) -> Option<Self> {
+        let key = key.as_ref();
+        let mut deserializer = self.open(b'd')?;
+        let mut found = None;
+
+        while deserializer.peek_byte().ok()? != b'e' {
+            let entry_key = deserializer.parse_byte_string().ok()?;
+
+            match entry_key.cmp(key) {
+                Ordering::Less => deserializer.skip_value().ok()?,
+                // Equal keys are allowed, the last one wins like it does for `Value`
+                Ordering::Equal => found = Some(self.next_element(&mut deserializer)?),
+                Ordering::Greater => break,
+            }
+        }
+
+        found
+    }
+
+    /// Returns an element of a list.
+    ///
+    /// The elements before it are skipped.
+    /// Returns `None` if this isn't a list or it is too short.
+    pub fn index(&self, index: usize) -> Option<Self> {
+        let mut deserializer = self.open(b'l')?;
+
+        for _ in 0..index {
+            if deserializer.peek_byte().ok()? == b'e' {
+                return None;
+            }
+            deserializer.skip_value().ok()?;
+        }
+
+        if deserializer.peek_byte().ok()? == b'e' {
+            return None;
+        }
+        self.next_element(&mut deserializer)
+    }
+
+    /// Deserializes this value into any type, borrowing from the input when possible.
+    ///
+    /// # Errors
+    /// Returns an error if the value doesn't match the type.
+    pub fn deserialize<T>(&self) -> Result<T>
+    where
+        T: Deserialize<'a>,
+    {
+        from_bytes(self.bytes)
+    }
+
+    /// Decodes this value into an owned [`Value`].
+    pub fn to_value(&self) -> Value {
+        Value::from_bytes(self.bytes).expect("lazy values are validated when created")
+    }
+
+    /// Starts reading a list or dictionary after its opening byte
+    fn open(&self, token: u8) -> Option<Deserializer<'a>> {
+        let mut deserializer = Deserializer::from_bytes(self.bytes);
+        (deserializer.next_byte().ok()? == token).then_some(deserializer)
+    }
+
+    /// Skips the next element, returning it as a lazy value
+    fn next_element(&self, deserializer: &mut Deserializer<'a>) -> Option<Self> {
+        let start = deserializer.index;
+        deserializer.skip_value().ok()?;
+
+        Some(Self {
+            bytes: &self.bytes[start..deserializer.index],
+        })
+    }
+}
+
+impl<'a> Debug for LazyValue<'a> {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        f.debug_tuple("LazyValue")
+            .field(&alloc::string::String::from_utf8_lossy(self.bytes))
+            .finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_derive::Deserialize;
+
+    use super::LazyValue;
+    use crate::{from_bytes, Error, Value};
+
+    #[test]
+    fn get_and_index() {
+        let bytes = include_bytes!("../../tests/torrents/archlinux.torrent");
+        let torrent = LazyValue::new(bytes).unwrap();
+        let value = from_bytes::<Value>(bytes).unwrap();
+        let Value::Dictionary(dictionary) = &value else {
+            panic!("torrent is not a dictionary")
+        };
+
+        for (key, expected) in dictionary {
+            assert_eq!(expected, &torrent.get(key).unwrap().to_value());
+        }
+        assert_eq!(None, torrent.get("missing"));
+        assert_eq!(None, torrent.index(0));
+
+        let info = torrent.get("info").unwrap();
+        assert!(info.is_dictionary());
+        assert_eq!(
+            Some(&b"archlinux-2023.01.01-x86_64.iso"[..]),
+            info.get("name").and_then(|name| name.as_byte_string())
+        );
+
+        let list = LazyValue::new(b"li1e4:spamli2eei3ee").unwrap();
+        assert_eq!(Some(1), list.index(0).and_then(|value| value.as_integer()));
+        assert_eq!(Some(&b"spam"[..]), list.index(1).unwrap().as_byte_string());
+        assert_eq!(b"li2ee", list.index(2).unwrap().as_bytes());
+        assert_eq!(Some(3), list.index(3).unwrap().as_integer());
+        assert_eq!(None, list.index(4));
+
+        let duplicates = LazyValue::new(b"d1:ai1e1:ai2e1:cli3eee").unwrap();
+        assert_eq!(Some(2), duplicates.get("a").unwrap().as_integer());
+        assert_eq!(None, duplicates.get("b"));
+        assert_eq!(b"li3ee", duplicates.get("c").unwrap().as_bytes());
+    }
+
+    #[test]
+    fn deserialize() {
+        #[derive(Debug, PartialEq, Deserialize)]
+        struct Info<'a> {
+            length: u64,
+            name: &'a str,
+        }
+
+        let torrent = LazyValue::new(b"d4:infod6:lengthi42e4:name4:spamee").unwrap();
+
+        assert_eq!(
+            Info {
+                length: 42,
+                name: "spam"
+            },
+            torrent.get("info").unwrap().deserialize().unwrap()
+        );
+    }
+
+    #[test]
+    fn invalid_input() {
+        assert!(matches!(
+            LazyValue::new(b"d1:bi1e1:ai2ee"),
+            Err(Error::UnsortedKeys)
+        ));
+        assert!(matches!(LazyValue::new(b"li1e"), Err(Error::Eof)));
+        assert!(matches!(
+            LazyValue::new(b"i1ei2e"),
+            Err(Error::TrailingBytes)
+        ));
+    }
+}
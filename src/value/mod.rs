@@ -1,14 +1,24 @@
+use alloc::{collections::BTreeMap, string::String, vec, vec::Vec};
+use core::{fmt::Debug, mem};
 use serde::{de::DeserializeOwned, Serialize};
-use std::{collections::BTreeMap, fmt::Debug};
 
-use crate::{byte_string::ByteString, Error};
+use crate::{byte_string::ByteString, ser::encoder::byte_string_len, Deserializer, Error};
 
+mod borrowed;
 mod de;
 mod integer;
+mod lazy;
 mod ser;
+#[cfg(feature = "bytes")]
+mod shared;
 
+pub use borrowed::BorrowedValue;
+pub(crate) use de::{RawValue, NAME};
 pub use integer::Integer;
+pub use lazy::LazyValue;
 pub use ser::ValueSerializer;
+#[cfg(feature = "bytes")]
+pub use shared::{from_bytes_shared, SharedValue};
 
 pub type Dictionary<V = Value> = BTreeMap<ByteString, V>;
 
@@ -26,7 +36,7 @@ pub enum Value {
 }
 
 impl Debug for Value {
-    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
         match self {
             Self::ByteString(value) => f
                 .debug_tuple("ByteString")
@@ -48,6 +58,49 @@ impl Debug for Value {
 }
 
 impl Value {
+    /// Deserializes a value like [`from_bytes`](crate::from_bytes) does, returning the same errors.
+    ///
+    /// Lists and dictionaries are parsed with an explicit stack instead of recursion,
+    /// so any nesting depth can be decoded without overflowing the stack.
+    ///
+    /// # Errors
+    /// Returns an error if the input isn't valid bencode.
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self, Error> {
+        let mut deserializer = Deserializer::from_bytes(bytes);
+        let value = deserializer.parse_value()?;
+        deserializer.check_trailing_bytes()?;
+        Ok(value)
+    }
+
+    /// Returns the length of this value once encoded, without encoding it.
+    ///
+    /// This is the length of the output of [`to_bytes`](crate::to_bytes) for this value.
+    /// Like [`Value::from_bytes`], nested values are visited without recursion.
+    pub fn encoded_len(&self) -> usize {
+        let mut len = 0;
+        let mut stack = vec![self];
+
+        while let Some(value) = stack.pop() {
+            len += match value {
+                Self::ByteString(byte_string) => byte_string_len(byte_string.len()),
+                Self::Integer(integer) => integer.encoded_len(),
+                Self::List(list) => {
+                    stack.extend(list);
+                    2
+                }
+                Self::Dictionary(dictionary) => {
+                    for (key, value) in dictionary {
+                        len += byte_string_len(key.len());
+                        stack.push(value);
+                    }
+                    2
+                }
+            };
+        }
+
+        len
+    }
+
     pub const fn is_byte_string(&self) -> bool {
         matches!(self, Self::ByteString(_))
     }
@@ -93,11 +146,33 @@ impl Value {
     }
 }
 
+impl Drop for Value {
+    /// Tears down nested lists and dictionaries iteratively, so dropping deep values can't overflow the stack.
+    fn drop(&mut self) {
+        let mut stack = match self {
+            Self::List(list) if !list.is_empty() => mem::take(list),
+            Self::Dictionary(dictionary) if !dictionary.is_empty() => {
+                mem::take(dictionary).into_values().collect()
+            }
+            _ => return,
+        };
+
+        // Every value is emptied before being dropped, so the drop above returns right away
+        while let Some(mut value) = stack.pop() {
+            match &mut value {
+                Self::List(list) => stack.append(list),
+                Self::Dictionary(dictionary) => stack.extend(mem::take(dictionary).into_values()),
+                _ => {}
+            }
+        }
+    }
+}
+
 pub fn to_value<T>(value: T) -> Result<Value, Error>
 where
     T: Serialize,
 {
-    value.serialize(ValueSerializer)
+    value.serialize(ValueSerializer::new())
 }
 
 pub fn from_value<T>(value: Value) -> Result<T, Error>
@@ -109,9 +184,28 @@ where
 
 #[cfg(test)]
 mod tests {
-    use super::{ByteString, Integer, Value};
-    use serde_test::{assert_tokens, Token};
-    use std::collections::BTreeMap;
+    use super::{from_value, ByteString, Dictionary, Integer, Value, NAME};
+    use crate::{from_bytes, Error};
+    use alloc::collections::BTreeMap;
+    use serde_test::{assert_de_tokens, assert_tokens, Token};
+
+    const DEPTH: usize = 1_000_000;
+
+    /// Number of lists nested in each other, without recursion
+    fn list_depth(mut value: &Value) -> usize {
+        let mut depth = 1;
+        while let Value::List(list) = value {
+            match list.as_slice() {
+                [inner] => {
+                    value = inner;
+                    depth += 1;
+                }
+                [] => break,
+                _ => panic!("unexpected list {list:?}"),
+            }
+        }
+        depth
+    }
 
     #[test]
     fn deserialize_and_serialize_dictionary() {
@@ -142,4 +236,156 @@ mod tests {
             ],
         )
     }
+
+    #[test]
+    fn from_bytes_matches_deserialize() {
+        let torrent = include_bytes!("../../tests/torrents/fedora.torrent");
+        assert_eq!(
+            from_bytes::<Value>(torrent).unwrap(),
+            Value::from_bytes(torrent).unwrap()
+        );
+
+        for input in [
+            &b"d1:ai1e1:ai2ee"[..],
+            b"d1:bi1e1:ai2ee",
+            b"d1:ai1ei2ei3ee",
+            b"d1:ae",
+            b"li-0ee",
+            b"li01ee",
+            b"l4:spa",
+            b"li1eei2e",
+            b"lx",
+        ] {
+            assert_eq!(
+                from_bytes::<Value>(input).map_err(|error| error.to_string()),
+                Value::from_bytes(input).map_err(|error| error.to_string()),
+                "{:?}",
+                String::from_utf8_lossy(input)
+            );
+        }
+    }
+
+    #[test]
+    fn from_value_round_trips() {
+        let values = || {
+            [
+                Value::ByteString(ByteString::from("spam")),
+                Value::Integer(Integer::from(1u64)),
+                Value::Integer(Integer::from(-1i64)),
+                Value::List(vec![
+                    Value::Integer(Integer::from(1u64)),
+                    Value::List(Vec::new()),
+                ]),
+                Value::Dictionary(Dictionary::from([
+                    (ByteString::from("a"), Value::List(Vec::new())),
+                    (ByteString::from("b"), Value::Dictionary(Dictionary::new())),
+                ])),
+            ]
+        };
+
+        for (value, expected) in values().into_iter().zip(values()) {
+            assert_eq!(from_value::<Value>(value).unwrap(), expected);
+        }
+        assert_eq!(
+            from_value::<Vec<Value>>(Value::List(values().into())).unwrap(),
+            values()
+        );
+    }
+
+    #[test]
+    fn byte_string_is_only_a_u8_sequence() {
+        let spam = || Value::ByteString(ByteString::from("spam"));
+
+        assert_eq!(from_value::<Vec<u8>>(spam()).unwrap(), b"spam");
+        assert!(matches!(
+            from_value::<Vec<u32>>(spam()),
+            Err(Error::InvalidType)
+        ));
+        assert!(matches!(
+            from_value::<(String, String)>(Value::ByteString(ByteString::from("ab"))),
+            Err(Error::InvalidType)
+        ));
+    }
+
+    #[test]
+    fn raw_value_key_is_private() {
+        let dictionary = || {
+            Value::Dictionary(Dictionary::from([(
+                ByteString::from(NAME),
+                Value::ByteString(ByteString::from("i1e")),
+            )]))
+        };
+
+        assert_de_tokens(
+            &dictionary(),
+            &[
+                Token::Map { len: Some(1) },
+                Token::BorrowedStr(NAME),
+                Token::BorrowedBytes(b"i1e"),
+                Token::MapEnd,
+            ],
+        );
+        assert_eq!(
+            from_bytes::<Value>(b"d20:$bde::private::Value3:i1ee").unwrap(),
+            dictionary()
+        );
+        assert_eq!(from_value::<Value>(dictionary()).unwrap(), dictionary());
+    }
+
+    #[test]
+    fn deeply_nested_lists() {
+        let mut bytes = vec![b'l'; DEPTH];
+        bytes.resize(2 * DEPTH, b'e');
+
+        assert_eq!(DEPTH, list_depth(&Value::from_bytes(&bytes).unwrap()));
+
+        // Unclosed lists are dropped too
+        bytes.truncate(DEPTH);
+        assert!(Value::from_bytes(&bytes).is_err());
+    }
+
+    #[test]
+    fn deserialize_deeply_nested_lists() {
+        #[derive(serde_derive::Deserialize)]
+        struct Wrapper {
+            value: Value,
+        }
+
+        let mut bytes = vec![b'l'; DEPTH];
+        bytes.resize(2 * DEPTH, b'e');
+
+        assert_eq!(DEPTH, list_depth(&from_bytes::<Value>(&bytes).unwrap()));
+
+        let wrapper = [&b"d5:value"[..], &bytes, b"e"].concat();
+        assert_eq!(
+            DEPTH,
+            list_depth(&from_bytes::<Wrapper>(&wrapper).unwrap().value)
+        );
+
+        bytes.truncate(DEPTH);
+        assert!(from_bytes::<Value>(&bytes).is_err());
+    }
+
+    #[test]
+    fn deeply_nested_dictionaries() {
+        let mut bytes = b"d1:a".repeat(DEPTH);
+        bytes.extend_from_slice(b"i0e");
+        bytes.resize(bytes.len() + DEPTH, b'e');
+
+        drop(Value::from_bytes(&bytes).unwrap());
+    }
+
+    #[test]
+    fn drop_deeply_nested_values() {
+        let mut value = Value::List(Vec::new());
+        for depth in 0..DEPTH {
+            value = if depth % 2 == 0 {
+                Value::List(vec![value, Value::Integer(Integer::from(0i64))])
+            } else {
+                Value::Dictionary(Dictionary::from([(ByteString::from("a"), value)]))
+            };
+        }
+
+        drop(value);
+    }
 }
@@ -1,12 +1,24 @@
-use std::collections::BTreeMap;
+use alloc::{
+    collections::{btree_map::Entry, BTreeMap},
+    format, vec,
+    vec::Vec,
+};
 
-use serde::{ser::Impossible, Serialize};
+use serde::Serialize;
 
 use crate::{
-    ser::map_key_serializer::MapKeySerializer, to_value, value::Value, ByteString, Dictionary,
-    Error,
+    ser::{
+        byte_serializer::ByteSerializer, map_key_serializer::MapKeySerializer,
+        map_serializer::KeyOrderCheck, none_serializer::is_none, EnumRepresentation, KeyOrder,
+        SerializerOptions,
+    },
+    value::Value,
+    ByteString, Dictionary, Error,
 };
 
+#[cfg(feature = "std")]
+use crate::ser::read_bytes::{self, ReadBytesSerializer};
+
 impl Serialize for Value {
     fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
     where
@@ -21,11 +33,61 @@ impl Serialize for Value {
     }
 }
 
-pub struct ValueSerializer;
+#[derive(Clone, Copy)]
+pub struct ValueSerializer {
+    options: SerializerOptions,
+    /// Number of lists and dictionaries the value is nested in
+    depth: usize,
+}
 
 impl ValueSerializer {
     pub const fn new() -> Self {
-        Self {}
+        Self::with_options(SerializerOptions::new())
+    }
+
+    /// Creates a serializer with the given options.
+    ///
+    /// Dictionaries are always sorted and [`max_size`](SerializerOptions::max_size) is ignored.
+    pub const fn with_options(options: SerializerOptions) -> Self {
+        Self { options, depth: 0 }
+    }
+
+    /// Turns sequences, tuples and arrays made only of `u8` into byte strings instead of lists of integers.
+    ///
+    /// See [`UnsortedSerializer::u8_seq_as_bytes`](crate::UnsortedSerializer::u8_seq_as_bytes).
+    pub const fn u8_seq_as_bytes(mut self, enabled: bool) -> Self {
+        self.options = self.options.u8_seq_as_bytes(enabled);
+        self
+    }
+
+    /// Skips `None` elements of sequences and tuples instead of failing with [`Error::Unsupported`].
+    ///
+    /// See [`UnsortedSerializer::skip_none_in_seq`](crate::UnsortedSerializer::skip_none_in_seq).
+    pub const fn skip_none_in_seq(mut self, enabled: bool) -> Self {
+        self.options = self.options.skip_none_in_seq(enabled);
+        self
+    }
+
+    /// Returns the serializer for the elements of a new list or dictionary, failing if it is nested too deep
+    fn open(self) -> Result<Self, Error> {
+        self.options.check_depth(self.depth)?;
+
+        Ok(Self {
+            depth: self.depth + 1,
+            ..self
+        })
+    }
+
+    /// Wraps the payload of an enum variant in a single-entry dictionary or a list
+    fn variant(self, variant: &'static str, payload: Value) -> Value {
+        let variant = ByteString::from(variant);
+
+        match self.options.enum_representation {
+            EnumRepresentation::Dictionary => {
+                Value::Dictionary(BTreeMap::from([(variant, payload)]))
+            }
+            EnumRepresentation::List => Value::List(vec![Value::ByteString(variant), payload]),
+        }
     }
 }
 
@@ -40,23 +102,23 @@ impl serde::Serializer for ValueSerializer {
 
     type Error = Error;
 
-    // TODO: remove all this impossible with actual serializers.
     type SerializeSeq = SerializeVec;
 
-    type SerializeTuple = Impossible<Value, Error>;
+    type SerializeTuple = SerializeVec;
 
-    type SerializeTupleStruct = Impossible<Value, Error>;
+    type SerializeTupleStruct = SerializeVec;
 
-    type SerializeTupleVariant = Impossible<Value, Error>;
+    type SerializeTupleVariant = SerializeTupleVariant;
 
     type SerializeMap = SerializeMap;
 
     type SerializeStruct = SerializeMap;
 
-    type SerializeStructVariant = Impossible<Value, Error>;
+    type SerializeStructVariant = SerializeStructVariant;
 
-    fn serialize_bool(self, _value: bool) -> Result<Self::Ok, Self::Error> {
-        Err(Error::Unsupported("bool"))
+    fn serialize_bool(self, value: bool) -> Result<Self::Ok, Self::Error> {
+        self.options.check_non_bencode("bool")?;
+        self.serialize_u8(u8::from(value))
     }
 
     #[inline]
@@ -97,16 +159,19 @@ impl serde::Serializer for ValueSerializer {
         Ok(Value::Integer(value.into()))
     }
 
-    fn serialize_f32(self, _value: f32) -> Result<Self::Ok, Self::Error> {
-        Err(Error::Unsupported("f32"))
+    fn serialize_f32(self, value: f32) -> Result<Self::Ok, Self::Error> {
+        self.options.check_non_bencode("f32")?;
+        self.serialize_str(&format!("{value:?}"))
     }
 
-    fn serialize_f64(self, _value: f64) -> Result<Self::Ok, Self::Error> {
-        Err(Error::Unsupported("f64"))
+    fn serialize_f64(self, value: f64) -> Result<Self::Ok, Self::Error> {
+        self.options.check_non_bencode("f64")?;
+        self.serialize_str(&format!("{value:?}"))
     }
 
-    fn serialize_char(self, _value: char) -> Result<Self::Ok, Self::Error> {
-        Err(Error::Unsupported("char"))
+    fn serialize_char(self, value: char) -> Result<Self::Ok, Self::Error> {
+        self.options.check_non_bencode("char")?;
+        self.serialize_str(value.encode_utf8(&mut [0; 4]))
     }
 
     fn serialize_str(self, value: &str) -> Result<Self::Ok, Self::Error> {
@@ -121,9 +186,9 @@ impl serde::Serializer for ValueSerializer {
         Err(Error::Unsupported("None"))
     }
 
-    fn serialize_some<T: ?Sized>(self, value: &T) -> Result<Self::Ok, Self::Error>
+    fn serialize_some<T>(self, value: &T) -> Result<Self::Ok, Self::Error>
     where
-        T: Serialize,
+        T: ?Sized + Serialize,
     {
         value.serialize(self)
     }
@@ -145,60 +210,70 @@ impl serde::Serializer for ValueSerializer {
         self.serialize_str(variant)
     }
 
-    fn serialize_newtype_struct<T: ?Sized>(
+    // Only ReadBytes, which needs std, is recognized by name
+    #[cfg_attr(not(feature = "std"), allow(unused_variables))]
+    fn serialize_newtype_struct<T>(
         self,
-        _name: &'static str,
+        name: &'static str,
         value: &T,
     ) -> Result<Self::Ok, Self::Error>
     where
-        T: Serialize,
+        T: ?Sized + Serialize,
     {
+        #[cfg(feature = "std")]
+        if name == read_bytes::NAME {
+            let mut bytes = Vec::new();
+            value.serialize(&mut ReadBytesSerializer::new(&mut bytes, false))?;
+            return Ok(Value::ByteString(ByteString::from(bytes)));
+        }
+
         value.serialize(self)
     }
 
-    fn serialize_newtype_variant<T: ?Sized>(
+    fn serialize_newtype_variant<T>(
         self,
         _name: &'static str,
         _variant_index: u32,
-        _variant: &'static str,
-        _value: &T,
+        variant: &'static str,
+        value: &T,
     ) -> Result<Self::Ok, Self::Error>
     where
-        T: Serialize,
+        T: ?Sized + Serialize,
     {
-        todo!()
+        Ok(self.variant(variant, value.serialize(self.open()?)?))
     }
 
     fn serialize_seq(self, len: Option<usize>) -> Result<Self::SerializeSeq, Self::Error> {
-        Ok(SerializeVec {
-            vec: Vec::with_capacity(len.unwrap_or(0)),
-        })
+        SerializeVec::new(self, len)
     }
 
-    fn serialize_tuple(self, _len: usize) -> Result<Self::SerializeTuple, Self::Error> {
-        todo!()
+    fn serialize_tuple(self, len: usize) -> Result<Self::SerializeTuple, Self::Error> {
+        self.serialize_seq(Some(len))
     }
 
     fn serialize_tuple_struct(
         self,
         _name: &'static str,
-        _len: usize,
+        len: usize,
     ) -> Result<Self::SerializeTupleStruct, Self::Error> {
-        todo!()
+        self.serialize_seq(Some(len))
     }
 
     fn serialize_tuple_variant(
         self,
         _name: &'static str,
         _variant_index: u32,
-        _variant: &'static str,
-        _len: usize,
+        variant: &'static str,
+        len: usize,
     ) -> Result<Self::SerializeTupleVariant, Self::Error> {
-        todo!()
+        Ok(SerializeTupleVariant {
+            variant,
+            vec: SerializeVec::new(self.open()?, Some(len))?,
+        })
     }
 
     fn serialize_map(self, _len: Option<usize>) -> Result<Self::SerializeMap, Self::Error> {
-        Ok(SerializeMap::new())
+        SerializeMap::new(self)
     }
 
     fn serialize_struct(
@@ -213,15 +288,81 @@ impl serde::Serializer for ValueSerializer {
         self,
         _name: &'static str,
         _variant_index: u32,
-        _variant: &'static str,
+        variant: &'static str,
         _len: usize,
     ) -> Result<Self::SerializeStructVariant, Self::Error> {
-        todo!()
+        Ok(SerializeStructVariant {
+            variant,
+            map: SerializeMap::new(self.open()?)?,
+        })
     }
 }
 
 pub struct SerializeVec {
+    /// Serializer of the list itself, opened again for each element
+    serializer: ValueSerializer,
     vec: Vec<Value>,
+    /// Elements collected so far while every one of them has been a `u8`.
+    ///
+    /// Only used when the serializer turns `u8` sequences into byte strings.
+    bytes: Option<Vec<u8>>,
+}
+
+impl SerializeVec {
+    fn new(serializer: ValueSerializer, len: Option<usize>) -> Result<Self, Error> {
+        let capacity = len.unwrap_or(0);
+
+        if serializer.options.u8_seq_as_bytes {
+            Ok(Self {
+                serializer,
+                vec: Vec::new(),
+                bytes: Some(Vec::with_capacity(capacity)),
+            })
+        } else {
+            serializer.open()?;
+
+            Ok(Self {
+                serializer,
+                vec: Vec::with_capacity(capacity),
+                bytes: None,
+            })
+        }
+    }
+
+    fn push<T>(&mut self, value: &T) -> Result<(), Error>
+    where
+        T: ?Sized + Serialize,
+    {
+        if self.serializer.options.skip_none_in_seq && is_none(value) {
+            return Ok(());
+        }
+
+        if let Some(bytes) = &mut self.bytes {
+            if let Ok(byte) = value.serialize(ByteSerializer) {
+                bytes.push(byte);
+                return Ok(());
+            }
+
+            // Not a byte sequence after all, turn the collected bytes into integers
+            self.vec = self
+                .bytes
+                .take()
+                .unwrap_or_default()
+                .into_iter()
+                .map(|byte| Value::Integer(u64::from(byte).into()))
+                .collect();
+        }
+
+        self.vec.push(value.serialize(self.serializer.open()?)?);
+        Ok(())
+    }
+
+    fn finish(self) -> Value {
+        match self.bytes {
+            Some(bytes) => Value::ByteString(ByteString::from_vec(bytes)),
+            None => Value::List(self.vec),
+        }
+    }
 }
 
 impl serde::ser::SerializeSeq for SerializeVec {
@@ -232,23 +373,109 @@ impl serde::ser::SerializeSeq for SerializeVec {
     where
         T: ?Sized + Serialize,
     {
-        self.vec.push(to_value(value)?);
-        Ok(())
+        self.push(value)
+    }
+
+    fn end(self) -> Result<Value, Error> {
+        Ok(self.finish())
+    }
+}
+
+impl serde::ser::SerializeTuple for SerializeVec {
+    type Ok = Value;
+    type Error = Error;
+
+    fn serialize_element<T>(&mut self, value: &T) -> Result<(), Error>
+    where
+        T: ?Sized + Serialize,
+    {
+        self.push(value)
+    }
+
+    fn end(self) -> Result<Value, Error> {
+        Ok(self.finish())
+    }
+}
+
+impl serde::ser::SerializeTupleStruct for SerializeVec {
+    type Ok = Value;
+    type Error = Error;
+
+    fn serialize_field<T>(&mut self, value: &T) -> Result<(), Error>
+    where
+        T: ?Sized + Serialize,
+    {
+        self.push(value)
+    }
+
+    fn end(self) -> Result<Value, Error> {
+        Ok(self.finish())
+    }
+}
+
+pub struct SerializeTupleVariant {
+    variant: &'static str,
+    vec: SerializeVec,
+}
+
+impl serde::ser::SerializeTupleVariant for SerializeTupleVariant {
+    type Ok = Value;
+    type Error = Error;
+
+    fn serialize_field<T>(&mut self, value: &T) -> Result<(), Error>
+    where
+        T: ?Sized + Serialize,
+    {
+        self.vec.push(value)
     }
 
     fn end(self) -> Result<Value, Error> {
-        Ok(Value::List(self.vec))
+        let serializer = self.vec.serializer;
+        Ok(serializer.variant(self.variant, self.vec.finish()))
     }
 }
 
 pub struct SerializeMap {
+    /// Serializer of the values of the dictionary
+    serializer: ValueSerializer,
     dictionary: Dictionary,
+    /// Key passed to `serialize_key`, held back until we know its value isn't None.
+    pending_key: Option<ByteString>,
+    /// Only used when verifying key order
+    keys: KeyOrderCheck,
 }
 
 impl SerializeMap {
-    pub const fn new() -> Self {
-        Self {
+    pub fn new(serializer: ValueSerializer) -> Result<Self, Error> {
+        Ok(Self {
+            serializer: serializer.open()?,
             dictionary: BTreeMap::new(),
+            pending_key: None,
+            keys: KeyOrderCheck::default(),
+        })
+    }
+
+    fn insert<T>(&mut self, key: ByteString, value: &T) -> Result<(), Error>
+    where
+        T: ?Sized + Serialize,
+    {
+        // Bencode has no null value, None entries are left out
+        if is_none(value) {
+            return Ok(());
+        }
+
+        if self.serializer.options.key_order == KeyOrder::Verify {
+            self.keys.check(&key)?;
+        }
+
+        let value = value.serialize(self.serializer)?;
+
+        match self.dictionary.entry(key) {
+            Entry::Vacant(entry) => {
+                entry.insert(value);
+                Ok(())
+            }
+            Entry::Occupied(entry) => Err(Error::DuplicateKey(entry.key().clone())),
         }
     }
 }
@@ -258,35 +485,29 @@ impl serde::ser::SerializeMap for SerializeMap {
 
     type Error = Error;
 
-    fn serialize_key<T: ?Sized>(&mut self, _key: &T) -> Result<(), Error>
+    fn serialize_key<T>(&mut self, key: &T) -> Result<(), Error>
     where
-        T: Serialize,
+        T: ?Sized + Serialize,
     {
-        unreachable!()
+        self.pending_key = Some(key.serialize(MapKeySerializer::new())?);
+        Ok(())
     }
 
-    fn serialize_value<T: ?Sized>(&mut self, _value: &T) -> Result<(), Error>
+    fn serialize_value<T>(&mut self, value: &T) -> Result<(), Error>
     where
-        T: Serialize,
+        T: ?Sized + Serialize,
     {
-        unreachable!()
+        let key = self.pending_key.take().ok_or(Error::Syntax)?;
+        self.insert(key, value)
     }
 
-    fn serialize_entry<K: ?Sized, V: ?Sized>(
-        &mut self,
-        key: &K,
-        value: &V,
-    ) -> Result<(), Self::Error>
+    fn serialize_entry<K, V>(&mut self, key: &K, value: &V) -> Result<(), Self::Error>
     where
-        K: Serialize,
-        V: Serialize,
+        K: ?Sized + Serialize,
+        V: ?Sized + Serialize,
     {
         let key = key.serialize(MapKeySerializer::new())?;
-        let value = value.serialize(ValueSerializer::new())?;
-
-        self.dictionary.insert(key, value);
-
-        Ok(())
+        self.insert(key, value)
     }
 
     fn end(self) -> Result<Self::Ok, Error> {
@@ -299,23 +520,37 @@ impl serde::ser::SerializeStruct for SerializeMap {
 
     type Error = Error;
 
-    fn serialize_field<T: ?Sized>(
-        &mut self,
-        key: &'static str,
-        value: &T,
-    ) -> Result<(), Self::Error>
+    fn serialize_field<T>(&mut self, key: &'static str, value: &T) -> Result<(), Self::Error>
     where
-        T: Serialize,
+        T: ?Sized + Serialize,
     {
-        let key = key.serialize(MapKeySerializer::new())?;
-        let value = value.serialize(ValueSerializer::new())?;
+        self.insert(ByteString::from(key), value)
+    }
+
+    fn end(self) -> Result<Self::Ok, Self::Error> {
+        Ok(Value::Dictionary(self.dictionary))
+    }
+}
 
-        self.dictionary.insert(key, value);
+pub struct SerializeStructVariant {
+    variant: &'static str,
+    map: SerializeMap,
+}
 
-        Ok(())
+impl serde::ser::SerializeStructVariant for SerializeStructVariant {
+    type Ok = Value;
+
+    type Error = Error;
+
+    fn serialize_field<T>(&mut self, key: &'static str, value: &T) -> Result<(), Self::Error>
+    where
+        T: ?Sized + Serialize,
+    {
+        self.map.insert(ByteString::from(key), value)
     }
 
     fn end(self) -> Result<Self::Ok, Self::Error> {
-        Ok(Value::Dictionary(self.dictionary))
+        let serializer = self.map.serializer;
+        Ok(serializer.variant(self.variant, Value::Dictionary(self.map.dictionary)))
     }
 }
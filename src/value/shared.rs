@@ -0,0 +1,142 @@
+use alloc::{collections::BTreeMap, string::String, vec::Vec};
+use bytes::Bytes;
+use core::fmt::Debug;
+use serde::Serialize;
+
+use crate::{from_bytes, BorrowedValue, ByteString, Integer, Result, Value};
+
+/// Like [`Value`] but byte strings are [`Bytes`] slices sharing the input buffer.
+///
+/// Created by [`from_bytes_shared`].
+#[derive(Clone, PartialEq, Eq, PartialOrd, Ord)]
+pub enum SharedValue {
+    /// Represents a bencode byte string.
+    ByteString(Bytes),
+    /// Represents a bencode integer.
+    Integer(Integer),
+    /// Represents a bencode list.
+    List(Vec<SharedValue>),
+    /// Represents a bencode dictionary.
+    Dictionary(BTreeMap<Bytes, SharedValue>),
+}
+
+impl SharedValue {
+    /// Turns a value borrowed from `input` into slices of it
+    fn from_borrowed(input: &Bytes, value: BorrowedValue<'_>) -> Self {
+        match value {
+            BorrowedValue::ByteString(byte_string) => {
+                Self::ByteString(input.slice_ref(byte_string))
+            }
+            BorrowedValue::Integer(integer) => Self::Integer(integer),
+            BorrowedValue::List(list) => Self::List(
+                list.into_iter()
+                    .map(|value| Self::from_borrowed(input, value))
+                    .collect(),
+            ),
+            BorrowedValue::Dictionary(dictionary) => Self::Dictionary(
+                dictionary
+                    .into_iter()
+                    .map(|(key, value)| (input.slice_ref(key), Self::from_borrowed(input, value)))
+                    .collect(),
+            ),
+        }
+    }
+}
+
+impl Debug for SharedValue {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        match self {
+            Self::ByteString(value) => f
+                .debug_tuple("ByteString")
+                .field(&String::from_utf8_lossy(value))
+                .finish(),
+            Self::Integer(value) => Debug::fmt(value, f),
+            Self::List(value) => {
+                f.write_str("List(")?;
+                Debug::fmt(value, f)?;
+                f.write_str(")")
+            }
+            Self::Dictionary(value) => {
+                f.write_str("Dictionary(")?;
+                f.debug_map()
+                    .entries(
+                        value
+                            .iter()
+                            .map(|(key, value)| (String::from_utf8_lossy(key), value)),
+                    )
+                    .finish()?;
+                f.write_str(")")
+            }
+        }
+    }
+}
+
+impl From<SharedValue> for Value {
+    fn from(value: SharedValue) -> Self {
+        match value {
+            SharedValue::ByteString(byte_string) => {
+                Value::ByteString(ByteString::from(byte_string))
+            }
+            SharedValue::Integer(integer) => Value::Integer(integer),
+            SharedValue::List(list) => Value::List(list.into_iter().map(Value::from).collect()),
+            SharedValue::Dictionary(dictionary) => Value::Dictionary(
+                dictionary
+                    .into_iter()
+                    .map(|(key, value)| (ByteString::from(key), Value::from(value)))
+                    .collect(),
+            ),
+        }
+    }
+}
+
+impl Serialize for SharedValue {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: serde::Serializer,
+    {
+        match self {
+            Self::ByteString(byte_string) => serializer.serialize_bytes(byte_string),
+            Self::Integer(integer) => integer.serialize(serializer),
+            Self::List(list) => list.serialize(serializer),
+            Self::Dictionary(dictionary) => serializer.collect_map(
+                dictionary
+                    .iter()
+                    .map(|(key, value)| (BorrowedValue::ByteString(key), value)),
+            ),
+        }
+    }
+}
+
+/// Deserializes a [`SharedValue`] whose byte strings are zero-copy slices of the input.
+///
+/// # Errors
+/// See [`from_bytes`].
+pub fn from_bytes_shared(bytes: &Bytes) -> Result<SharedValue> {
+    let value: BorrowedValue = from_bytes(bytes)?;
+    Ok(SharedValue::from_borrowed(bytes, value))
+}
+
+#[cfg(test)]
+mod tests {
+    use bytes::Bytes;
+
+    use super::{from_bytes_shared, SharedValue};
+    use crate::{from_bytes, to_bytes, Value};
+
+    #[test]
+    fn shares_the_input() {
+        let bytes = Bytes::from_static(include_bytes!("../../tests/torrents/lubuntu.torrent"));
+        let value = from_bytes_shared(&bytes).unwrap();
+
+        let SharedValue::Dictionary(dictionary) = &value else {
+            panic!("torrent is not a dictionary")
+        };
+        let Some(SharedValue::ByteString(announce)) = dictionary.get(&b"announce"[..]) else {
+            panic!("torrent has no announce")
+        };
+        assert!(bytes.as_ptr_range().contains(&announce.as_ptr()));
+
+        assert_eq!(bytes.to_vec(), to_bytes(&value).unwrap());
+        assert_eq!(from_bytes::<Value>(&bytes).unwrap(), Value::from(value));
+    }
+}